@@ -235,7 +235,7 @@ test.describe('Group 2: The Play — Write Operations', () => {
       return resp;
     });
 
-    expect(updateResult.result.scene.stage).toBe('in_progress');
+    expect(updateResult.scene.stage).toBe('in_progress');
 
     // Verify the state persists — a subsequent read reflects the change
     const readResult = await page.evaluate(async () => {
@@ -243,7 +243,7 @@ test.describe('Group 2: The Play — Write Operations', () => {
         method: 'play/scenes/list',
         params: { act_id: 'act-e8623a0da3ca' },
       });
-      return resp.result.scenes;
+      return resp.scenes;
     });
 
     const arb = readResult.find((s) => s.scene_id === 'scene-ddd1c212f3d4');
@@ -270,7 +270,7 @@ test.describe('Group 2: The Play — Write Operations', () => {
       return resp;
     });
 
-    expect(writeResult.result.ok).toBe(true);
+    expect(writeResult.ok).toBe(true);
 
     // Read it back — should reflect the write
     const readResult = await page.evaluate(async () => {
@@ -278,7 +278,7 @@ test.describe('Group 2: The Play — Write Operations', () => {
         method: 'play/kb/read',
         params: { act_id: 'act-e8623a0da3ca', path: 'kb.md' },
       });
-      return resp.result.text;
+      return resp.text;
     });
 
     expect(readResult).toContain('Updated Career Growth');
@@ -320,7 +320,7 @@ test.describe('Group 3: Attention System', () => {
         method: 'cairn/attention',
         params: { hours: 168, limit: 50 },
       });
-      return resp.result;
+      return resp;
     });
 
     expect(attentionResult.count).toBe(5);
@@ -339,7 +339,7 @@ test.describe('Group 3: Attention System', () => {
         method: 'cairn/attention',
         params: { hours: 168, limit: 50 },
       });
-      return resp.result.items;
+      return resp.items;
     });
 
     const migration = attentionResult.find((i) => i.title === 'Q2 Platform Migration');
@@ -362,7 +362,7 @@ test.describe('Group 3: Attention System', () => {
         method: 'cairn/attention',
         params: {},
       });
-      return resp.result.items.map((i) => i.scene_id);
+      return resp.items.map((i) => i.scene_id);
     });
 
     // Initial order: job-search first (urgency 0.8), then migration (urgency 0.9 seed order)
@@ -382,7 +382,7 @@ test.describe('Group 3: Attention System', () => {
           ],
         },
       });
-      return resp.result;
+      return resp;
     });
 
     expect(reorderResult.ok).toBe(true);
@@ -393,7 +393,7 @@ test.describe('Group 3: Attention System', () => {
         method: 'cairn/attention',
         params: {},
       });
-      return resp.result.items.map((i) => i.scene_id);
+      return resp.items.map((i) => i.scene_id);
     });
 
     // Q2 Platform Migration should now be first
@@ -446,7 +446,7 @@ test.describe('Group 4: Chat Interface', () => {
         method: 'lifecycle/conversations/get_active',
         params: {},
       });
-      return resp.result.conversation;
+      return resp.conversation;
     });
     expect(beforeConv).toBeNull();
 
@@ -456,7 +456,7 @@ test.describe('Group 4: Chat Interface', () => {
         method: 'lifecycle/conversations/start',
         params: {},
       });
-      return resp.result.conversation;
+      return resp.conversation;
     });
 
     expect(startResult).toBeTruthy();
@@ -469,7 +469,7 @@ test.describe('Group 4: Chat Interface', () => {
         method: 'lifecycle/conversations/start',
         params: {},
       });
-      return resp.result.conversation;
+      return resp.conversation;
     });
     expect(start2Result.conversation_id).toBe(startResult.conversation_id);
   });
@@ -489,7 +489,7 @@ test.describe('Group 4: Chat Interface', () => {
         method: 'lifecycle/conversations/get_active',
         params: {},
       });
-      return resp.result.conversation;
+      return resp.conversation;
     });
     expect(active).toBeTruthy();
     expect(active.status).toBe('active');
@@ -508,7 +508,7 @@ test.describe('Group 4: Chat Interface', () => {
         method: 'lifecycle/conversations/get_active',
         params: {},
       });
-      return resp.result.conversation;
+      return resp.conversation;
     });
     expect(afterClose).toBeNull();
   });
@@ -520,7 +520,7 @@ test.describe('Group 4: Chat Interface', () => {
         method: 'cairn/chat_async',
         params: { text: 'Hello CAIRN', conversation_id: null },
       });
-      return resp.result;
+      return resp;
     });
 
     expect(asyncResult.chat_id).toMatch(/^mock-chat-/);
@@ -532,12 +532,12 @@ test.describe('Group 4: Chat Interface', () => {
         method: 'cairn/chat_status',
         params: { chat_id: chatId },
       });
-      return resp.result;
+      return resp;
     }, asyncResult.chat_id);
 
     expect(statusResult.status).toBe('complete');
-    expect(statusResult.result.answer).toContain('recommend focusing');
-    expect(statusResult.result.conversation_id).toMatch(/^mock-conv-/);
+    expect(statusResult.answer).toContain('recommend focusing');
+    expect(statusResult.conversation_id).toMatch(/^mock-conv-/);
   });
 });
 
@@ -557,7 +557,7 @@ test.describe('Group 5: Memory Review', () => {
         method: 'lifecycle/memories/pending',
         params: {},
       });
-      return resp.result.memories;
+      return resp.memories;
     });
 
     // Seed data has 3 pending_review memories
@@ -578,7 +578,7 @@ test.describe('Group 5: Memory Review', () => {
         method: 'lifecycle/memories/pending',
         params: {},
       });
-      return resp.result.memories.find((m) => m.memory_id === 'mem-004');
+      return resp.memories.find((m) => m.memory_id === 'mem-004');
     });
     expect(before.status).toBe('pending_review');
 
@@ -588,7 +588,7 @@ test.describe('Group 5: Memory Review', () => {
         method: 'lifecycle/memories/approve',
         params: { memory_id: 'mem-004' },
       });
-      return resp.result;
+      return resp;
     });
     expect(approveResult.ok).toBe(true);
 
@@ -598,7 +598,7 @@ test.describe('Group 5: Memory Review', () => {
         method: 'lifecycle/memories/pending',
         params: {},
       });
-      return resp.result.memories.map((m) => m.memory_id);
+      return resp.memories.map((m) => m.memory_id);
     });
     expect(pendingAfter).not.toContain('mem-004');
 
@@ -608,7 +608,7 @@ test.describe('Group 5: Memory Review', () => {
         method: 'memories/list',
         params: {},
       });
-      return resp.result.memories.map((m) => m.memory_id);
+      return resp.memories.map((m) => m.memory_id);
     });
     expect(approvedList).toContain('mem-004');
   });
@@ -620,7 +620,7 @@ test.describe('Group 5: Memory Review', () => {
         method: 'lifecycle/memories/reject',
         params: { memory_id: 'mem-005' },
       });
-      return resp.result;
+      return resp;
     });
     expect(rejectResult.ok).toBe(true);
 
@@ -630,7 +630,7 @@ test.describe('Group 5: Memory Review', () => {
         method: 'lifecycle/memories/pending',
         params: {},
       });
-      return resp.result.memories.map((m) => m.memory_id);
+      return resp.memories.map((m) => m.memory_id);
     });
     expect(pendingAfter).not.toContain('mem-005');
 
@@ -640,7 +640,7 @@ test.describe('Group 5: Memory Review', () => {
         method: 'memories/list',
         params: {},
       });
-      return resp.result.memories.map((m) => m.memory_id);
+      return resp.memories.map((m) => m.memory_id);
     });
     expect(approvedList).not.toContain('mem-005');
   });
@@ -651,7 +651,7 @@ test.describe('Group 5: Memory Review', () => {
         method: 'memories/list',
         params: {},
       });
-      return resp.result.memories;
+      return resp.memories;
     });
 
     // Seed has 5 approved memories (mem-001, 002, 003, 007, 008)
@@ -698,7 +698,7 @@ test.describe('Group 6: Context Meter', () => {
         method: 'context/stats',
         params: {},
       });
-      return resp.result;
+      return resp;
     });
 
     expect(stats.usage_percent).toBeCloseTo(34.8, 0);
@@ -844,15 +844,15 @@ test.describe('Group 8: Error Resilience', () => {
         });
         return { ok: true, resp };
       } catch (e) {
-        return { ok: false, error: e.message };
+        return { ok: false, error: e };
       }
     });
 
-    // Mock returns a JSON-RPC error object, not a thrown exception
+    // kernel_request rejects with a structured CommandError
     // The app-level UI should still be functional
-    expect(result.ok).toBe(true);  // invoke didn't throw
-    expect(result.resp.error).toBeTruthy();
-    expect(result.resp.error.code).toBe(-32601);
+    expect(result.ok).toBe(false);
+    expect(result.error.kind).toBe('rpc');
+    expect(result.error.code).toBe(-32601);
 
     // UI is still alive
     await expect(shell).toBeVisible();
@@ -869,7 +869,7 @@ test.describe('Group 8: Error Resilience', () => {
         method: 'play/acts/create',
         params: { title: 'Empty Test Act' },
       });
-      return resp.result.act;
+      return resp.act;
     });
 
     expect(newAct.act_id).toMatch(/^act-/);
@@ -880,7 +880,7 @@ test.describe('Group 8: Error Resilience', () => {
         method: 'play/scenes/list',
         params: { act_id: actId },
       });
-      return resp.result.scenes;
+      return resp.scenes;
     }, newAct.act_id);
 
     expect(scenes.length).toBe(0);
@@ -914,7 +914,7 @@ test.describe('Group 8: Error Resilience', () => {
         method: 'play/acts/list',
         params: {},
       });
-      return resp.result.acts.length;
+      return resp.acts.length;
     });
     expect(before).toBe(4);  // seed has 4 acts
 
@@ -935,7 +935,7 @@ test.describe('Group 8: Error Resilience', () => {
         method: 'play/acts/list',
         params: {},
       });
-      return resp.result.acts;
+      return resp.acts;
     });
 
     expect(after.length).toBe(6);
@@ -953,7 +953,7 @@ test.describe('Group 8: Error Resilience', () => {
         method: 'play/scenes/create',
         params: { act_id: actId, title: 'New Health Scene', stage: 'planning' },
       });
-      return resp.result.scene;
+      return resp.scene;
     }, actId);
 
     expect(created.scene_id).toMatch(/^scene-/);
@@ -974,7 +974,7 @@ test.describe('Group 8: Error Resilience', () => {
         method: 'play/scenes/list',
         params: { act_id: actId },
       });
-      return resp.result.scenes.find((s) => s.scene_id === sceneId);
+      return resp.scenes.find((s) => s.scene_id === sceneId);
     }, { actId, sceneId: created.scene_id });
 
     expect(afterUpdate.stage).toBe('in_progress');
@@ -993,7 +993,7 @@ test.describe('Group 8: Error Resilience', () => {
         method: 'play/scenes/list',
         params: { act_id: actId },
       });
-      return resp.result.scenes.find((s) => s.scene_id === sceneId);
+      return resp.scenes.find((s) => s.scene_id === sceneId);
     }, { actId, sceneId: created.scene_id });
 
     expect(afterDelete).toBeUndefined();
@@ -1004,7 +1004,7 @@ test.describe('Group 8: Error Resilience', () => {
         method: 'play/scenes/list',
         params: { act_id: actId },
       });
-      return resp.result.scenes.length;
+      return resp.scenes.length;
     }, actId);
 
     expect(finalCount).toBe(3);
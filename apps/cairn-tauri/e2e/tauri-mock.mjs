@@ -460,6 +460,19 @@ function getMockScript() {
     return { jsonrpc: '2.0', id: 1, error: { code: code, message: message } };
   }
 
+  // Like the Rust command: resolve with the result, reject with a CommandError.
+  function unwrapEnvelope(envelope) {
+    if (envelope.error) {
+      throw {
+        kind: 'rpc',
+        message: envelope.error.message,
+        code: envelope.error.code,
+        data: envelope.error.data === undefined ? null : envelope.error.data,
+      };
+    }
+    return envelope.result;
+  }
+
   // -------------------------------------------------------------------------
   // kernel_request dispatch
   // -------------------------------------------------------------------------
@@ -974,7 +987,7 @@ function getMockScript() {
       if (cmd === 'kernel_request') {
         const method = (args && args.method) || '';
         const params = (args && args.params) || {};
-        return dispatchKernelRequest(method, params);
+        return unwrapEnvelope(dispatchKernelRequest(method, params));
       }
 
       // ---- PTY (ReOS terminal) — not available in test environment ----
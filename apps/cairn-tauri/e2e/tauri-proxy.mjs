@@ -112,30 +112,31 @@ function getProxyScript() {
           if (!resp.ok) {
             var errText = await resp.text().catch(function () { return '(no body)'; });
             console.error('[tauri-proxy] HTTP error', resp.status, method, errText);
-            return {
-              jsonrpc: '2.0',
-              id: 1,
-              error: {
-                code: -32603,
-                message: 'HTTP ' + resp.status + ': ' + errText.substring(0, 200),
-              },
+            throw {
+              kind: 'rpc',
+              message: 'HTTP ' + resp.status + ': ' + errText.substring(0, 200),
+              code: -32603,
+              data: null,
             };
           }
 
           var json = await resp.json();
           console.log('[tauri-proxy]', method, '->', JSON.stringify(json).substring(0, 200));
-          return json;
+          // Like the Rust command: resolve with the result, reject with a CommandError.
+          if (json.error) {
+            throw {
+              kind: 'rpc',
+              message: json.error.message,
+              code: json.error.code,
+              data: json.error.data === undefined ? null : json.error.data,
+            };
+          }
+          return json.result;
 
         } catch (e) {
+          if (e && e.kind) throw e;
           console.error('[tauri-proxy] fetch failed:', method, String(e));
-          return {
-            jsonrpc: '2.0',
-            id: 1,
-            error: {
-              code: -32603,
-              message: String(e),
-            },
-          };
+          throw { kind: 'kernel', message: String(e), reason: 'exited' };
         }
       }
 
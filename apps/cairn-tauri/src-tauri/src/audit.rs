@@ -0,0 +1,472 @@
+//! Append-only, hash-chained audit log of kernel calls.
+//!
+//! `kernel_request` injects `__session` so the kernel can audit, but the
+//! kernel is the less trusted side. The shell keeps its own record: one JSON
+//! line per call in `<app data dir>/audit/kernel-audit.jsonl` with who called
+//! what, a SHA-256 digest of the params (not the params themselves), the
+//! outcome, latency, and the kernel restart epoch that served it.
+//!
+//! Every entry carries `prev`, the hash of the entry before it, and `hash`,
+//! the SHA-256 of its own fields including `prev`. Editing or removing an
+//! entry breaks the chain from that point on, which `verify` reports.
+//! Removing entries from the end leaves a valid but shorter chain: within a
+//! run `verify` compares against the head this process last wrote; across
+//! runs, compare against a head recorded out of band (e.g. from an export).
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::{Instant, SystemTime, UNIX_EPOCH};
+
+use crate::auth::SessionInfo;
+use crate::error::CommandError;
+
+/// `prev` of the first entry.
+pub const GENESIS: &str = "0000000000000000000000000000000000000000000000000000000000000000";
+
+const FILE_NAME: &str = "kernel-audit.jsonl";
+
+/// How a call ended.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "status", rename_all = "snake_case")]
+pub enum Outcome {
+    Ok,
+    /// The call was refused or failed; same `kind`, `reason` and `code` as
+    /// the `CommandError` returned to the webview.
+    Error {
+        kind: String,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        reason: Option<String>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        code: Option<i64>,
+    },
+}
+
+impl Outcome {
+    pub fn of<T>(result: &Result<T, CommandError>) -> Self {
+        match result {
+            Ok(_) => Self::Ok,
+            Err(e) => Self::Error {
+                kind: e.kind().to_string(),
+                reason: e.reason().map(str::to_string),
+                code: match e {
+                    CommandError::Rpc { code, .. } => Some(*code),
+                    _ => None,
+                },
+            },
+        }
+    }
+}
+
+/// The hashed fields of an entry.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Record {
+    /// 1 for the first entry, then consecutive.
+    pub seq: u64,
+    pub timestamp_ms: u64,
+    pub session_id: String,
+    pub username: String,
+    pub method: String,
+    /// SHA-256 of the params as sent by the webview, before `__session`.
+    pub params_sha256: String,
+    pub outcome: Outcome,
+    pub latency_ms: u64,
+    /// Restart epoch of the kernel that served the call; `None` if the call
+    /// never reached one (refused by policy, kernel unavailable).
+    pub epoch: Option<u64>,
+    pub prev: String,
+}
+
+impl Record {
+    fn hash(&self) -> String {
+        let json = serde_json::to_string(self).unwrap_or_default();
+        hex::encode(Sha256::digest(json.as_bytes()))
+    }
+}
+
+/// One line of the log.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AuditEntry {
+    #[serde(flatten)]
+    pub record: Record,
+    pub hash: String,
+}
+
+/// Result of `AuditLog::verify`.
+#[derive(Debug, Clone, Serialize)]
+pub struct Verification {
+    pub intact: bool,
+    /// Entries read, up to the first problem.
+    pub entries: u64,
+    /// `seq` and `hash` of the last good entry. Record these to detect
+    /// entries later removed from the end.
+    pub head_seq: u64,
+    pub head_hash: String,
+    pub problem: Option<ChainBreak>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ChainBreak {
+    /// 1-based line number in the file; one past the end for truncation.
+    pub line: usize,
+    pub reason: String,
+}
+
+struct Chain {
+    path: Option<PathBuf>,
+    file: Option<File>,
+    /// `seq` and `hash` of the last entry appended or found on open.
+    seq: u64,
+    head: String,
+}
+
+/// The audit log. Entries are held only in the file; until `set_dir` is
+/// called, calls are chained in memory but not persisted.
+pub struct AuditLog {
+    inner: Mutex<Chain>,
+}
+
+impl AuditLog {
+    pub fn new() -> Self {
+        Self {
+            inner: Mutex::new(Chain {
+                path: None,
+                file: None,
+                seq: 0,
+                head: GENESIS.to_string(),
+            }),
+        }
+    }
+
+    /// Append to `<dir>/kernel-audit.jsonl`, continuing the chain already
+    /// in it.
+    ///
+    /// A final line without its newline was torn by a crash mid-write; the
+    /// entry never finished, so it is cut off rather than left to break the
+    /// chain for good.
+    pub fn set_dir(&self, dir: &Path) -> io::Result<()> {
+        fs::create_dir_all(dir)?;
+        let path = dir.join(FILE_NAME);
+        let file = open_append(&path)?;
+
+        let (mut seq, mut head) = (0, GENESIS.to_string());
+        let mut reader = BufReader::new(File::open(&path)?);
+        let mut line = String::new();
+        // Length of the file up to the end of its last complete line.
+        let mut complete = 0;
+        while reader.read_line(&mut line)? > 0 {
+            if !line.ends_with('\n') {
+                eprintln!("[audit] dropping torn entry of {} bytes", line.len());
+                file.set_len(complete)?;
+                break;
+            }
+            complete += line.len() as u64;
+            if let Ok(entry) = serde_json::from_str::<AuditEntry>(line.trim_end()) {
+                seq = entry.record.seq;
+                head = entry.hash;
+            }
+            line.clear();
+        }
+
+        if let Ok(mut chain) = self.inner.lock() {
+            *chain = Chain {
+                path: Some(path),
+                file: Some(file),
+                seq,
+                head,
+            };
+        }
+        Ok(())
+    }
+
+    /// Start timing a call by `session` to `method`. Finish it with
+    /// `PendingCall::finish` once the outcome is known.
+    pub fn begin(
+        self: &Arc<Self>,
+        session: &SessionInfo,
+        method: &str,
+        params: &Value,
+    ) -> PendingCall {
+        let params_json = serde_json::to_string(params).unwrap_or_default();
+        PendingCall {
+            log: self.clone(),
+            session_id: session.session_id.clone(),
+            username: session.username.clone(),
+            method: method.to_string(),
+            params_sha256: hex::encode(Sha256::digest(params_json.as_bytes())),
+            started: Instant::now(),
+            epoch: None,
+        }
+    }
+
+    fn append(&self, call: PendingCall, outcome: Outcome) {
+        let Ok(mut chain) = self.inner.lock() else {
+            return;
+        };
+        let record = Record {
+            seq: chain.seq + 1,
+            timestamp_ms: now_ms(),
+            session_id: call.session_id,
+            username: call.username,
+            method: call.method,
+            params_sha256: call.params_sha256,
+            outcome,
+            latency_ms: call.started.elapsed().as_millis() as u64,
+            epoch: call.epoch,
+            prev: chain.head.clone(),
+        };
+        let entry = AuditEntry {
+            hash: record.hash(),
+            record,
+        };
+        chain.seq = entry.record.seq;
+        chain.head = entry.hash.clone();
+
+        if let Some(file) = chain.file.as_mut() {
+            let mut line = serde_json::to_string(&entry).unwrap_or_default();
+            line.push('\n');
+            if let Err(e) = file.write_all(line.as_bytes()) {
+                eprintln!("[audit] write failed, disabling: {e}");
+                chain.file = None;
+            }
+        }
+    }
+
+    /// Walk the file and check every link, then that nothing is missing
+    /// from the end relative to what this process last wrote.
+    pub fn verify(&self) -> io::Result<Verification> {
+        let (path, seq, head) = self.snapshot()?;
+        let mut verification = verify_chain(BufReader::new(File::open(path)?))?;
+        if verification.intact && (verification.head_seq, &verification.head_hash) != (seq, &head) {
+            verification.intact = false;
+            verification.problem = Some(ChainBreak {
+                line: verification.entries as usize + 1,
+                reason: format!(
+                    "log ends at seq {} but seq {seq} was written",
+                    verification.head_seq
+                ),
+            });
+        }
+        Ok(verification)
+    }
+
+    /// The raw log, for archiving or verifying elsewhere.
+    pub fn export(&self) -> io::Result<String> {
+        let (path, _, _) = self.snapshot()?;
+        let mut contents = String::new();
+        File::open(path)?.read_to_string(&mut contents)?;
+        Ok(contents)
+    }
+
+    fn snapshot(&self) -> io::Result<(PathBuf, u64, String)> {
+        let chain = self
+            .inner
+            .lock()
+            .map_err(|_| io::Error::other("audit log lock poisoned"))?;
+        let path = chain
+            .path
+            .clone()
+            .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "audit log has no file"))?;
+        Ok((path, chain.seq, chain.head.clone()))
+    }
+}
+
+/// A call in flight, recorded by `finish`.
+pub struct PendingCall {
+    log: Arc<AuditLog>,
+    session_id: String,
+    username: String,
+    method: String,
+    params_sha256: String,
+    started: Instant,
+    /// Set once the call has been handed to a kernel.
+    pub epoch: Option<u64>,
+}
+
+impl PendingCall {
+    /// Record the call's outcome and pass `result` through.
+    pub fn finish<T>(self, result: Result<T, CommandError>) -> Result<T, CommandError> {
+        let log = self.log.clone();
+        log.append(self, Outcome::of(&result));
+        result
+    }
+}
+
+/// Check `seq` continuity, `prev` links and hashes line by line, stopping at
+/// the first problem.
+pub fn verify_chain(reader: impl BufRead) -> io::Result<Verification> {
+    let mut verification = Verification {
+        intact: true,
+        entries: 0,
+        head_seq: 0,
+        head_hash: GENESIS.to_string(),
+        problem: None,
+    };
+    for (index, line) in reader.lines().enumerate() {
+        let line = line?;
+        let problem = match serde_json::from_str::<AuditEntry>(&line) {
+            Err(e) => Some(format!("unreadable entry: {e}")),
+            Ok(entry) if entry.record.seq != verification.head_seq + 1 => Some(format!(
+                "expected seq {}, found {}",
+                verification.head_seq + 1,
+                entry.record.seq
+            )),
+            Ok(entry) if entry.record.prev != verification.head_hash => Some(format!(
+                "seq {} does not link to the entry before it",
+                entry.record.seq
+            )),
+            Ok(entry) if entry.record.hash() != entry.hash => {
+                Some(format!("seq {} was modified", entry.record.seq))
+            }
+            Ok(entry) => {
+                verification.entries += 1;
+                verification.head_seq = entry.record.seq;
+                verification.head_hash = entry.hash;
+                None
+            }
+        };
+        if let Some(reason) = problem {
+            verification.intact = false;
+            verification.problem = Some(ChainBreak {
+                line: index + 1,
+                reason,
+            });
+            break;
+        }
+    }
+    Ok(verification)
+}
+
+fn open_append(path: &Path) -> io::Result<File> {
+    let mut options = OpenOptions::new();
+    options.create(true).append(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+    options.open(path)
+}
+
+fn now_ms() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis() as u64)
+        .unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::policy::PolicyError;
+    use serde_json::json;
+    use std::time::Duration;
+
+    fn session() -> SessionInfo {
+        SessionInfo {
+            username: "alice".to_string(),
+            session_id: "0123456789abcdef".to_string(),
+            age: Duration::ZERO,
+        }
+    }
+
+    fn temp_log(name: &str) -> (Arc<AuditLog>, PathBuf) {
+        let dir = std::env::temp_dir().join(format!("cairn-audit-{name}-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        let log = Arc::new(AuditLog::new());
+        log.set_dir(&dir).unwrap();
+        (log, dir)
+    }
+
+    fn record_calls(log: &Arc<AuditLog>, n: usize) {
+        for i in 0..n {
+            let mut call = log.begin(&session(), "play/acts/list", &json!({ "i": i }));
+            call.epoch = Some(1);
+            let _ = call.finish(Ok::<_, CommandError>(json!({})));
+        }
+    }
+
+    #[test]
+    fn test_chain_records_calls_and_resumes() {
+        let (log, dir) = temp_log("resume");
+        record_calls(&log, 2);
+        let denied = log.begin(&session(), "os/system", &json!({}));
+        let _ = denied.finish::<Value>(Err(PolicyError::UnknownMethod("os/system".into()).into()));
+
+        let verification = log.verify().unwrap();
+        assert!(verification.intact, "{:?}", verification.problem);
+        assert_eq!(verification.entries, 3);
+
+        let text = log.export().unwrap();
+        let last: AuditEntry = serde_json::from_str(text.lines().last().unwrap()).unwrap();
+        assert_eq!(last.record.method, "os/system");
+        assert_eq!(last.record.epoch, None);
+        assert_eq!(
+            last.record.outcome,
+            Outcome::Error {
+                kind: "policy".to_string(),
+                reason: Some("unknown_method".to_string()),
+                code: None,
+            }
+        );
+
+        // A new process continues the same chain.
+        let reopened = Arc::new(AuditLog::new());
+        reopened.set_dir(&dir).unwrap();
+        record_calls(&reopened, 1);
+        let verification = reopened.verify().unwrap();
+        assert!(verification.intact, "{:?}", verification.problem);
+        assert_eq!(verification.head_seq, 4);
+        let _ = fs::remove_dir_all(dir);
+    }
+
+    #[test]
+    fn test_verify_detects_edits_and_removals() {
+        let (log, dir) = temp_log("tamper");
+        record_calls(&log, 4);
+        let path = dir.join(FILE_NAME);
+        let original = fs::read_to_string(&path).unwrap();
+        let lines: Vec<&str> = original.lines().collect();
+
+        let edited = original.replacen("play/acts/list", "play/acts/delete", 2);
+        let result = verify_chain(edited.as_bytes()).unwrap();
+        assert_eq!(result.problem.unwrap().line, 1);
+
+        let removed = [lines[0], lines[2], lines[3]].join("\n");
+        let result = verify_chain(removed.as_bytes()).unwrap();
+        assert!(!result.intact);
+        assert_eq!(result.problem.unwrap().line, 2);
+
+        // Dropping the tail keeps a valid chain, but not the head we wrote.
+        fs::write(&path, format!("{}\n{}\n", lines[0], lines[1])).unwrap();
+        let result = log.verify().unwrap();
+        assert!(!result.intact);
+        assert_eq!(result.entries, 2);
+        assert_eq!(result.problem.unwrap().line, 3);
+        let _ = fs::remove_dir_all(dir);
+    }
+
+    #[test]
+    fn test_torn_tail_is_dropped_on_reopen() {
+        let (log, dir) = temp_log("torn");
+        record_calls(&log, 2);
+        let path = dir.join(FILE_NAME);
+        let written = fs::read_to_string(&path).unwrap();
+        let mut torn = OpenOptions::new().append(true).open(&path).unwrap();
+        torn.write_all(br#"{"seq":3,"timestamp_ms":"#).unwrap();
+        drop(torn);
+
+        let reopened = Arc::new(AuditLog::new());
+        reopened.set_dir(&dir).unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap(), written);
+        record_calls(&reopened, 1);
+        let verification = reopened.verify().unwrap();
+        assert!(verification.intact, "{:?}", verification.problem);
+        assert_eq!(verification.entries, 3);
+        let _ = fs::remove_dir_all(dir);
+    }
+}
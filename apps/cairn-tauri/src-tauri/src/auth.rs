@@ -7,30 +7,207 @@
 //! - Frontend sends credentials to Python kernel via auth/login RPC
 //! - Python validates via PAM, derives encryption key
 //! - Python returns session token to Rust
-//! - Rust stores session token and validates on each request
+//! - Rust stores a keyed hash of the token and validates on each request
 //! - Python handles encrypted storage with the derived key
+//!
+//! The shell never keeps a usable token. `SessionStore` is keyed by an
+//! HMAC-SHA256 of the token under a random per-process key (`TokenHash`),
+//! compared in constant time and zeroized when dropped; the `session_id`
+//! used in logs and events is derived from that hash too. The kernel names
+//! each session it creates with an opaque `session_ref`, returned from
+//! `auth/login`, which the shell passes back to its `auth/*` methods. It is
+//! no credential for anything else.
+//!
+//! The Rust store is authoritative. Each session remembers the kernel
+//! process (epoch) that holds its key; logout, refresh and expiry are passed
+//! on to that kernel, and a session whose kernel has gone (crash, restart,
+//! stop) ends with it, since the key went with the process.
+//!
+//! Session lifetimes come from `<app config dir>/session.json` (see
+//! `SessionPolicy`); every field is optional:
+//!
+//! ```json
+//! { "idle_timeout_secs": 900, "max_lifetime_secs": 43200, "warn_before_secs": 120 }
+//! ```
 
+use hmac::{Hmac, Mac};
 use rand::RngCore;
 use serde::{Deserialize, Serialize};
+use sha2::Sha256;
 use std::collections::HashMap;
+use std::fmt;
+use std::path::Path;
 use std::sync::{Arc, Mutex};
 use std::time::{Duration, Instant};
+use subtle::ConstantTimeEq;
+use zeroize::{Zeroize, Zeroizing};
+
+/// Name of the session policy file inside the app config dir.
+pub const POLICY_FILE_NAME: &str = "session.json";
+
+/// How often the shell sweeps expired sessions out of the store and warns
+/// about ones close to expiry.
+pub const SESSION_REAP_INTERVAL: Duration = Duration::from_secs(30);
+
+/// Sweeps in a row in which the kernel could not be asked about a session
+/// before the shell ends it.
+pub const KERNEL_PROBE_FAILURE_LIMIT: u32 = 3;
+
+/// Event emitted to every window when a session is reaped.
+pub const SESSION_EXPIRED_EVENT: &str = "cairn://session-expired";
+
+/// Event emitted to every window once a session enters its warning window.
+pub const SESSION_EXPIRING_EVENT: &str = "cairn://session-expiring";
+
+/// How long sessions live, from `session.json`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct SessionPolicy {
+    /// Inactivity after which a session expires.
+    pub idle_timeout_secs: u64,
+    /// Age after which a session expires however active it is.
+    pub max_lifetime_secs: u64,
+    /// How long before either cutoff `SESSION_EXPIRING_EVENT` fires. Only
+    /// reliable when longer than `SESSION_REAP_INTERVAL`.
+    pub warn_before_secs: u64,
+}
+
+impl Default for SessionPolicy {
+    fn default() -> Self {
+        Self {
+            idle_timeout_secs: 15 * 60,
+            max_lifetime_secs: 12 * 60 * 60,
+            warn_before_secs: 2 * 60,
+        }
+    }
+}
+
+impl SessionPolicy {
+    /// Read `<config_dir>/session.json`. A missing file is not an error.
+    pub fn load(config_dir: &Path) -> Result<Self, String> {
+        let path = config_dir.join(POLICY_FILE_NAME);
+        match std::fs::read_to_string(&path) {
+            Ok(text) => {
+                serde_json::from_str(&text).map_err(|e| format!("invalid {}: {e}", path.display()))
+            }
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(Self::default()),
+            Err(e) => Err(format!("cannot read {}: {e}", path.display())),
+        }
+    }
+
+    fn idle_timeout(&self) -> Duration {
+        Duration::from_secs(self.idle_timeout_secs)
+    }
+
+    fn max_lifetime(&self) -> Duration {
+        Duration::from_secs(self.max_lifetime_secs)
+    }
+
+    fn warn_before(&self) -> Duration {
+        Duration::from_secs(self.warn_before_secs)
+    }
+}
+
+/// Which limit ends a session.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ExpiryReason {
+    /// No activity for `idle_timeout_secs`; any request extends it.
+    Idle,
+    /// `max_lifetime_secs` since login; only a new login helps.
+    Lifetime,
+    /// The kernel holding the session's key exited or no longer knows it.
+    Kernel,
+}
+
+/// A session token as the store keeps it: HMAC-SHA256 under the store's key.
+///
+/// Equality is constant time; the bytes are zeroized on drop and never
+/// printed.
+#[derive(Clone)]
+pub struct TokenHash([u8; 32]);
+
+impl TokenHash {
+    /// Short id for logs and events. Derived from the keyed hash, so it says
+    /// nothing about the token.
+    pub fn session_id(&self) -> String {
+        hex::encode(&self.0[..8])
+    }
+}
+
+impl PartialEq for TokenHash {
+    fn eq(&self, other: &Self) -> bool {
+        self.0.ct_eq(&other.0).into()
+    }
+}
+
+impl Eq for TokenHash {}
 
-/// Session idle timeout (15 minutes)
-const SESSION_IDLE_TIMEOUT: Duration = Duration::from_secs(15 * 60);
+impl std::hash::Hash for TokenHash {
+    fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+        self.0.hash(state);
+    }
+}
+
+impl Drop for TokenHash {
+    fn drop(&mut self) {
+        self.0.zeroize();
+    }
+}
+
+impl fmt::Debug for TokenHash {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("TokenHash(..)")
+    }
+}
+
+/// The kernel's side of a session.
+#[derive(Clone)]
+pub struct KernelSession {
+    /// Epoch of the kernel process that holds the session's key.
+    pub epoch: u64,
+    /// What that kernel's `auth/*` methods know the session by.
+    pub session_ref: String,
+}
+
+impl Drop for KernelSession {
+    fn drop(&mut self) {
+        self.session_ref.zeroize();
+    }
+}
 
 /// A user session with authentication state
 pub struct Session {
-    pub token: String,
+    /// For logs and events; assigned by `SessionStore::insert` (see
+    /// `TokenHash::session_id`).
+    pub session_id: String,
     pub username: String,
     pub created_at: Instant,
     pub last_activity: Instant,
+    /// The kernel holding this session; `None` if the kernel never saw it
+    /// (dev sessions).
+    pub kernel: Option<KernelSession>,
+    /// The cutoff the last `SESSION_EXPIRING_EVENT` was sent for.
+    warned_for: Option<Instant>,
+    /// Sweeps in a row in which the kernel could not be asked about it.
+    kernel_probe_failures: u32,
 }
 
 impl Session {
-    /// Check if session has expired due to inactivity
-    pub fn is_expired(&self) -> bool {
-        self.last_activity.elapsed() > SESSION_IDLE_TIMEOUT
+    /// When the session expires under `policy`, and which limit ends it.
+    pub fn expires_at(&self, policy: &SessionPolicy) -> (Instant, ExpiryReason) {
+        let idle = self.last_activity + policy.idle_timeout();
+        let lifetime = self.created_at + policy.max_lifetime();
+        if lifetime < idle {
+            (lifetime, ExpiryReason::Lifetime)
+        } else {
+            (idle, ExpiryReason::Idle)
+        }
+    }
+
+    /// Check if session has expired due to inactivity or age
+    pub fn is_expired(&self, policy: &SessionPolicy) -> bool {
+        Instant::now() >= self.expires_at(policy).0
     }
 
     /// Update last activity timestamp
@@ -41,39 +218,164 @@ impl Session {
 
 /// Thread-safe session store
 pub struct SessionStore {
-    sessions: HashMap<String, Session>,
+    sessions: HashMap<TokenHash, Session>,
+    policy: SessionPolicy,
+    /// HMAC key for `TokenHash`; random per process, never persisted.
+    key: [u8; 32],
+}
+
+impl Drop for SessionStore {
+    fn drop(&mut self) {
+        self.key.zeroize();
+    }
 }
 
 impl SessionStore {
     pub fn new() -> Self {
+        let mut key = [0u8; 32];
+        rand::rngs::OsRng.fill_bytes(&mut key);
         Self {
             sessions: HashMap::new(),
+            policy: SessionPolicy::default(),
+            key,
         }
     }
 
-    /// Insert a new session
-    pub fn insert(&mut self, session: Session) {
-        self.sessions.insert(session.token.clone(), session);
+    /// The form `token` is stored under.
+    pub fn hash(&self, token: &str) -> TokenHash {
+        let mut mac =
+            Hmac::<Sha256>::new_from_slice(&self.key).expect("HMAC takes keys of any length");
+        mac.update(token.as_bytes());
+        TokenHash(mac.finalize().into_bytes().into())
+    }
+
+    /// Apply `policy` to existing sessions as well as new ones.
+    pub fn set_policy(&mut self, policy: SessionPolicy) {
+        self.policy = policy;
+    }
+
+    /// Insert a new session for `token`, giving it its `session_id`
+    pub fn insert(&mut self, token: &str, mut session: Session) {
+        let hash = self.hash(token);
+        session.session_id = hash.session_id();
+        self.sessions.insert(hash, session);
     }
 
     /// Get a session by token (if valid and not expired)
     pub fn get(&self, token: &str) -> Option<&Session> {
-        self.sessions.get(token).filter(|s| !s.is_expired())
+        self.get_hashed(&self.hash(token))
+    }
+
+    /// Like `get`, for a token already hashed with `hash`
+    pub fn get_hashed(&self, hash: &TokenHash) -> Option<&Session> {
+        self.sessions
+            .get(hash)
+            .filter(|s| !s.is_expired(&self.policy))
     }
 
     /// Get a mutable session by token (if valid and not expired)
     pub fn get_mut(&mut self, token: &str) -> Option<&mut Session> {
-        self.sessions.get_mut(token).filter(|s| !s.is_expired())
+        let (hash, policy) = (self.hash(token), self.policy);
+        self.sessions
+            .get_mut(&hash)
+            .filter(|s| !s.is_expired(&policy))
+    }
+
+    /// Remove a session, returning it
+    pub fn remove(&mut self, token: &str) -> Option<Session> {
+        self.remove_hashed(&self.hash(token))
+    }
+
+    /// Like `remove`, for a token already hashed with `hash`
+    pub fn remove_hashed(&mut self, hash: &TokenHash) -> Option<Session> {
+        self.sessions.remove(hash)
+    }
+
+    /// Remove every session and return them, e.g. when the screen locks
+    pub fn drain(&mut self) -> Vec<Session> {
+        self.sessions.drain().map(|(_, s)| s).collect()
+    }
+
+    /// Remove all expired sessions and return them with what ended them
+    pub fn cleanup_expired(&mut self) -> Vec<(Session, ExpiryReason)> {
+        let policy = self.policy;
+        self.remove_where(|s| s.is_expired(&policy))
+            .into_iter()
+            .map(|s| {
+                let reason = s.expires_at(&policy).1;
+                (s, reason)
+            })
+            .collect()
+    }
+
+    /// Remove the sessions held by kernels older than `epoch`; their keys
+    /// left with the process.
+    pub fn end_kernel_sessions(&mut self, epoch: u64) -> Vec<Session> {
+        self.remove_where(|s| s.kernel.as_ref().is_some_and(|k| k.epoch < epoch))
     }
 
-    /// Remove a session
-    pub fn remove(&mut self, token: &str) -> bool {
-        self.sessions.remove(token).is_some()
+    /// The live sessions held by the kernel with `epoch`.
+    pub fn kernel_sessions(&self, epoch: u64) -> Vec<TokenHash> {
+        self.sessions
+            .iter()
+            .filter(|(_, s)| {
+                s.kernel.as_ref().is_some_and(|k| k.epoch == epoch) && !s.is_expired(&self.policy)
+            })
+            .map(|(hash, _)| hash.clone())
+            .collect()
+    }
+
+    /// Note whether the kernel could be asked about the session under
+    /// `hash`, and return how many sweeps in a row it could not.
+    pub fn record_kernel_probe(&mut self, hash: &TokenHash, reached: bool) -> u32 {
+        let Some(session) = self.sessions.get_mut(hash) else {
+            return 0;
+        };
+        session.kernel_probe_failures = if reached {
+            0
+        } else {
+            session.kernel_probe_failures + 1
+        };
+        session.kernel_probe_failures
     }
 
-    /// Remove all expired sessions
-    pub fn cleanup_expired(&mut self) {
-        self.sessions.retain(|_, s| !s.is_expired());
+    fn remove_where(&mut self, pred: impl Fn(&Session) -> bool) -> Vec<Session> {
+        let hashes: Vec<TokenHash> = self
+            .sessions
+            .iter()
+            .filter(|(_, s)| pred(s))
+            .map(|(hash, _)| hash.clone())
+            .collect();
+        hashes
+            .iter()
+            .filter_map(|hash| self.sessions.remove(hash))
+            .collect()
+    }
+
+    /// Warnings for live sessions that have entered the policy's warning
+    /// window. Each cutoff is warned about once; activity that pushes the
+    /// idle cutoff back re-arms the warning.
+    pub fn take_expiring(&mut self) -> Vec<SessionExpiringEvent> {
+        let policy = self.policy;
+        let now = Instant::now();
+        let mut warnings = Vec::new();
+        for session in self.sessions.values_mut() {
+            let (expires, reason) = session.expires_at(&policy);
+            if expires <= now
+                || expires > now + policy.warn_before()
+                || session.warned_for == Some(expires)
+            {
+                continue;
+            }
+            session.warned_for = Some(expires);
+            warnings.push(SessionExpiringEvent {
+                username: session.username.clone(),
+                session_id: session.session_id.clone(),
+                expires_in_secs: (expires - now).as_secs(),
+                reason,
+            });
+        }
+        warnings
     }
 }
 
@@ -90,19 +392,80 @@ impl AuthState {
 #[derive(Serialize, Deserialize, Clone)]
 pub struct AuthResult {
     pub success: bool,
-    #[serde(skip_serializing_if = "Option::is_none")]
-    pub session_token: Option<String>,
+    /// The new session's token, wiped when the result is dropped. Only the
+    /// webview keeps it; the store holds its hash.
+    #[serde(
+        default,
+        skip_serializing_if = "Option::is_none",
+        with = "zeroizing_string"
+    )]
+    pub session_token: Option<Zeroizing<String>>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub username: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub error: Option<String>,
+    /// The kernel's `session_ref` for the new session. Kept by the shell,
+    /// never passed on to the webview.
+    #[serde(default, skip_serializing)]
+    pub session_ref: Option<String>,
+}
+
+/// Serde for `Option<Zeroizing<String>>`, which `zeroize` does not provide.
+/// Deserializing from a `serde_json::Value` moves the string out of it
+/// rather than copying it.
+mod zeroizing_string {
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+    use zeroize::Zeroizing;
+
+    pub fn serialize<S: Serializer>(
+        value: &Option<Zeroizing<String>>,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        value.as_deref().serialize(serializer)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Option<Zeroizing<String>>, D::Error> {
+        Ok(Option::<String>::deserialize(deserializer)?.map(Zeroizing::new))
+    }
 }
 
 /// Session info for injection into RPC params
 #[derive(Serialize, Deserialize, Clone)]
 pub struct SessionInfo {
     pub username: String,
-    pub session_id: String, // Truncated token for logging (first 16 chars)
+    pub session_id: String, // See `TokenHash::session_id`
+    /// Time since login; used by the method policy, never sent anywhere.
+    #[serde(skip)]
+    pub age: Duration,
+}
+
+/// Payload of `SESSION_EXPIRED_EVENT`
+#[derive(Serialize, Clone)]
+pub struct SessionExpiredEvent {
+    pub username: String,
+    pub session_id: String, // As in `SessionInfo`
+    pub reason: ExpiryReason,
+}
+
+impl SessionExpiredEvent {
+    pub fn new(session: &Session, reason: ExpiryReason) -> Self {
+        Self {
+            username: session.username.clone(),
+            session_id: session.session_id.clone(),
+            reason,
+        }
+    }
+}
+
+/// Payload of `SESSION_EXPIRING_EVENT`
+#[derive(Serialize, Clone, Debug)]
+pub struct SessionExpiringEvent {
+    pub username: String,
+    pub session_id: String, // As in `SessionInfo`
+    pub expires_in_secs: u64,
+    pub reason: ExpiryReason,
 }
 
 /// Generate a cryptographically secure session token
@@ -112,22 +475,34 @@ pub fn generate_session_token() -> String {
     hex::encode(bytes)
 }
 
-/// Create a new session after Python kernel validates credentials
-pub fn create_session(token: String, username: String) -> Session {
+/// Create a new session after Python kernel validates credentials. It is
+/// stored under its token with `SessionStore::insert`; the token itself is
+/// never kept.
+pub fn create_session(username: String) -> Session {
     let now = Instant::now();
     Session {
-        token,
+        session_id: String::new(),
         username,
         created_at: now,
         last_activity: now,
+        kernel: None,
+        warned_for: None,
+        kernel_probe_failures: 0,
     }
 }
 
 /// Validate a session token and return session info if valid
 pub fn validate_session(store: &SessionStore, token: &str) -> Option<SessionInfo> {
-    store.get(token).map(|session| SessionInfo {
+    validate_hashed_session(store, &store.hash(token))
+}
+
+/// Like `validate_session`, for a token already hashed with
+/// `SessionStore::hash`
+pub fn validate_hashed_session(store: &SessionStore, hash: &TokenHash) -> Option<SessionInfo> {
+    store.get_hashed(hash).map(|session| SessionInfo {
         username: session.username.clone(),
-        session_id: token.chars().take(16).collect(),
+        session_id: session.session_id.clone(),
+        age: session.created_at.elapsed(),
     })
 }
 
@@ -145,28 +520,150 @@ mod tests {
 
     #[test]
     fn test_session_expiry() {
-        let mut session = Session {
-            token: "test".to_string(),
-            username: "testuser".to_string(),
-            created_at: Instant::now(),
-            last_activity: Instant::now() - Duration::from_secs(20 * 60), // 20 mins ago
+        let policy = SessionPolicy::default();
+        let mut session = create_session("testuser".to_string());
+        session.last_activity = Instant::now() - Duration::from_secs(20 * 60); // 20 mins ago
+
+        assert!(session.is_expired(&policy));
+        session.refresh();
+        assert!(!session.is_expired(&policy));
+
+        // Activity does not extend a session past its absolute lifetime.
+        let short = SessionPolicy {
+            max_lifetime_secs: 60,
+            ..policy
         };
+        session.created_at -= Duration::from_secs(2 * 60);
+        session.refresh();
+        assert!(session.is_expired(&short));
+        assert_eq!(session.expires_at(&short).1, ExpiryReason::Lifetime);
+    }
 
-        assert!(session.is_expired());
+    #[test]
+    fn test_expiry_warning_fires_once_per_cutoff() {
+        let mut store = SessionStore::new();
+        store.set_policy(SessionPolicy {
+            idle_timeout_secs: 600,
+            max_lifetime_secs: 3600,
+            warn_before_secs: 120,
+        });
+        let mut idle = create_session("testuser".to_string());
+        idle.last_activity -= Duration::from_secs(550);
+        store.insert("idle-token", idle);
+        store.insert("busy-token", create_session("testuser".to_string()));
+
+        let warnings = store.take_expiring();
+        assert_eq!(warnings.len(), 1);
+        assert_eq!(
+            warnings[0].session_id,
+            store.hash("idle-token").session_id()
+        );
+        assert_eq!(warnings[0].reason, ExpiryReason::Idle);
+        assert!(warnings[0].expires_in_secs <= 50);
+        assert!(store.take_expiring().is_empty());
+
+        // Activity moves the cutoff, so the next approach is warned about again.
+        let session = store.get_mut("idle-token").unwrap();
         session.refresh();
-        assert!(!session.is_expired());
+        session.last_activity -= Duration::from_secs(560);
+        assert_eq!(store.take_expiring().len(), 1);
+    }
+
+    #[test]
+    fn test_policy_file_is_optional() {
+        let dir = std::env::temp_dir().join(format!("cairn-session-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        assert_eq!(SessionPolicy::load(&dir).unwrap(), SessionPolicy::default());
+
+        std::fs::write(dir.join(POLICY_FILE_NAME), r#"{"idle_timeout_secs": 60}"#).unwrap();
+        let policy = SessionPolicy::load(&dir).unwrap();
+        assert_eq!(policy.idle_timeout_secs, 60);
+        assert_eq!(
+            policy.max_lifetime_secs,
+            SessionPolicy::default().max_lifetime_secs
+        );
+
+        std::fs::write(dir.join(POLICY_FILE_NAME), r#"{"idle": 60}"#).unwrap();
+        assert!(SessionPolicy::load(&dir).is_err());
+        let _ = std::fs::remove_dir_all(&dir);
     }
 
     #[test]
     fn test_session_store() {
         let mut store = SessionStore::new();
         let token = generate_session_token();
-        let session = create_session(token.clone(), "testuser".to_string());
+        let session = create_session("testuser".to_string());
 
-        store.insert(session);
+        store.insert(&token, session);
         assert!(store.get(&token).is_some());
+        assert!(store.get(&generate_session_token()).is_none());
 
         store.remove(&token);
         assert!(store.get(&token).is_none());
     }
+
+    #[test]
+    fn test_tokens_are_not_kept() {
+        let mut store = SessionStore::new();
+        let token = generate_session_token();
+        store.insert(&token, create_session("testuser".to_string()));
+
+        // Hashes are keyed per store and never print their bytes.
+        let hash = store.hash(&token);
+        assert_eq!(hash, store.hash(&token));
+        assert_ne!(hash, SessionStore::new().hash(&token));
+        assert_eq!(format!("{hash:?}"), "TokenHash(..)");
+
+        // Nor does the session id, which comes from the hash.
+        let info = validate_hashed_session(&store, &hash).unwrap();
+        assert_eq!(info.session_id, hash.session_id());
+        assert_eq!(info.session_id.len(), 16);
+        assert!(!token.starts_with(&info.session_id));
+    }
+
+    #[test]
+    fn test_cleanup_returns_expired_sessions() {
+        let mut store = SessionStore::new();
+        let mut stale = create_session("testuser".to_string());
+        stale.last_activity = Instant::now() - Duration::from_secs(20 * 60);
+        store.insert("stale-token", stale);
+        store.insert("fresh-token", create_session("testuser".to_string()));
+
+        let expired = store.cleanup_expired();
+        assert_eq!(expired.len(), 1);
+        let stale_id = store.hash("stale-token").session_id();
+        assert_eq!(expired[0].0.session_id, stale_id);
+        assert_eq!(expired[0].1, ExpiryReason::Idle);
+        assert!(store.cleanup_expired().is_empty());
+        assert!(store.get("fresh-token").is_some());
+
+        let event = SessionExpiredEvent::new(&expired[0].0, expired[0].1);
+        assert_eq!(event.session_id, stale_id);
+    }
+
+    #[test]
+    fn test_sessions_end_with_their_kernel() {
+        let mut store = SessionStore::new();
+        for (token, epoch) in [("old", Some(1)), ("current", Some(2)), ("dev", None)] {
+            let mut session = create_session("testuser".to_string());
+            session.kernel = epoch.map(|epoch| KernelSession {
+                epoch,
+                session_ref: format!("ref-{token}"),
+            });
+            store.insert(token, session);
+        }
+
+        let live = store.kernel_sessions(2);
+        assert_eq!(live.len(), 1);
+        assert!(live[0] == store.hash("current"));
+        let ended = store.end_kernel_sessions(2);
+        assert_eq!(ended.len(), 1);
+        assert_eq!(ended[0].session_id, store.hash("old").session_id());
+        assert!(store.get("current").is_some());
+        assert!(store.get("dev").is_some());
+
+        // A crash of epoch 2 ends its sessions too; dev sessions have no kernel.
+        assert_eq!(store.end_kernel_sessions(3).len(), 1);
+        assert!(store.get("dev").is_some());
+    }
 }
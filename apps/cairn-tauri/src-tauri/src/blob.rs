@@ -0,0 +1,387 @@
+//! Binary transfers between disk, the kernel and the webview.
+//!
+//! Files used to cross the kernel boundary as paths or as base64 inside JSON.
+//! Once `content-length` framing is negotiated (see `framing.rs`) raw bytes
+//! travel in binary frames instead, both ways:
+//!
+//! - **Uploads.** `upload` streams a file from disk into the kernel's blob
+//!   store: `blob/open`, then one binary frame per `CHUNK_SIZE` bytes, then
+//!   `blob/close` with the SHA-256 of everything sent. The resulting
+//!   `blob_id` is handed to `play/attachments/add` or `documents/insert` in
+//!   place of a `file_path`. Progress is reported after every chunk. Only
+//!   files the user picked in the shell's own file dialog can be sent: the
+//!   dialog records each choice in `UploadGrants`, and the webview cannot
+//!   name any other path.
+//! - **Downloads.** The webview loads `cairn-blob://localhost/<ticket>`. A
+//!   ticket from `BlobTickets::issue` names one attachment, document or blob
+//!   for one session and expires after `TICKET_TTL`, or earlier when the
+//!   session ends. The scheme handler fetches the bytes with `blob/fetch`,
+//!   which sends them in binary frames ahead of its reply, and answers the
+//!   webview with them directly.
+
+use crate::auth::TokenHash;
+use rand::rngs::OsRng;
+use rand::RngCore;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use crate::error::{rpc_result, CommandError};
+use crate::framing::OCTET_STREAM;
+use crate::kernel::{KernelError, KernelProcess, RequestOptions};
+
+/// Custom URI scheme the webview loads blobs from.
+pub const BLOB_SCHEME: &str = "cairn-blob";
+
+/// Bytes per binary frame on upload.
+pub const CHUNK_SIZE: usize = 256 * 1024;
+
+/// Largest file `upload` will send; the kernel enforces the same limit.
+pub const MAX_UPLOAD_BYTES: u64 = 100 * 1024 * 1024;
+
+/// How long a `cairn-blob://` URL stays valid after it is issued.
+pub const TICKET_TTL: Duration = Duration::from_secs(10 * 60);
+
+/// How long a file picked for upload may wait before it is uploaded.
+pub const GRANT_TTL: Duration = Duration::from_secs(10 * 60);
+
+/// Kernel methods that take an uploaded `blob_id` in place of `file_path`.
+pub const UPLOAD_METHODS: &[&str] = &["documents/insert", "play/attachments/add"];
+
+/// What a `cairn-blob://` URL points at.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BlobKind {
+    /// A Play attachment, by `attachment_id`.
+    Attachment,
+    /// The original file of a knowledge base document, by `document_id`.
+    Document,
+    /// A committed upload, by `blob_id`.
+    Blob,
+}
+
+impl BlobKind {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Attachment => "attachment",
+            Self::Document => "document",
+            Self::Blob => "blob",
+        }
+    }
+}
+
+/// Sent on the upload's progress channel after each chunk.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct UploadProgress {
+    pub sent: u64,
+    pub total: u64,
+}
+
+/// Stream the file at `path` into the kernel's blob store and return its
+/// `blob_id`. `session` is the `__session` value the kernel requires on
+/// every call. An upload that fails part way is aborted on the kernel side.
+pub fn upload(
+    proc: &KernelProcess,
+    path: &Path,
+    session: &Value,
+    mut on_progress: impl FnMut(UploadProgress),
+) -> Result<String, CommandError> {
+    if !proc.supports_blobs() {
+        return Err(KernelError::Unsupported("binary transfers").into());
+    }
+    let unreadable =
+        |e: std::io::Error| CommandError::InvalidArgument(format!("cannot read {path:?}: {e}"));
+    let mut file = File::open(path).map_err(unreadable)?;
+    let total = file.metadata().map_err(unreadable)?.len();
+    if total > MAX_UPLOAD_BYTES {
+        return Err(CommandError::InvalidArgument(format!(
+            "{path:?} is larger than {MAX_UPLOAD_BYTES} bytes"
+        )));
+    }
+    let name = path
+        .file_name()
+        .map(|n| n.to_string_lossy().into_owned())
+        .ok_or_else(|| CommandError::InvalidArgument(format!("{path:?} is not a file")))?;
+
+    let opened = rpc_result(proc.request(
+        "blob/open",
+        json!({ "name": name, "size": total, "__session": session }),
+    )?)?;
+    let blob_id = opened["blob_id"]
+        .as_str()
+        .ok_or_else(|| KernelError::InvalidJson("blob/open returned no blob_id".to_string()))?
+        .to_string();
+
+    let sent = (|| {
+        let mut hasher = Sha256::new();
+        let mut buf = vec![0; CHUNK_SIZE];
+        let mut sent = 0u64;
+        loop {
+            let n = file.read(&mut buf).map_err(unreadable)?;
+            if n == 0 {
+                break;
+            }
+            hasher.update(&buf[..n]);
+            proc.send_blob_chunk(&blob_id, &buf[..n])?;
+            sent += n as u64;
+            on_progress(UploadProgress { sent, total });
+        }
+        // The kernel checks size and digest, so a file that changed while
+        // it was read is rejected rather than stored half old, half new.
+        let params = json!({
+            "blob_id": blob_id,
+            "sha256": hex::encode(hasher.finalize()),
+            "__session": session,
+        });
+        rpc_result(proc.request("blob/close", params)?)
+    })();
+
+    if let Err(e) = sent {
+        let params = json!({ "blob_id": blob_id, "__session": session });
+        let _ = proc.request_with(
+            "blob/abort",
+            params,
+            RequestOptions {
+                timeout: Some(Duration::from_secs(5)),
+                ..Default::default()
+            },
+        );
+        return Err(e);
+    }
+    Ok(blob_id)
+}
+
+/// Bytes fetched for a `cairn-blob://` request.
+pub struct Fetched {
+    pub data: Vec<u8>,
+    pub mime: String,
+    pub file_name: String,
+}
+
+impl Fetched {
+    /// `Content-Disposition` value naming the file, reduced to characters
+    /// that are safe in a header.
+    pub fn content_disposition(&self) -> String {
+        let name: String = self
+            .file_name
+            .chars()
+            .map(|c| match c {
+                ' '..='~' if c != '"' && c != '\\' => c,
+                _ => '_',
+            })
+            .collect();
+        format!("inline; filename=\"{name}\"")
+    }
+}
+
+/// Fetch the bytes of `id` from the kernel with `blob/fetch`.
+pub fn fetch(
+    proc: &KernelProcess,
+    session: &Value,
+    kind: BlobKind,
+    id: &str,
+) -> Result<Fetched, CommandError> {
+    let params = json!({ "kind": kind.as_str(), "id": id, "__session": session });
+    let (envelope, data) = proc.request_bytes("blob/fetch", params, RequestOptions::default())?;
+    let result = rpc_result(envelope)?;
+    let text = |key: &str, default: &str| result[key].as_str().unwrap_or(default).to_string();
+    Ok(Fetched {
+        data,
+        mime: text("mime", OCTET_STREAM),
+        file_name: text("file_name", id),
+    })
+}
+
+/// The object a ticket grants access to.
+#[derive(Clone)]
+pub struct Ticket {
+    /// Checked again on every load, so a ticket dies with its session.
+    pub session: TokenHash,
+    pub kind: BlobKind,
+    pub id: String,
+    expires: Instant,
+}
+
+/// Outstanding `cairn-blob://` tickets.
+#[derive(Default)]
+pub struct BlobTickets {
+    tickets: Mutex<HashMap<String, Ticket>>,
+}
+
+impl BlobTickets {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Grant `session` access to one object and return the URL to load it
+    /// from. The ticket can be used any number of times until it expires,
+    /// so an `<img>` that is re-rendered keeps working.
+    pub fn issue(&self, session: TokenHash, kind: BlobKind, id: &str) -> String {
+        let mut bytes = [0u8; 24];
+        OsRng.fill_bytes(&mut bytes);
+        let ticket = hex::encode(bytes);
+        let now = Instant::now();
+        if let Ok(mut tickets) = self.tickets.lock() {
+            tickets.retain(|_, t| t.expires > now);
+            tickets.insert(
+                ticket.clone(),
+                Ticket {
+                    session,
+                    kind,
+                    id: id.to_string(),
+                    expires: now + TICKET_TTL,
+                },
+            );
+        }
+        blob_url(&ticket)
+    }
+
+    /// The object behind the ticket in a `cairn-blob://` request path.
+    pub fn resolve(&self, path: &str) -> Option<Ticket> {
+        let ticket = path.trim_start_matches('/');
+        let tickets = self.tickets.lock().ok()?;
+        tickets
+            .get(ticket)
+            .filter(|t| t.expires > Instant::now())
+            .cloned()
+    }
+}
+
+/// Files the user picked for upload in the shell's file dialog.
+#[derive(Default)]
+pub struct UploadGrants {
+    grants: Mutex<HashMap<PathBuf, Instant>>,
+}
+
+impl UploadGrants {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Allow one upload of `path`, which the shell itself chose. Returns the
+    /// canonical path, which is what the webview must pass back.
+    pub fn grant(&self, path: &Path) -> std::io::Result<PathBuf> {
+        let path = path.canonicalize()?;
+        let now = Instant::now();
+        if let Ok(mut grants) = self.grants.lock() {
+            grants.retain(|_, expires| *expires > now);
+            grants.insert(path.clone(), now + GRANT_TTL);
+        }
+        Ok(path)
+    }
+
+    /// Use up the grant for `path`. `None` if it was never picked, has
+    /// already been uploaded, or the grant has expired.
+    pub fn take(&self, path: &str) -> Option<PathBuf> {
+        let path = Path::new(path).canonicalize().ok()?;
+        let expires = self.grants.lock().ok()?.remove(&path)?;
+        (expires > Instant::now()).then_some(path)
+    }
+}
+
+/// Origins the app's own pages are served from on each platform.
+const APP_ORIGINS: &[&str] = &[
+    "tauri://localhost",
+    "http://tauri.localhost",
+    "https://tauri.localhost",
+];
+
+/// Whether `origin` is the app's own, and so may read `cairn-blob://`
+/// responses cross-origin. `dev_origin` is the dev server's, in debug builds.
+pub fn is_app_origin(origin: &str, dev_origin: Option<&str>) -> bool {
+    APP_ORIGINS.contains(&origin) || dev_origin == Some(origin)
+}
+
+/// URL the webview loads `ticket` from. WebView2 and Android only route
+/// custom schemes through `http://<scheme>.localhost`.
+pub fn blob_url(ticket: &str) -> String {
+    if cfg!(any(windows, target_os = "android")) {
+        format!("http://{BLOB_SCHEME}.localhost/{ticket}")
+    } else {
+        format!("{BLOB_SCHEME}://localhost/{ticket}")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::auth::SessionStore;
+
+    #[test]
+    fn test_tickets_resolve_by_path() {
+        let store = SessionStore::new();
+        let tickets = BlobTickets::new();
+        let url = tickets.issue(store.hash("token-1"), BlobKind::Attachment, "att-1");
+        let other = tickets.issue(store.hash("token-1"), BlobKind::Attachment, "att-1");
+        assert_ne!(url, other);
+
+        let path = url.rsplit_once('/').unwrap().1;
+        let ticket = tickets.resolve(&format!("/{path}")).unwrap();
+        assert_eq!(ticket.kind, BlobKind::Attachment);
+        assert_eq!(ticket.id, "att-1");
+        assert_eq!(ticket.session, store.hash("token-1"));
+        assert!(tickets.resolve("/not-a-ticket").is_none());
+        assert!(tickets.resolve("/").is_none());
+    }
+
+    #[test]
+    fn test_only_the_app_origin_may_read_blobs() {
+        assert!(is_app_origin("tauri://localhost", None));
+        assert!(is_app_origin("http://tauri.localhost", None));
+        assert!(!is_app_origin("https://example.com", None));
+        assert!(!is_app_origin("null", None));
+        assert!(!is_app_origin("http://localhost:1420", None));
+        assert!(is_app_origin(
+            "http://localhost:1420",
+            Some("http://localhost:1420")
+        ));
+    }
+
+    #[test]
+    fn test_only_granted_paths_upload_once() {
+        let dir = std::env::temp_dir().join(format!("cairn-grants-{}", std::process::id()));
+        std::fs::create_dir_all(dir.join("sub")).unwrap();
+        let picked = dir.join("picked.txt");
+        std::fs::write(&picked, b"ok").unwrap();
+        std::fs::write(dir.join("other.txt"), b"secret").unwrap();
+
+        let grants = UploadGrants::new();
+        let granted = grants.grant(&picked).unwrap();
+        assert!(grants
+            .take(dir.join("other.txt").to_str().unwrap())
+            .is_none());
+        assert!(grants.take("/no/such/file").is_none());
+
+        // Another spelling of the same file is the same grant, used once.
+        let dotted = dir.join("sub/../picked.txt");
+        assert_eq!(grants.take(dotted.to_str().unwrap()), Some(granted));
+        assert!(grants.take(picked.to_str().unwrap()).is_none());
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_content_disposition_is_header_safe() {
+        let fetched = Fetched {
+            data: Vec::new(),
+            mime: OCTET_STREAM.to_string(),
+            file_name: "r\u{e9}sum\u{e9} \"final\".pdf\r\n".to_string(),
+        };
+        assert_eq!(
+            fetched.content_disposition(),
+            "inline; filename=\"r_sum_ _final_.pdf__\""
+        );
+    }
+
+    #[test]
+    fn test_kind_names_match_kernel() {
+        for kind in [BlobKind::Attachment, BlobKind::Document, BlobKind::Blob] {
+            assert_eq!(serde_json::to_value(kind).unwrap(), kind.as_str());
+        }
+    }
+}
@@ -0,0 +1,508 @@
+//! Response cache and request coalescing for idempotent kernel reads.
+//!
+//! Views poll the same read methods over and over. Methods listed in
+//! `CACHED_METHODS` are answered from a short-lived per-session cache, and
+//! identical calls made while one is already in flight wait for it instead
+//! of sending their own. Only successful results are cached; an error is
+//! shared with the callers already waiting, then forgotten.
+//!
+//! A call to a method in `INVALIDATIONS` drops the cached reads it makes
+//! stale, for every session, once it completes. Reads still in flight at
+//! that point are neither cached nor joined by later callers.
+
+use serde_json::Value;
+use std::collections::HashMap;
+use std::sync::{Arc, Condvar, Mutex};
+use std::time::{Duration, Instant};
+
+use crate::error::CommandError;
+use crate::policy::{self, pattern_matches};
+
+/// A read-only method whose results may be reused for `ttl`.
+#[derive(Debug, Clone, Copy)]
+pub struct CachedMethod {
+    pub method: &'static str,
+    pub ttl: Duration,
+}
+
+const fn cached(method: &'static str, secs: u64) -> CachedMethod {
+    CachedMethod {
+        method,
+        ttl: Duration::from_secs(secs),
+    }
+}
+
+/// The opt-in list. Only add methods that neither change kernel state nor
+/// depend on anything but their params and the database.
+pub const CACHED_METHODS: &[CachedMethod] = &[
+    cached("context/stats", 10),
+    cached("lifecycle/memories/by_act_page", 15),
+    cached("play/acts/list", 30),
+    cached("play/scenes/list", 30),
+    cached("play/scenes/list_all", 30),
+];
+
+/// Mutating methods (`on`, exact names) and the cached reads they make stale
+/// (`stale`, exact names or namespace wildcards).
+#[derive(Debug, Clone, Copy)]
+pub struct Invalidation {
+    pub on: &'static [&'static str],
+    pub stale: &'static [&'static str],
+}
+
+/// Keep in step with `CACHED_METHODS`: a write missing here is served stale
+/// until the TTL runs out. Kernel notifications (`cairn/chat_status`) count
+/// as calls too, since chat tool use lands after `cairn/chat_async` returns.
+pub const INVALIDATIONS: &[Invalidation] = &[
+    Invalidation {
+        on: &["context/toggle_source"],
+        stale: &["context/stats"],
+    },
+    Invalidation {
+        on: &["chat/respond", "conversation/archive"],
+        stale: &["context/stats", "lifecycle/memories/*"],
+    },
+    Invalidation {
+        on: &[
+            "lifecycle/memories/approve",
+            "lifecycle/memories/ensure_page",
+            "lifecycle/memories/reject",
+        ],
+        stale: &["lifecycle/memories/*"],
+    },
+    Invalidation {
+        on: &[
+            "cairn/chat_async",
+            "cairn/chat_status",
+            "play/acts/assign_repo",
+            "play/acts/create",
+            "play/acts/delete",
+            "play/acts/set_active",
+            "play/acts/update",
+        ],
+        stale: &["play/acts/*", "play/scenes/*"],
+    },
+    Invalidation {
+        on: &[
+            "blocks/delete",
+            "play/scenes/create",
+            "play/scenes/delete",
+            "play/scenes/update",
+        ],
+        stale: &["play/scenes/*"],
+    },
+];
+
+/// Made stale by a `tools/call` to any tool `TOOL_POLICIES` confirms: those
+/// are the tools that change Acts and Scenes.
+pub const TOOL_WRITE_STALE: &[&str] = &["play/acts/*", "play/scenes/*"];
+
+/// Cached entries kept at most; expired ones go first, then those closest
+/// to expiring.
+const MAX_ENTRIES: usize = 256;
+
+pub fn cached_method(method: &str) -> Option<&'static CachedMethod> {
+    CACHED_METHODS.iter().find(|c| c.method == method)
+}
+
+/// The cached reads a call to `method` with `params` makes stale.
+pub fn stale_after(method: &str, params: &Value) -> Vec<&'static str> {
+    let mut stale: Vec<&'static str> = INVALIDATIONS
+        .iter()
+        .filter(|inv| inv.on.contains(&method))
+        .flat_map(|inv| inv.stale.iter().copied())
+        .collect();
+    let writes_tool = method == "tools/call"
+        && params
+            .get("name")
+            .and_then(Value::as_str)
+            .and_then(policy::lookup_tool)
+            .is_some_and(|tool| tool.confirm.is_some());
+    if writes_tool {
+        stale.extend_from_slice(TOOL_WRITE_STALE);
+    }
+    stale
+}
+
+/// Session id, method, serialized params.
+type Key = (String, &'static str, String);
+
+struct Entry {
+    value: Value,
+    expires: Instant,
+}
+
+/// One in-flight read that identical callers wait on.
+#[derive(Default)]
+struct Flight {
+    result: Mutex<Option<Result<Value, CommandError>>>,
+    done: Condvar,
+}
+
+impl Flight {
+    fn complete(&self, result: Result<Value, CommandError>) {
+        if let Ok(mut slot) = self.result.lock() {
+            *slot = Some(result);
+        }
+        self.done.notify_all();
+    }
+
+    fn wait(&self) -> Result<Value, CommandError> {
+        let mut slot = self.result.lock()?;
+        loop {
+            if let Some(result) = slot.as_ref() {
+                return result.clone();
+            }
+            slot = self.done.wait(slot)?;
+        }
+    }
+}
+
+/// Held by the caller that runs the fetch for a flight. Detaches and
+/// completes the flight when dropped, so waiters are never stranded even if
+/// the fetch panics.
+struct Leader<'a> {
+    cache: &'a ResponseCache,
+    key: Key,
+    flight: Arc<Flight>,
+    generation: u64,
+    ttl: Duration,
+    result: Option<Result<Value, CommandError>>,
+}
+
+impl Drop for Leader<'_> {
+    fn drop(&mut self) {
+        if let Ok(mut inner) = self.cache.inner.lock() {
+            // An invalidation may already have detached this flight.
+            if inner
+                .in_flight
+                .get(&self.key)
+                .is_some_and(|f| Arc::ptr_eq(f, &self.flight))
+            {
+                inner.in_flight.remove(&self.key);
+            }
+            if let (Some(Ok(value)), true) = (&self.result, inner.generation == self.generation) {
+                store(&mut inner, self.key.clone(), value.clone(), self.ttl);
+            }
+        }
+        self.flight.complete(self.result.take().unwrap_or_else(|| {
+            Err(CommandError::Internal(
+                "coalesced kernel request did not complete".to_string(),
+            ))
+        }));
+    }
+}
+
+#[derive(Default)]
+struct Inner {
+    entries: HashMap<Key, Entry>,
+    in_flight: HashMap<Key, Arc<Flight>>,
+    /// Bumped by every invalidation; a read that started before one is not
+    /// cached.
+    generation: u64,
+}
+
+#[derive(Default)]
+pub struct ResponseCache {
+    inner: Mutex<Inner>,
+}
+
+impl ResponseCache {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Run `method` through the cache. `fetch` performs the real call with
+    /// `params`; it runs only on a miss with no identical call in flight.
+    /// Calls to anything but a cached method go straight to `fetch`, then
+    /// invalidate whatever they make stale.
+    pub fn call(
+        &self,
+        session_id: &str,
+        method: &str,
+        params: Value,
+        fetch: impl FnOnce(Value) -> Result<Value, CommandError>,
+    ) -> Result<Value, CommandError> {
+        let Some(cached) = cached_method(method) else {
+            let stale = stale_after(method, &params);
+            let result = fetch(params);
+            self.invalidate(&stale);
+            return result;
+        };
+
+        let key: Key = (session_id.to_string(), cached.method, params.to_string());
+        let mut leader = {
+            let mut inner = self.inner.lock()?;
+            let now = Instant::now();
+            match inner.entries.get(&key) {
+                Some(entry) if entry.expires > now => return Ok(entry.value.clone()),
+                Some(_) => {
+                    inner.entries.remove(&key);
+                }
+                None => {}
+            }
+            if let Some(flight) = inner.in_flight.get(&key).cloned() {
+                drop(inner);
+                return flight.wait();
+            }
+            let flight = Arc::new(Flight::default());
+            inner.in_flight.insert(key.clone(), flight.clone());
+            Leader {
+                cache: self,
+                key,
+                flight,
+                generation: inner.generation,
+                ttl: cached.ttl,
+                result: None,
+            }
+        };
+
+        let result = fetch(params);
+        leader.result = Some(result.clone());
+        result
+    }
+
+    /// The fresh cached result for a read, if any. Unlike `call`, never
+    /// waits on a read in flight.
+    pub fn get(&self, session_id: &str, method: &str, params: &Value) -> Option<Value> {
+        let cached = cached_method(method)?;
+        let key: Key = (session_id.to_string(), cached.method, params.to_string());
+        let inner = self.inner.lock().ok()?;
+        inner
+            .entries
+            .get(&key)
+            .filter(|entry| entry.expires > Instant::now())
+            .map(|entry| entry.value.clone())
+    }
+
+    /// Taken before fetching a read outside `call`, and handed back to
+    /// `insert` so a result that raced an invalidation is not cached.
+    pub fn generation(&self) -> u64 {
+        self.inner.lock().map(|inner| inner.generation).unwrap_or(0)
+    }
+
+    /// Cache a read fetched outside `call`. Ignored for methods that are not
+    /// cached, or if anything was invalidated since `generation`.
+    pub fn insert(
+        &self,
+        session_id: &str,
+        method: &str,
+        params: &Value,
+        value: Value,
+        generation: u64,
+    ) {
+        let Some(cached) = cached_method(method) else {
+            return;
+        };
+        let key: Key = (session_id.to_string(), cached.method, params.to_string());
+        if let Ok(mut inner) = self.inner.lock() {
+            if inner.generation == generation {
+                store(&mut inner, key, value, cached.ttl);
+            }
+        }
+    }
+
+    /// Drop every session's cached reads that a call to `method` with
+    /// `params` makes stale.
+    pub fn invalidate_for(&self, method: &str, params: &Value) {
+        self.invalidate(&stale_after(method, params));
+    }
+
+    /// Drop every session's cached reads matching `stale` (see
+    /// `stale_after`), and stop new callers joining such reads still in
+    /// flight.
+    pub fn invalidate(&self, stale: &[&str]) {
+        if stale.is_empty() {
+            return;
+        }
+        let Ok(mut inner) = self.inner.lock() else {
+            return;
+        };
+        let is_stale = |key: &Key| stale.iter().any(|p| pattern_matches(p, key.1));
+        inner.generation += 1;
+        inner.entries.retain(|key, _| !is_stale(key));
+        inner.in_flight.retain(|key, _| !is_stale(key));
+    }
+}
+
+fn store(inner: &mut Inner, key: Key, value: Value, ttl: Duration) {
+    let entry = Entry {
+        value,
+        expires: Instant::now() + ttl,
+    };
+    inner.entries.insert(key, entry);
+    evict(&mut inner.entries);
+}
+
+fn evict(entries: &mut HashMap<Key, Entry>) {
+    if entries.len() <= MAX_ENTRIES {
+        return;
+    }
+    let now = Instant::now();
+    entries.retain(|_, e| e.expires > now);
+    while entries.len() > MAX_ENTRIES {
+        let Some(oldest) = entries
+            .iter()
+            .min_by_key(|(_, e)| e.expires)
+            .map(|(k, _)| k.clone())
+        else {
+            break;
+        };
+        entries.remove(&oldest);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Barrier;
+    use std::thread;
+
+    #[test]
+    fn test_tables_name_allowed_methods() {
+        for cached in CACHED_METHODS {
+            assert!(policy::lookup(cached.method).is_some(), "{}", cached.method);
+        }
+        for inv in INVALIDATIONS {
+            for method in inv.on {
+                assert!(policy::lookup(method).is_some(), "{method}");
+                assert!(cached_method(method).is_none(), "{method} is cached");
+            }
+            for pattern in inv.stale {
+                assert!(
+                    CACHED_METHODS
+                        .iter()
+                        .any(|c| pattern_matches(pattern, c.method)),
+                    "{pattern} matches no cached method"
+                );
+            }
+        }
+        for pattern in TOOL_WRITE_STALE {
+            assert!(
+                CACHED_METHODS
+                    .iter()
+                    .any(|c| pattern_matches(pattern, c.method)),
+                "{pattern} matches no cached method"
+            );
+        }
+    }
+
+    #[test]
+    fn test_tool_writes_invalidate_play_reads() {
+        let read = json!({ "name": "cairn_list_acts" });
+        assert!(stale_after("tools/call", &read).is_empty());
+
+        let write = json!({ "name": "cairn_delete_scene", "arguments": {} });
+        assert_eq!(stale_after("tools/call", &write), TOOL_WRITE_STALE);
+
+        let cache = ResponseCache::new();
+        let calls = AtomicUsize::new(0);
+        let fetch = |_params: Value| {
+            calls.fetch_add(1, Ordering::SeqCst);
+            Ok(json!({ "acts": [] }))
+        };
+        cache
+            .call("s1", "play/acts/list", json!({}), fetch)
+            .unwrap();
+        cache
+            .call("s1", "tools/call", read, |_| Ok(json!({})))
+            .unwrap();
+        cache
+            .call("s1", "play/acts/list", json!({}), fetch)
+            .unwrap();
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+
+        cache
+            .call("s1", "tools/call", write, |_| Ok(json!({})))
+            .unwrap();
+        cache
+            .call("s1", "play/acts/list", json!({}), fetch)
+            .unwrap();
+        assert_eq!(calls.load(Ordering::SeqCst), 2);
+    }
+
+    #[test]
+    fn test_hits_and_invalidation() {
+        let cache = ResponseCache::new();
+        let calls = AtomicUsize::new(0);
+        let fetch = |_params: Value| {
+            calls.fetch_add(1, Ordering::SeqCst);
+            Ok(json!({ "acts": [] }))
+        };
+
+        cache
+            .call("s1", "play/acts/list", json!({}), fetch)
+            .unwrap();
+        cache
+            .call("s1", "play/acts/list", json!({}), fetch)
+            .unwrap();
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+
+        // Other sessions and other params are separate entries.
+        cache
+            .call("s2", "play/acts/list", json!({}), fetch)
+            .unwrap();
+        cache
+            .call("s1", "play/acts/list", json!({ "x": 1 }), fetch)
+            .unwrap();
+        assert_eq!(calls.load(Ordering::SeqCst), 3);
+
+        // A write drops the entries it makes stale, for every session.
+        cache
+            .call("s1", "play/scenes/list", json!({ "act_id": "a" }), fetch)
+            .unwrap();
+        cache
+            .call("s1", "play/acts/update", json!({ "act_id": "a" }), |_| {
+                Ok(json!({}))
+            })
+            .unwrap();
+        cache
+            .call("s2", "play/acts/list", json!({}), fetch)
+            .unwrap();
+        cache
+            .call("s1", "play/scenes/list", json!({ "act_id": "a" }), fetch)
+            .unwrap();
+        assert_eq!(calls.load(Ordering::SeqCst), 6);
+
+        // Errors are not cached.
+        let failing = |_params: Value| -> Result<Value, CommandError> {
+            calls.fetch_add(1, Ordering::SeqCst);
+            Err(CommandError::Internal("boom".to_string()))
+        };
+        assert!(cache
+            .call("s1", "context/stats", json!({}), failing)
+            .is_err());
+        assert!(cache
+            .call("s1", "context/stats", json!({}), failing)
+            .is_err());
+        assert_eq!(calls.load(Ordering::SeqCst), 8);
+    }
+
+    #[test]
+    fn test_concurrent_identical_calls_share_one_request() {
+        let cache = Arc::new(ResponseCache::new());
+        let calls = Arc::new(AtomicUsize::new(0));
+        let barrier = Arc::new(Barrier::new(8));
+
+        let handles: Vec<_> = (0..8)
+            .map(|_| {
+                let (cache, calls, barrier) = (cache.clone(), calls.clone(), barrier.clone());
+                thread::spawn(move || {
+                    barrier.wait();
+                    cache.call("s1", "play/scenes/list_all", json!({}), |_| {
+                        calls.fetch_add(1, Ordering::SeqCst);
+                        thread::sleep(Duration::from_millis(100));
+                        Ok(json!({ "scenes": [1, 2] }))
+                    })
+                })
+            })
+            .collect();
+
+        for handle in handles {
+            assert_eq!(handle.join().unwrap().unwrap(), json!({ "scenes": [1, 2] }));
+        }
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+    }
+}
@@ -0,0 +1,447 @@
+//! Recorded kernel sessions, for tests and for running the UI without Python.
+//!
+//! With `CAIRN_KERNEL_RECORD=<path>` set, every request sent through a
+//! `KernelProcess` is written to `<path>` together with its response, one
+//! JSON object per line:
+//!
+//! ```text
+//! {"method":"play/acts/list","params":{},"response":{"jsonrpc":"2.0","result":{...}}}
+//! {"method":"chat/respond","params":{...},"partials":[...],"response":{...}}
+//! ```
+//!
+//! `__session` is dropped from params, request ids from responses, and the
+//! values of `SECRET_KEYS` are replaced wherever they appear. Bytes sent in
+//! binary frames (see `blob.rs`) are not recorded.
+//!
+//! `FakeKernel` is a `KernelTransport` that answers from such a cassette,
+//! so the shell can be driven deterministically without a kernel: in unit
+//! tests, or in the app with `CAIRN_KERNEL_REPLAY=<path>`. Each request gets
+//! the first unused exchange with the same method and params (ignoring
+//! `__session`, and params altogether for `initialize`); once those run out
+//! the last match is reused, so polling keeps working. A request with no
+//! match gets a JSON-RPC error.
+
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, BufWriter, Read, Write};
+use std::path::Path;
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+use crate::framing::{read_frame, Frame, Framing};
+use crate::kernel::{Connection, KernelError, KernelTransport};
+
+/// Record every kernel exchange to this file.
+pub const RECORD_ENV: &str = "CAIRN_KERNEL_RECORD";
+
+/// Replay this cassette instead of starting a kernel.
+pub const REPLAY_ENV: &str = "CAIRN_KERNEL_REPLAY";
+
+/// Keys whose values never reach a cassette.
+pub const SECRET_KEYS: &[&str] = &["password", "session_token", "session_ref"];
+
+const REDACTED: &str = "[redacted]";
+
+/// JSON-RPC error code for a request the cassette has no answer for.
+pub const NOT_RECORDED: i64 = -32099;
+
+/// One request and everything the kernel sent back for it.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Exchange {
+    pub method: String,
+    #[serde(default)]
+    pub params: Value,
+    /// Streamed chunks, in order, ahead of the response.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub partials: Vec<Value>,
+    /// The final response envelope, without its id.
+    pub response: Value,
+}
+
+impl Exchange {
+    /// Build an exchange from a request envelope and its reply, in the form
+    /// it is stored in.
+    pub fn new(request: &Value, partials: Vec<Value>, response: &Value) -> Self {
+        let mut response = response.clone();
+        if let Value::Object(map) = &mut response {
+            map.remove("id");
+        }
+        redact(&mut response);
+        let mut partials = partials;
+        partials.iter_mut().for_each(redact);
+        Self {
+            method: request["method"].as_str().unwrap_or_default().to_string(),
+            params: normalize(&request["params"]),
+            partials,
+            response,
+        }
+    }
+}
+
+/// `params` as stored and as matched: no `__session`, secrets redacted.
+fn normalize(params: &Value) -> Value {
+    let mut params = params.clone();
+    if let Value::Object(map) = &mut params {
+        map.remove("__session");
+    }
+    redact(&mut params);
+    params
+}
+
+fn redact(value: &mut Value) {
+    match value {
+        Value::Object(map) => {
+            for (key, value) in map.iter_mut() {
+                if SECRET_KEYS.contains(&key.as_str()) && !value.is_null() {
+                    *value = Value::String(REDACTED.to_string());
+                } else {
+                    redact(value);
+                }
+            }
+        }
+        Value::Array(items) => items.iter_mut().for_each(redact),
+        _ => {}
+    }
+}
+
+/// An exchange whose request succeeded with `result`.
+#[cfg(test)]
+pub fn exchange(method: &str, params: Value, result: Value) -> Exchange {
+    let request = json!({ "method": method, "params": params });
+    Exchange::new(
+        &request,
+        Vec::new(),
+        &json!({ "jsonrpc": "2.0", "result": result }),
+    )
+}
+
+/// A kernel handshake at the shell's protocol version.
+#[cfg(test)]
+pub fn handshake() -> Exchange {
+    let info = json!({
+        "protocol_version": crate::kernel::PROTOCOL_VERSION,
+        "kernel_version": "test",
+        "capabilities": [],
+    });
+    exchange("initialize", json!({}), info)
+}
+
+/// A successful `auth/login` for `username`. Its token and session ref are
+/// redacted like any recorded ones, so only the username varies.
+#[cfg(test)]
+pub fn login_exchange(username: &str) -> Exchange {
+    exchange(
+        "auth/login",
+        json!({ "username": username }),
+        json!({
+            "success": true,
+            "session_token": "tok-1",
+            "username": username,
+            "session_ref": "ref-1"
+        }),
+    )
+}
+
+/// Appends exchanges to a cassette file as they complete.
+pub struct Recorder {
+    out: Mutex<BufWriter<File>>,
+}
+
+impl Recorder {
+    /// Start a new cassette at `path`, replacing any existing file.
+    pub fn create(path: &Path) -> io::Result<Self> {
+        Ok(Self {
+            out: Mutex::new(BufWriter::new(File::create(path)?)),
+        })
+    }
+
+    /// Write one exchange. Failures are reported on stderr and otherwise
+    /// ignored; recording must never break a request.
+    pub fn record(&self, exchange: &Exchange) {
+        let Ok(line) = serde_json::to_string(exchange) else {
+            return;
+        };
+        let Ok(mut out) = self.out.lock() else {
+            return;
+        };
+        if let Err(e) = writeln!(out, "{line}").and_then(|_| out.flush()) {
+            eprintln!("[cassette] cannot record {}: {e}", exchange.method);
+        }
+    }
+}
+
+/// Load the exchanges in a cassette file.
+pub fn load(path: &Path) -> io::Result<Vec<Exchange>> {
+    let file = BufReader::new(File::open(path)?);
+    let mut exchanges = Vec::new();
+    for (n, line) in file.lines().enumerate() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let exchange = serde_json::from_str(&line).map_err(|e| {
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("{}:{}: {e}", path.display(), n + 1),
+            )
+        })?;
+        exchanges.push(exchange);
+    }
+    Ok(exchanges)
+}
+
+/// A transport that answers from a cassette instead of a kernel.
+///
+/// Every `connect` replays the cassette from the start, so a restarted
+/// kernel sees the same session again.
+pub struct FakeKernel {
+    exchanges: Arc<Vec<Exchange>>,
+    hold: usize,
+}
+
+impl FakeKernel {
+    pub fn new(exchanges: Vec<Exchange>) -> Self {
+        Self {
+            exchanges: Arc::new(exchanges),
+            hold: 1,
+        }
+    }
+
+    /// Replay the cassette at `path`.
+    pub fn load(path: &Path) -> io::Result<Self> {
+        load(path).map(Self::new)
+    }
+
+    /// Hold replies until `n` requests are waiting, then answer them newest
+    /// first, to exercise out-of-order responses. `initialize` is always
+    /// answered at once.
+    #[cfg(test)]
+    pub fn reply_in_reverse(mut self, n: usize) -> Self {
+        self.hold = n.max(1);
+        self
+    }
+}
+
+impl KernelTransport for FakeKernel {
+    fn connect(&self) -> Result<Connection, KernelError> {
+        let (to_kernel, requests) = mpsc::channel();
+        let (to_shell, replies) = mpsc::channel();
+        let mut replay = Replay {
+            exchanges: self.exchanges.clone(),
+            used: vec![false; self.exchanges.len()],
+            hold: self.hold,
+            held: Vec::new(),
+            out: to_shell,
+        };
+        thread::Builder::new()
+            .name("fake-kernel".to_string())
+            .spawn(move || replay.run(BufReader::new(ChannelReader::new(requests))))
+            .map_err(|e| KernelError::SpawnFailed(format!("fake kernel thread: {e}")))?;
+
+        Ok(Connection {
+            reader: Box::new(ChannelReader::new(replies)),
+            writer: Box::new(ChannelWriter(to_kernel)),
+            child: None,
+            stderr: None,
+            lifeline: None,
+            disconnect: None,
+        })
+    }
+}
+
+/// The fake kernel's side of one connection.
+struct Replay {
+    exchanges: Arc<Vec<Exchange>>,
+    used: Vec<bool>,
+    hold: usize,
+    /// Replies waiting to be sent, oldest first.
+    held: Vec<Vec<Value>>,
+    out: Sender<Vec<u8>>,
+}
+
+impl Replay {
+    /// Answer requests until the shell closes its end.
+    fn run(&mut self, mut requests: impl BufRead) {
+        while let Ok(Some(frame)) = read_frame(&mut requests) {
+            let Frame::Message(body) = frame else {
+                continue;
+            };
+            match serde_json::from_slice::<Value>(&body) {
+                Ok(Value::Array(batch)) => {
+                    let replies: Vec<Value> = batch
+                        .iter()
+                        .filter_map(|req| self.answer(req))
+                        .filter_map(|mut reply| reply.pop())
+                        .collect();
+                    if !replies.is_empty() {
+                        self.send(&[Value::Array(replies)]);
+                    }
+                }
+                Ok(req) => {
+                    let Some(reply) = self.answer(&req) else {
+                        continue;
+                    };
+                    if req["method"] == "initialize" {
+                        self.send(&reply);
+                        continue;
+                    }
+                    self.held.push(reply);
+                    if self.held.len() >= self.hold {
+                        for reply in std::mem::take(&mut self.held).iter().rev() {
+                            self.send(reply);
+                        }
+                    }
+                }
+                Err(_) => {}
+            }
+        }
+    }
+
+    /// The messages that answer `req`: any partials, then the response.
+    /// `None` for notifications.
+    fn answer(&mut self, req: &Value) -> Option<Vec<Value>> {
+        let id = req.get("id")?.clone();
+        let method = req["method"].as_str().unwrap_or_default();
+        let params = normalize(&req["params"]);
+        // The handshake carries the shell's version, so any recorded one will do.
+        let matches: Vec<usize> = (0..self.exchanges.len())
+            .filter(|&i| {
+                let exchange = &self.exchanges[i];
+                exchange.method == method && (method == "initialize" || exchange.params == params)
+            })
+            .collect();
+        let Some(index) = matches
+            .iter()
+            .copied()
+            .find(|&i| !self.used[i])
+            .or(matches.last().copied())
+        else {
+            return Some(vec![json!({
+                "jsonrpc": "2.0",
+                "id": id,
+                "error": {
+                    "code": NOT_RECORDED,
+                    "message": format!("no recorded response for {method}"),
+                },
+            })]);
+        };
+        self.used[index] = true;
+
+        let exchange = &self.exchanges[index];
+        let mut messages: Vec<Value> = exchange
+            .partials
+            .iter()
+            .map(|chunk| json!({ "jsonrpc": "2.0", "id": id, "partial": chunk }))
+            .collect();
+        let mut response = exchange.response.clone();
+        if let Value::Object(map) = &mut response {
+            map.insert("id".to_string(), id);
+        }
+        messages.push(response);
+        Some(messages)
+    }
+
+    fn send(&self, messages: &[Value]) {
+        for message in messages {
+            let _ = self.out.send(Framing::Line.encode(&message.to_string()));
+        }
+    }
+}
+
+/// Reads the byte chunks sent on a channel as one stream; EOF once every
+/// sender is gone.
+struct ChannelReader {
+    rx: Receiver<Vec<u8>>,
+    buf: Vec<u8>,
+    pos: usize,
+}
+
+impl ChannelReader {
+    fn new(rx: Receiver<Vec<u8>>) -> Self {
+        Self {
+            rx,
+            buf: Vec::new(),
+            pos: 0,
+        }
+    }
+}
+
+impl Read for ChannelReader {
+    fn read(&mut self, out: &mut [u8]) -> io::Result<usize> {
+        while self.pos == self.buf.len() {
+            match self.rx.recv() {
+                Ok(chunk) => {
+                    self.buf = chunk;
+                    self.pos = 0;
+                }
+                Err(_) => return Ok(0),
+            }
+        }
+        let n = out.len().min(self.buf.len() - self.pos);
+        out[..n].copy_from_slice(&self.buf[self.pos..self.pos + n]);
+        self.pos += n;
+        Ok(n)
+    }
+}
+
+/// Sends everything written as one chunk on a channel.
+struct ChannelWriter(Sender<Vec<u8>>);
+
+impl Write for ChannelWriter {
+    fn write(&mut self, data: &[u8]) -> io::Result<usize> {
+        self.0
+            .send(data.to_vec())
+            .map_err(|_| io::ErrorKind::BrokenPipe)?;
+        Ok(data.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_exchanges_are_stored_without_ids_or_secrets() {
+        let request = json!({
+            "jsonrpc": "2.0",
+            "id": 7,
+            "method": "auth/logout",
+            "params": {
+                "session_token": "abc123",
+                "__session": { "username": "kellogg", "session_id": "abc" },
+            },
+        });
+        let response = json!({
+            "jsonrpc": "2.0",
+            "id": 7,
+            "result": { "success": true, "nested": [{ "password": "hunter2" }] },
+        });
+        let exchange = Exchange::new(&request, Vec::new(), &response);
+        assert_eq!(exchange.method, "auth/logout");
+        assert_eq!(exchange.params, json!({ "session_token": REDACTED }));
+        assert_eq!(
+            exchange.response,
+            json!({
+                "jsonrpc": "2.0",
+                "result": { "success": true, "nested": [{ "password": REDACTED }] },
+            })
+        );
+
+        let path =
+            std::env::temp_dir().join(format!("cairn-cassette-{}.jsonl", std::process::id()));
+        let recorder = Recorder::create(&path).unwrap();
+        recorder.record(&exchange);
+        recorder.record(&Exchange::new(&request, vec![json!("chunk")], &response));
+        let loaded = load(&path).unwrap();
+        let _ = std::fs::remove_file(&path);
+        assert_eq!(loaded.len(), 2);
+        assert_eq!(loaded[0], exchange);
+        assert_eq!(loaded[1].partials, vec![json!("chunk")]);
+    }
+}
@@ -0,0 +1,218 @@
+//! Errors returned by Tauri commands.
+//!
+//! Every command returns `Result<_, CommandError>`. It serializes to a flat
+//! object the frontend can branch on without parsing message strings:
+//!
+//! ```json
+//! { "kind": "rpc", "message": "Session required", "code": -32003, "data": null }
+//! { "kind": "kernel", "message": "kernel request timed out after 60s", "reason": "timeout" }
+//! { "kind": "auth", "message": "Invalid or expired session" }
+//! { "kind": "invalid_params", "message": "...", "errors": [{ "path": "/title", "message": "..." }] }
+//! ```
+//!
+//! `kind` is one of `kernel`, `rpc`, `auth`, `policy`, `invalid_params`,
+//! `pty`, `invalid_argument`, `lock_poisoned` or `internal`. `kernel` and
+//! `policy` errors carry a `reason` as well; `invalid_params` carries the
+//! failing fields.
+
+use serde::ser::{Serialize, SerializeMap, Serializer};
+use serde_json::Value;
+use std::sync::PoisonError;
+use thiserror::Error;
+
+use crate::kernel::KernelError;
+use crate::policy::PolicyError;
+use crate::schema::ParamsError;
+
+#[derive(Debug, Clone, Error)]
+pub enum CommandError {
+    /// The kernel could not be reached or did not answer: spawn failure,
+    /// crash, timeout, cancellation. `reason` says which.
+    #[error(transparent)]
+    Kernel(#[from] KernelError),
+    /// The kernel answered with a JSON-RPC error object.
+    #[error("{message}")]
+    Rpc {
+        code: i64,
+        message: String,
+        data: Option<Value>,
+    },
+    /// Missing, invalid or expired session.
+    #[error("{0}")]
+    Auth(String),
+    /// The method policy refused the call (see `policy.rs`).
+    #[error(transparent)]
+    Policy(#[from] PolicyError),
+    /// `params` failed the method's schema (see `schema.rs`).
+    #[error(transparent)]
+    InvalidParams(#[from] ParamsError),
+    #[error("{0}")]
+    Pty(String),
+    /// The command was called with arguments it cannot act on.
+    #[error("{0}")]
+    InvalidArgument(String),
+    #[error("internal state lock poisoned")]
+    LockPoisoned,
+    /// A failure inside the shell itself, e.g. a blocking task panicked.
+    #[error("{0}")]
+    Internal(String),
+}
+
+impl CommandError {
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Self::Kernel(_) => "kernel",
+            Self::Rpc { .. } => "rpc",
+            Self::Auth(_) => "auth",
+            Self::Policy(_) => "policy",
+            Self::InvalidParams(_) => "invalid_params",
+            Self::Pty(_) => "pty",
+            Self::InvalidArgument(_) => "invalid_argument",
+            Self::LockPoisoned => "lock_poisoned",
+            Self::Internal(_) => "internal",
+        }
+    }
+
+    /// Finer-grained cause for `kernel` and `policy` errors.
+    pub fn reason(&self) -> Option<&'static str> {
+        match self {
+            Self::Kernel(e) => Some(e.reason()),
+            Self::Policy(e) => Some(e.reason()),
+            _ => None,
+        }
+    }
+
+    /// The error for a command called without a usable session.
+    pub fn invalid_session() -> Self {
+        Self::Auth("Invalid or expired session".to_string())
+    }
+
+    /// `Internal` error for a `spawn_blocking` task that did not complete.
+    pub fn join(command: &str, err: impl std::fmt::Display) -> Self {
+        Self::Internal(format!("{command} join error: {err}"))
+    }
+}
+
+impl<T> From<PoisonError<T>> for CommandError {
+    fn from(_: PoisonError<T>) -> Self {
+        Self::LockPoisoned
+    }
+}
+
+impl Serialize for CommandError {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut map = serializer.serialize_map(None)?;
+        map.serialize_entry("kind", self.kind())?;
+        map.serialize_entry("message", &self.to_string())?;
+        if let Some(reason) = self.reason() {
+            map.serialize_entry("reason", reason)?;
+        }
+        match self {
+            Self::InvalidParams(e) => map.serialize_entry("errors", &e.errors)?,
+            Self::Rpc { code, data, .. } => {
+                map.serialize_entry("code", code)?;
+                map.serialize_entry("data", data)?;
+            }
+            _ => {}
+        }
+        map.end()
+    }
+}
+
+/// Unwrap a JSON-RPC response envelope: its `result`, or its `error` as
+/// `CommandError::Rpc`.
+pub fn rpc_result(envelope: Value) -> Result<Value, CommandError> {
+    let Value::Object(mut map) = envelope else {
+        return Err(KernelError::InvalidJson("response is not an object".to_string()).into());
+    };
+
+    if let Some(error) = map.remove("error").filter(|e| !e.is_null()) {
+        let code = error.get("code").and_then(Value::as_i64).unwrap_or(-32603);
+        let message = error
+            .get("message")
+            .and_then(Value::as_str)
+            .unwrap_or("unknown error")
+            .to_string();
+        let data = error.get("data").cloned().filter(|d| !d.is_null());
+        return Err(CommandError::Rpc {
+            code,
+            message,
+            data,
+        });
+    }
+
+    Ok(map.remove("result").unwrap_or(Value::Null))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::schema::FieldError;
+    use serde_json::json;
+    use std::time::Duration;
+
+    /// JSON-RPC code the kernel uses when `__session` is missing or invalid.
+    const SESSION_REQUIRED: i64 = -32003;
+
+    #[test]
+    fn test_rpc_result_unwraps_envelope() {
+        let ok = rpc_result(json!({ "jsonrpc": "2.0", "id": 1, "result": { "a": 1 } }));
+        assert_eq!(ok.unwrap(), json!({ "a": 1 }));
+
+        let err = rpc_result(json!({
+            "jsonrpc": "2.0",
+            "id": 2,
+            "error": { "code": SESSION_REQUIRED, "message": "Session required" }
+        }))
+        .unwrap_err();
+        match err {
+            CommandError::Rpc {
+                code,
+                message,
+                data,
+            } => {
+                assert_eq!(code, SESSION_REQUIRED);
+                assert_eq!(message, "Session required");
+                assert_eq!(data, None);
+            }
+            other => panic!("unexpected {other:?}"),
+        }
+    }
+
+    #[test]
+    fn test_serialized_shape() {
+        let rpc = CommandError::Rpc {
+            code: -32602,
+            message: "bad params".to_string(),
+            data: Some(json!({ "field": "title" })),
+        };
+        assert_eq!(
+            serde_json::to_value(&rpc).unwrap(),
+            json!({
+                "kind": "rpc",
+                "message": "bad params",
+                "code": -32602,
+                "data": { "field": "title" }
+            })
+        );
+
+        let kernel = CommandError::from(KernelError::Timeout(Duration::from_secs(5)));
+        let value = serde_json::to_value(&kernel).unwrap();
+        assert_eq!(value["kind"], "kernel");
+        assert_eq!(value["reason"], "timeout");
+
+        let params = CommandError::from(ParamsError {
+            method: "play/acts/create".to_string(),
+            errors: vec![FieldError {
+                path: "/title".to_string(),
+                message: "\"title\" is a required property".to_string(),
+            }],
+        });
+        let value = serde_json::to_value(&params).unwrap();
+        assert_eq!(value["kind"], "invalid_params");
+        assert_eq!(value["errors"][0]["path"], "/title");
+
+        let value = serde_json::to_value(CommandError::LockPoisoned).unwrap();
+        assert_eq!(value["kind"], "lock_poisoned");
+    }
+}
@@ -0,0 +1,265 @@
+//! Message framing on the kernel connection.
+//!
+//! Messages start out newline-delimited. During `initialize` the shell
+//! offers `content-length` framing, LSP style:
+//!
+//! ```text
+//! Content-Length: 42\r\n
+//! \r\n
+//! {"jsonrpc":"2.0","id":1,"result":{...}}
+//! ```
+//!
+//! and switches its writer over if the kernel accepts. Reading never depends
+//! on the negotiation: every message announces its own framing, so replies
+//! written either way (including the `initialize` reply itself) are read
+//! correctly. A large payload then arrives as one sized read instead of one
+//! huge line, and a newline inside it no longer matters.
+//!
+//! With `content-length` framing a frame may also carry raw bytes instead of
+//! JSON, marked by `Content-Type: application/octet-stream`. Bytes going to
+//! the kernel name the upload they belong to in a `Blob-Id` header; bytes
+//! coming back name the request they answer in `Request-Id` (see `blob.rs`).
+//!
+//! Anything else on the stream, such as a stray `print()` in the kernel, is
+//! handed back as `Frame::Stray` so the caller can log it and carry on. That
+//! includes bare lines that open like JSON but do not parse, and binary
+//! frames that name no request.
+
+use serde::de::IgnoredAny;
+use serde::{Deserialize, Serialize};
+use std::io::{self, BufRead};
+
+/// Largest frame body accepted. A bigger length means the stream is corrupt.
+pub const MAX_FRAME_BYTES: usize = 256 * 1024 * 1024;
+
+/// Content type of a binary frame.
+pub const OCTET_STREAM: &str = "application/octet-stream";
+
+const CONTENT_LENGTH: &str = "content-length:";
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Framing {
+    /// One JSON message per line.
+    #[default]
+    Line,
+    /// `Content-Length` header, blank line, then exactly that many bytes.
+    ContentLength,
+}
+
+impl Framing {
+    /// What the shell offers in `initialize`, most preferred first.
+    pub const OFFERED: &'static [Framing] = &[Framing::ContentLength, Framing::Line];
+
+    /// `message` ready to write.
+    pub fn encode(self, message: &str) -> Vec<u8> {
+        match self {
+            Self::Line => {
+                let mut out = Vec::with_capacity(message.len() + 1);
+                out.extend_from_slice(message.as_bytes());
+                out.push(b'\n');
+                out
+            }
+            Self::ContentLength => {
+                let mut out = format!("Content-Length: {}\r\n\r\n", message.len()).into_bytes();
+                out.extend_from_slice(message.as_bytes());
+                out
+            }
+        }
+    }
+}
+
+/// A binary frame holding one chunk of the upload `blob_id`. Only valid
+/// once `content-length` framing has been negotiated.
+pub fn encode_blob_chunk(blob_id: &str, data: &[u8]) -> Vec<u8> {
+    let mut out = format!(
+        "Content-Length: {}\r\nContent-Type: {OCTET_STREAM}\r\nBlob-Id: {blob_id}\r\n\r\n",
+        data.len()
+    )
+    .into_bytes();
+    out.extend_from_slice(data);
+    out
+}
+
+/// One unit read from the kernel.
+#[derive(Debug, PartialEq, Eq)]
+pub enum Frame {
+    /// A JSON message body, in either framing.
+    Message(Vec<u8>),
+    /// Raw bytes sent ahead of the reply to request `request_id`.
+    Bytes { request_id: u64, data: Vec<u8> },
+    /// A line that is not part of the protocol.
+    Stray(String),
+}
+
+/// Read the next frame, skipping blank lines. `None` at end of stream.
+///
+/// Bare lines that parse as JSON are messages; any other line, even one
+/// that opens with `{` or `[`, is `Stray`. A framed body is always a
+/// `Message`, so a garbled one still reaches the caller's parser.
+pub fn read_frame(reader: &mut impl BufRead) -> io::Result<Option<Frame>> {
+    let mut line = Vec::new();
+    loop {
+        line.clear();
+        if reader.read_until(b'\n', &mut line)? == 0 {
+            return Ok(None);
+        }
+        let text = String::from_utf8_lossy(&line);
+        let trimmed = text.trim();
+        if trimmed.is_empty() {
+            continue;
+        }
+
+        if let Some(length) = header_value(trimmed, CONTENT_LENGTH) {
+            let length = parse_length(length)?;
+            let headers = read_headers(reader)?;
+            let mut body = vec![0; length];
+            reader.read_exact(&mut body)?;
+            return binary_frame(&headers, body).map(Some);
+        }
+
+        let looks_like_json = trimmed.starts_with('{') || trimmed.starts_with('[');
+        if looks_like_json && serde_json::from_str::<IgnoredAny>(trimmed).is_ok() {
+            return Ok(Some(Frame::Message(trimmed.as_bytes().to_vec())));
+        }
+        return Ok(Some(Frame::Stray(trimmed.to_string())));
+    }
+}
+
+/// The value of header line `line` if it is the header `name` (lowercase,
+/// with its colon).
+fn header_value<'a>(line: &'a str, name: &str) -> Option<&'a str> {
+    let prefix = line.get(..name.len())?;
+    prefix
+        .eq_ignore_ascii_case(name)
+        .then(|| line[name.len()..].trim())
+}
+
+/// Classify a frame body by the headers that followed `Content-Length`.
+/// Bytes that name no request are `Stray`; the frame has been consumed, so
+/// the stream stays in step.
+fn binary_frame(headers: &[String], body: Vec<u8>) -> io::Result<Frame> {
+    let header = |name| headers.iter().find_map(|h| header_value(h, name));
+    if !header("content-type:").is_some_and(|t| t.eq_ignore_ascii_case(OCTET_STREAM)) {
+        return Ok(Frame::Message(body));
+    }
+    let Some(request_id) = header("request-id:").and_then(|id| id.parse().ok()) else {
+        return Ok(Frame::Stray(format!(
+            "binary frame of {} bytes without a Request-Id",
+            body.len()
+        )));
+    };
+    Ok(Frame::Bytes {
+        request_id,
+        data: body,
+    })
+}
+
+fn parse_length(value: &str) -> io::Result<usize> {
+    let length: usize = value.parse().map_err(|_| {
+        io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("bad Content-Length {value:?}"),
+        )
+    })?;
+    if length > MAX_FRAME_BYTES {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("frame of {length} bytes exceeds the {MAX_FRAME_BYTES} byte limit"),
+        ));
+    }
+    Ok(length)
+}
+
+/// Read any further header lines, up to and including the blank line.
+fn read_headers(reader: &mut impl BufRead) -> io::Result<Vec<String>> {
+    let mut headers = Vec::new();
+    let mut line = Vec::new();
+    loop {
+        line.clear();
+        if reader.read_until(b'\n', &mut line)? == 0 {
+            return Err(io::ErrorKind::UnexpectedEof.into());
+        }
+        let header = String::from_utf8_lossy(&line);
+        let header = header.trim();
+        if header.is_empty() {
+            return Ok(headers);
+        }
+        headers.push(header.to_string());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn frames(input: &[u8]) -> Vec<Frame> {
+        let mut reader = input;
+        let mut out = Vec::new();
+        while let Some(frame) = read_frame(&mut reader).unwrap() {
+            out.push(frame);
+        }
+        out
+    }
+
+    #[test]
+    fn test_mixed_framings_and_stray_output() {
+        let body = "{\"id\":2,\"result\":\"two\nlines\"}";
+        let mut input = b"{\"id\":1}\nloading model...\n\n".to_vec();
+        input.extend(Framing::ContentLength.encode(body));
+        input.extend(b"{'debug': True}\n[1, 2\n");
+        input.extend(Framing::Line.encode("[{\"id\":3}]"));
+
+        assert_eq!(
+            frames(&input),
+            vec![
+                Frame::Message(b"{\"id\":1}".to_vec()),
+                Frame::Stray("loading model...".to_string()),
+                Frame::Message(body.as_bytes().to_vec()),
+                Frame::Stray("{'debug': True}".to_string()),
+                Frame::Stray("[1, 2".to_string()),
+                Frame::Message(b"[{\"id\":3}]".to_vec()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_extra_headers_and_bad_lengths() {
+        let input = b"content-length: 2\r\nContent-Type: application/json\r\n\r\n{}";
+        assert_eq!(frames(input), vec![Frame::Message(b"{}".to_vec())]);
+
+        let mut bad: &[u8] = b"Content-Length: lots\r\n\r\n{}";
+        assert!(read_frame(&mut bad).is_err());
+
+        let huge = format!("Content-Length: {}\r\n\r\n", MAX_FRAME_BYTES + 1);
+        assert!(read_frame(&mut huge.as_bytes()).is_err());
+
+        // Cut off mid-body.
+        let mut short: &[u8] = b"Content-Length: 10\r\n\r\n{}";
+        assert!(read_frame(&mut short).is_err());
+    }
+
+    #[test]
+    fn test_binary_frames() {
+        let input = b"Content-Length: 3\r\nContent-Type: application/octet-stream\r\nRequest-Id: 7\r\n\r\n\0\n\xff";
+        assert_eq!(
+            frames(input),
+            vec![Frame::Bytes {
+                request_id: 7,
+                data: vec![0, b'\n', 0xff],
+            }]
+        );
+
+        // Bytes for no request are skipped over whole, e.g. an upload chunk
+        // echoed back, and reading carries on after them.
+        let mut input = encode_blob_chunk("b1", b"abc");
+        input.extend(Framing::Line.encode("{\"id\":1}"));
+        assert_eq!(
+            frames(&input),
+            vec![
+                Frame::Stray("binary frame of 3 bytes without a Request-Id".to_string()),
+                Frame::Message(b"{\"id\":1}".to_vec()),
+            ]
+        );
+    }
+}
@@ -1,11 +1,32 @@
-use std::io::{BufRead, BufReader, Write};
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::io::{BufRead, BufReader, Read, Write};
+#[cfg(unix)]
+use std::os::unix::{
+    fs::{DirBuilderExt, FileTypeExt, MetadataExt},
+    io::AsRawFd,
+    net::UnixStream,
+};
+#[cfg(unix)]
 use std::path::{Path, PathBuf};
-use std::process::{Child, ChildStdin, Command, Stdio};
+use std::process::{Child, ChildStderr, ChildStdin, ExitStatus, Stdio};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError, Sender};
+use std::sync::{Arc, Condvar, Mutex, MutexGuard, Weak};
+use std::thread;
+use std::time::{Duration, Instant};
 
+use serde::{Deserialize, Serialize};
 use serde_json::{json, Value};
 use thiserror::Error;
 
-#[derive(Debug, Error)]
+use crate::cassette::{Exchange, Recorder};
+use crate::error::CommandError;
+use crate::framing::{encode_blob_chunk, read_frame, Frame, Framing};
+use crate::kernel_config::KernelLaunch;
+use crate::kernel_log::{KernelLog, DEFAULT_CAPACITY, STDOUT_PREFIX};
+use crate::metrics::KernelMetrics;
+
+#[derive(Debug, Clone, Error)]
 pub enum KernelError {
     #[error("kernel not started")]
     NotStarted,
@@ -19,64 +40,408 @@ pub enum KernelError {
     InvalidJson(String),
     #[error("kernel process exited")]
     Exited,
+    #[error("kernel request timed out after {0:?}")]
+    Timeout(Duration),
+    #[error("kernel request cancelled")]
+    Cancelled,
+    #[error("kernel handshake failed: {0}")]
+    HandshakeFailed(String),
+    #[error("kernel speaks protocol {kernel}, shell supports {min}..={max}")]
+    IncompatibleProtocol { kernel: u32, min: u32, max: u32 },
+    #[error("kernel does not support {0}")]
+    Unsupported(&'static str),
+}
+
+impl KernelError {
+    /// Stable snake_case name for the frontend to branch on.
+    pub fn reason(&self) -> &'static str {
+        match self {
+            Self::NotStarted => "not_started",
+            Self::SpawnFailed(_) => "spawn_failed",
+            Self::StdinWriteFailed(_) => "write_failed",
+            Self::StdoutReadFailed(_) => "read_failed",
+            Self::InvalidJson(_) => "invalid_json",
+            Self::Exited => "exited",
+            Self::Timeout(_) => "timeout",
+            Self::Cancelled => "cancelled",
+            Self::HandshakeFailed(_) => "handshake_failed",
+            Self::IncompatibleProtocol { .. } => "incompatible_protocol",
+            Self::Unsupported(_) => "unsupported",
+        }
+    }
+}
+
+/// Kernel protocol versions this shell can talk to. Bump `PROTOCOL_VERSION`
+/// when the envelope or a shell-visible convention changes, and raise
+/// `MIN_PROTOCOL_VERSION` once older kernels are no longer usable.
+pub const PROTOCOL_VERSION: u32 = 1;
+pub const MIN_PROTOCOL_VERSION: u32 = 1;
+
+/// How long a freshly spawned kernel has to import, migrate its database and
+/// answer `initialize`.
+const HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// After closing stdin, how long the kernel gets to finish the request in
+/// hand (and any SQLite write) and exit on its own.
+pub const SHUTDOWN_GRACE: Duration = Duration::from_secs(5);
+
+/// How long SIGTERM gets before the process is killed outright.
+const TERM_GRACE: Duration = Duration::from_secs(2);
+
+/// What the kernel reported in its `initialize` reply.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct KernelInfo {
+    pub protocol_version: u32,
+    pub kernel_version: String,
+    #[serde(default)]
+    pub capabilities: Vec<String>,
+    /// Framing the kernel accepted for messages it reads from us; kernels
+    /// that predate the offer reply without one and keep reading lines.
+    #[serde(default)]
+    pub framing: Framing,
+}
+
+/// Capability a kernel reports when it accepts JSON-RPC batch arrays.
+pub const BATCH_CAPABILITY: &str = "batch";
+
+/// Capability a kernel reports when it takes and returns raw bytes in
+/// binary frames (see `blob.rs`).
+pub const BLOBS_CAPABILITY: &str = "blobs";
+
+impl KernelInfo {
+    pub fn has_capability(&self, capability: &str) -> bool {
+        self.capabilities.iter().any(|c| c == capability)
+    }
+}
+
+/// How long a request may wait for its response unless the method has an
+/// entry in `METHOD_TIMEOUTS`.
+pub const DEFAULT_REQUEST_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// Per-method overrides for calls that legitimately run long: LLM round
+/// trips, bulk indexing, and the Polkit dialog behind `auth/login`.
+const METHOD_TIMEOUTS: &[(&str, Duration)] = &[
+    ("auth/login", Duration::from_secs(120)),
+    ("chat/respond", Duration::from_secs(300)),
+    ("reos/converse", Duration::from_secs(180)),
+    ("reos/execute", Duration::from_secs(300)),
+    ("memory/extract", Duration::from_secs(180)),
+    ("memory/index/batch", Duration::from_secs(300)),
+    ("documents/insert", Duration::from_secs(180)),
+    ("lifecycle/briefing/generate", Duration::from_secs(180)),
+    ("conversation/archive/preview", Duration::from_secs(180)),
+    ("conversation/archive/confirm", Duration::from_secs(300)),
+    ("archive/assess", Duration::from_secs(180)),
+];
+
+/// Notification sent to the kernel when a caller gives up on a request,
+/// so it can skip or abandon the work (params: `{ "id": <request id> }`).
+const CANCEL_METHOD: &str = "rpc/cancel";
+
+/// Timeout applied to `method` when the caller does not supply one.
+pub fn timeout_for(method: &str) -> Duration {
+    METHOD_TIMEOUTS
+        .iter()
+        .find(|(m, _)| *m == method)
+        .map(|(_, t)| *t)
+        .unwrap_or(DEFAULT_REQUEST_TIMEOUT)
+}
+
+/// Per-call options for `KernelProcess::request_with`.
+#[derive(Debug, Default, Clone)]
+pub struct RequestOptions {
+    /// Overrides `timeout_for(method)`.
+    pub timeout: Option<Duration>,
+    /// Caller-chosen handle that `KernelProcess::cancel` can later refer to.
+    pub cancel_key: Option<String>,
+}
+
+/// Output that arrives ahead of a request's reply.
+enum Chunk {
+    /// An intermediate chunk of a streaming response.
+    Partial(Value),
+    /// Raw bytes from a binary frame.
+    Bytes(Vec<u8>),
+}
+
+/// What the reader thread hands to a blocked caller.
+enum Reply {
+    /// More will follow.
+    Chunk(Chunk),
+    /// The full response envelope (or why there will not be one).
+    Done(Result<Value, KernelError>),
 }
 
+/// A caller blocked in `KernelProcess::request_with`.
+struct Waiter {
+    tx: Sender<Reply>,
+    cancel_key: Option<String>,
+}
+
+/// Waiters for in-flight requests, keyed by JSON-RPC id.
+///
+/// Shared between callers of `KernelProcess::request` (which register a
+/// waiter before writing) and the stdout reader thread (which removes the
+/// waiter when the matching response arrives). Once the reader sees EOF the
+/// table is closed and every later registration fails fast.
+#[derive(Default)]
+struct PendingTable {
+    waiters: HashMap<u64, Waiter>,
+    /// Cancel keys of in-flight requests, mapped to their JSON-RPC ids (the
+    /// calls of a batch share one key).
+    cancel_keys: HashMap<String, Vec<u64>>,
+    closed: bool,
+}
+
+type SharedPending = Arc<Mutex<PendingTable>>;
+
+impl PendingTable {
+    /// Remove the waiter for `id`, along with its cancel key.
+    fn take(&mut self, id: u64) -> Option<Waiter> {
+        let waiter = self.waiters.remove(&id)?;
+        if let Some(key) = &waiter.cancel_key {
+            if let Some(ids) = self.cancel_keys.get_mut(key) {
+                ids.retain(|&other| other != id);
+                if ids.is_empty() {
+                    self.cancel_keys.remove(key);
+                }
+            }
+        }
+        Some(waiter)
+    }
+
+    /// Fail every outstanding waiter with the error produced by `err`.
+    fn fail_all(&mut self, err: impl Fn() -> KernelError) {
+        self.cancel_keys.clear();
+        for (_, waiter) in self.waiters.drain() {
+            let _ = waiter.tx.send(Reply::Done(Err(err())));
+        }
+    }
+}
+
+/// A connected Python kernel, spawned by this shell or attached to over a
+/// socket (see `KernelTransport`).
+///
+/// Requests are multiplexed: any number of threads may call `request`
+/// concurrently. Each call registers a waiter under a fresh JSON-RPC id,
+/// writes its message to the transport, and blocks until the reader thread
+/// routes the matching response back to it.
 pub struct KernelProcess {
-    child: Child,
-    stdin: ChildStdin,
-    stdout: BufReader<std::process::ChildStdout>,
-    next_id: u64,
+    /// `None` when attached to a kernel this shell did not spawn.
+    child: Option<Mutex<Child>>,
+    /// `None` once `shutdown` has closed it.
+    writer: Mutex<Option<Box<dyn Write + Send>>>,
+    /// How outgoing messages are framed; lines until `initialize` settles it.
+    framing: Framing,
+    lifeline: Mutex<Option<ChildStdin>>,
+    disconnect: Option<Box<dyn Fn() + Send + Sync>>,
+    pending: SharedPending,
+    next_id: AtomicU64,
+    info: KernelInfo,
+    metrics: Arc<KernelMetrics>,
+    recorder: Option<Arc<Recorder>>,
+    /// Supervisor restart epoch this process was started in; 0 if started
+    /// directly.
+    epoch: u64,
 }
 
-fn find_repo_venv_python() -> Option<PathBuf> {
-    // Walk upward from the current executable looking for `.venv/bin/python`.
-    // This makes `tauri dev` work reliably in a monorepo-style checkout.
-    let exe = std::env::current_exe().ok()?;
-    let mut dir = exe.parent()?.to_path_buf();
+/// Callback for id-less messages pushed by the kernel: `(method, params)`.
+pub type NotificationHandler = Arc<dyn Fn(&str, Value) + Send + Sync>;
 
-    for _ in 0..12 {
-        let candidate = dir.join(".venv").join("bin").join("python");
-        if candidate.is_file() {
-            return Some(candidate);
+/// Callbacks and sinks a kernel process reports into.
+pub struct KernelHooks {
+    /// Runs on the reader thread for every notification.
+    pub on_notification: NotificationHandler,
+    /// Runs on the reader thread once stdout closes, after every in-flight
+    /// request has been failed with `KernelError::Exited`.
+    pub on_exit: Box<dyn FnOnce() + Send>,
+    /// Receives every stderr line.
+    pub log: Arc<KernelLog>,
+    /// Records every request's outcome, latency and queue wait.
+    pub metrics: Arc<KernelMetrics>,
+    /// Writes every completed exchange to a cassette (see `cassette.rs`).
+    pub recorder: Option<Arc<Recorder>>,
+}
+
+/// Forward kernel stderr into `log` line by line until the pipe closes.
+fn stderr_loop(stderr: impl BufRead, log: Arc<KernelLog>) {
+    let mut stderr = stderr;
+    let mut buf = Vec::new();
+    loop {
+        buf.clear();
+        match stderr.read_until(b'\n', &mut buf) {
+            Ok(0) | Err(_) => break,
+            Ok(_) => {}
+        }
+        let line = String::from_utf8_lossy(&buf);
+        let line = line.trim_end();
+        if line.is_empty() {
+            continue;
         }
-        if !dir.pop() {
-            break;
+        // Keep the old inherited-stderr behaviour for `tauri dev`.
+        eprintln!("{line}");
+        log.push(line);
+    }
+}
+
+/// Read JSON-RPC messages from the kernel and route each one.
+///
+/// Runs on a dedicated thread for the lifetime of the process. Messages may
+/// arrive as lines or as `Content-Length` frames (see `framing.rs`).
+/// Responses go to the waiter registered under their id; messages carrying
+/// an id and a `partial` member (instead of `result`/`error`) are streaming
+/// chunks and leave the waiter in place, as do binary frames addressed to
+/// the request. Id-less messages with a `method` are notifications and go
+/// to `on_notification`. A message holding an array is the reply to a batch
+/// and each element is routed on its own. Output that is not a message at
+/// all, such as a stray `print()`, goes to `log`. Anything else, and
+/// responses whose waiter has already gone away, is dropped.
+fn read_loop(
+    stdout: impl BufRead,
+    pending: SharedPending,
+    on_notification: NotificationHandler,
+    log: &KernelLog,
+) {
+    let mut stdout = stdout;
+
+    loop {
+        let body = match read_frame(&mut stdout) {
+            Ok(None) => break,
+            Ok(Some(Frame::Message(body))) => body,
+            Ok(Some(Frame::Bytes { request_id, data })) => {
+                if let Ok(table) = pending.lock() {
+                    if let Some(waiter) = table.waiters.get(&request_id) {
+                        let _ = waiter.tx.send(Reply::Chunk(Chunk::Bytes(data)));
+                    }
+                }
+                continue;
+            }
+            Ok(Some(Frame::Stray(line))) => {
+                let line = format!("{STDOUT_PREFIX}{line}");
+                eprintln!("{line}");
+                log.push(&line);
+                continue;
+            }
+            Err(e) => {
+                let msg = e.to_string();
+                if let Ok(mut table) = pending.lock() {
+                    table.fail_all(|| KernelError::StdoutReadFailed(msg.clone()));
+                }
+                break;
+            }
+        };
+
+        let parsed: Value = match serde_json::from_slice(&body) {
+            Ok(v) => v,
+            Err(e) => {
+                // Only a framed body gets here garbled (bare lines that do
+                // not parse are `Stray`). We cannot tell which request it
+                // belonged to, so everything currently in flight fails.
+                let msg = e.to_string();
+                if let Ok(mut table) = pending.lock() {
+                    table.fail_all(|| KernelError::InvalidJson(msg.clone()));
+                }
+                continue;
+            }
+        };
+
+        match parsed {
+            Value::Array(messages) => {
+                for message in messages {
+                    route_message(message, &pending, &on_notification);
+                }
+            }
+            message => route_message(message, &pending, &on_notification),
         }
     }
 
-    None
+    if let Ok(mut table) = pending.lock() {
+        table.closed = true;
+        table.fail_all(|| KernelError::Exited);
+    }
+}
+
+/// Whether a request counts as an error in the metrics: it never completed,
+/// or the kernel answered with an error object.
+fn failed(result: &Result<Value, KernelError>) -> bool {
+    match result {
+        Ok(envelope) => envelope.get("error").is_some_and(|e| !e.is_null()),
+        Err(_) => true,
+    }
 }
 
-fn python_command() -> String {
-    // Highest priority: explicit override.
-    if let Ok(p) = std::env::var("CAIRN_PYTHON") {
-        let p = p.trim();
-        if !p.is_empty() {
-            return p.to_string();
+/// Deliver one parsed message to its waiter or to `on_notification`.
+fn route_message(message: Value, pending: &SharedPending, on_notification: &NotificationHandler) {
+    let Some(id) = message.get("id").and_then(Value::as_u64) else {
+        if let Some(method) = message.get("method").and_then(Value::as_str) {
+            let params = message.get("params").cloned().unwrap_or(Value::Null);
+            on_notification(method, params);
+        }
+        return;
+    };
+
+    if let Some(partial) = message.get("partial") {
+        if let Ok(table) = pending.lock() {
+            if let Some(waiter) = table.waiters.get(&id) {
+                let _ = waiter
+                    .tx
+                    .send(Reply::Chunk(Chunk::Partial(partial.clone())));
+            }
         }
+        return;
     }
 
-    // Next: auto-detect `.venv/bin/python`.
-    if let Some(p) = find_repo_venv_python() {
-        return p.to_string_lossy().to_string();
+    let waiter = pending.lock().ok().and_then(|mut t| t.take(id));
+    if let Some(waiter) = waiter {
+        let _ = waiter.tx.send(Reply::Done(Ok(message)));
     }
+}
 
-    // Fallback: whatever is on PATH.
-    "python".to_string()
+/// A live link to a kernel: its byte streams plus whatever controls its
+/// lifetime.
+pub struct Connection {
+    pub reader: Box<dyn Read + Send>,
+    pub writer: Box<dyn Write + Send>,
+    /// The kernel process, when this shell spawned it.
+    pub child: Option<Child>,
+    /// The kernel's stderr, when this shell spawned it.
+    pub stderr: Option<ChildStderr>,
+    /// Stdin of a kernel spawned in socket mode. The kernel exits once it
+    /// closes, so dropping it is the graceful shutdown request.
+    pub lifeline: Option<ChildStdin>,
+    /// Close both directions so the reader sees EOF. This is all "stopping"
+    /// means for a kernel the shell attached to but does not own.
+    pub disconnect: Option<Box<dyn Fn() + Send + Sync>>,
 }
 
-impl KernelProcess {
-    pub fn start() -> Result<Self, KernelError> {
-        // Dev-mode: prefer CAIRN_PYTHON or a repo `.venv/bin/python`.
-        // Packaging: likely ship a Python runtime or use a platform sidecar.
-        let python = python_command();
-        let mut child = Command::new(&python)
-            .args(["-m", "cairn.ui_rpc_server"])
+/// How the shell reaches a kernel.
+pub trait KernelTransport: Send + Sync {
+    fn connect(&self) -> Result<Connection, KernelError>;
+}
+
+/// Spawn a private kernel and speak JSON-RPC over its stdin/stdout.
+pub struct StdioTransport {
+    launch: KernelLaunch,
+}
+
+impl StdioTransport {
+    pub fn new(launch: KernelLaunch) -> Self {
+        Self { launch }
+    }
+}
+
+impl KernelTransport for StdioTransport {
+    fn connect(&self) -> Result<Connection, KernelError> {
+        let mut child = self
+            .launch
+            .command()
             .stdin(Stdio::piped())
             .stdout(Stdio::piped())
-            .stderr(Stdio::inherit())
+            .stderr(Stdio::piped())
             .spawn()
-            .map_err(|e| KernelError::SpawnFailed(e.to_string()))?;
+            .map_err(|e| {
+                KernelError::SpawnFailed(format!("{}: {e}", self.launch.program.display()))
+            })?;
 
         let stdin = child
             .stdin
@@ -86,23 +451,539 @@ impl KernelProcess {
             .stdout
             .take()
             .ok_or_else(|| KernelError::SpawnFailed("missing stdout".to_string()))?;
+        let stderr = child.stderr.take();
 
-        Ok(Self {
+        Ok(Connection {
+            reader: Box::new(stdout),
+            writer: Box::new(stdin),
+            child: Some(child),
+            stderr,
+            lifeline: None,
+            disconnect: None,
+        })
+    }
+}
+
+/// Speak JSON-RPC over a Unix socket, so other processes (a second window,
+/// a CLI, a test harness) can share one kernel.
+///
+/// `connect` attaches to the kernel already listening on `path` if there is
+/// one; otherwise it spawns the kernel with `--socket <path>` and waits for
+/// the socket to appear. Either way the socket must be owned by this user
+/// with mode 0600, and the peer process must run as this user.
+#[cfg(unix)]
+pub struct UnixSocketTransport {
+    path: PathBuf,
+    launch: KernelLaunch,
+}
+
+#[cfg(unix)]
+impl UnixSocketTransport {
+    pub fn new(path: PathBuf, launch: KernelLaunch) -> Self {
+        Self { path, launch }
+    }
+
+    fn open(
+        stream: UnixStream,
+        child: Option<Child>,
+        lifeline: Option<ChildStdin>,
+        stderr: Option<ChildStderr>,
+    ) -> Result<Connection, KernelError> {
+        let clone = |s: &UnixStream| {
+            s.try_clone()
+                .map_err(|e| KernelError::SpawnFailed(format!("socket clone: {e}")))
+        };
+        let writer = clone(&stream)?;
+        let control = clone(&stream)?;
+        Ok(Connection {
+            reader: Box::new(stream),
+            writer: Box::new(writer),
             child,
-            stdin,
-            stdout: BufReader::new(stdout),
-            next_id: 1,
+            stderr,
+            lifeline,
+            disconnect: Some(Box::new(move || {
+                let _ = control.shutdown(std::net::Shutdown::Both);
+            })),
         })
     }
 
-    pub fn request(&mut self, method: &str, params: Value) -> Result<Value, KernelError> {
-        if let Some(status) = self.child.try_wait().map_err(|e| KernelError::Exited)? {
-            let _ = status;
-            return Err(KernelError::Exited);
+    fn spawn(&self) -> Result<Connection, KernelError> {
+        if let Some(dir) = self.path.parent() {
+            std::fs::DirBuilder::new()
+                .recursive(true)
+                .mode(0o700)
+                .create(dir)
+                .map_err(|e| KernelError::SpawnFailed(format!("{}: {e}", dir.display())))?;
+        }
+
+        let mut child = self
+            .launch
+            .command()
+            .arg("--socket")
+            .arg(&self.path)
+            .arg("--exit-with-parent")
+            .stdin(Stdio::piped())
+            .stdout(Stdio::null())
+            .stderr(Stdio::piped())
+            .spawn()
+            .map_err(|e| {
+                KernelError::SpawnFailed(format!("{}: {e}", self.launch.program.display()))
+            })?;
+        let lifeline = child.stdin.take();
+        let stderr = child.stderr.take();
+
+        let deadline = Instant::now() + SOCKET_WAIT;
+        loop {
+            match connect_verified(&self.path) {
+                Ok(stream) => return Self::open(stream, Some(child), lifeline, stderr),
+                Err(e) if is_absent(&e) && Instant::now() < deadline => {}
+                Err(e) => {
+                    let _ = child.kill();
+                    let _ = child.wait();
+                    return Err(KernelError::SpawnFailed(format!(
+                        "kernel socket {}: {e}",
+                        self.path.display()
+                    )));
+                }
+            }
+            if let Ok(Some(status)) = child.try_wait() {
+                return Err(KernelError::SpawnFailed(format!(
+                    "kernel exited before opening its socket ({status})"
+                )));
+            }
+            thread::sleep(Duration::from_millis(50));
+        }
+    }
+}
+
+#[cfg(unix)]
+impl KernelTransport for UnixSocketTransport {
+    fn connect(&self) -> Result<Connection, KernelError> {
+        match connect_verified(&self.path) {
+            Ok(stream) => Self::open(stream, None, None, None),
+            // Nothing listening (or a stale socket file): start our own.
+            Err(e) if is_absent(&e) => self.spawn(),
+            Err(e) => Err(KernelError::SpawnFailed(format!(
+                "refusing kernel socket {}: {e}",
+                self.path.display()
+            ))),
+        }
+    }
+}
+
+/// How long a freshly spawned socket-mode kernel has to start listening.
+#[cfg(unix)]
+const SOCKET_WAIT: Duration = Duration::from_secs(30);
+
+#[cfg(unix)]
+fn is_absent(e: &std::io::Error) -> bool {
+    matches!(
+        e.kind(),
+        std::io::ErrorKind::NotFound | std::io::ErrorKind::ConnectionRefused
+    )
+}
+
+/// Connect to `path` after checking that the socket is ours and private,
+/// then check that the process on the other end runs as us too.
+#[cfg(unix)]
+fn connect_verified(path: &Path) -> std::io::Result<UnixStream> {
+    use std::io::{Error, ErrorKind};
+
+    // SAFETY: getuid(2) cannot fail.
+    let uid = unsafe { libc::getuid() };
+
+    let meta = std::fs::symlink_metadata(path)?;
+    if !meta.file_type().is_socket() {
+        return Err(Error::new(ErrorKind::PermissionDenied, "not a socket"));
+    }
+    if meta.uid() != uid {
+        return Err(Error::new(
+            ErrorKind::PermissionDenied,
+            format!("owned by uid {}", meta.uid()),
+        ));
+    }
+    if meta.mode() & 0o077 != 0 {
+        return Err(Error::new(
+            ErrorKind::PermissionDenied,
+            format!("mode {:o} is not 0600", meta.mode() & 0o777),
+        ));
+    }
+
+    let stream = UnixStream::connect(path)?;
+    let peer = peer_uid(&stream)?;
+    if peer != uid {
+        return Err(Error::new(
+            ErrorKind::PermissionDenied,
+            format!("peer runs as uid {peer}"),
+        ));
+    }
+    Ok(stream)
+}
+
+#[cfg(target_os = "linux")]
+fn peer_uid(stream: &UnixStream) -> std::io::Result<u32> {
+    let mut cred = libc::ucred {
+        pid: 0,
+        uid: 0,
+        gid: 0,
+    };
+    let mut len = std::mem::size_of::<libc::ucred>() as libc::socklen_t;
+    // SAFETY: `cred` and `len` are valid for writes of the sizes passed.
+    let rc = unsafe {
+        libc::getsockopt(
+            stream.as_raw_fd(),
+            libc::SOL_SOCKET,
+            libc::SO_PEERCRED,
+            (&mut cred as *mut libc::ucred).cast(),
+            &mut len,
+        )
+    };
+    if rc != 0 {
+        return Err(std::io::Error::last_os_error());
+    }
+    Ok(cred.uid)
+}
+
+#[cfg(all(unix, not(target_os = "linux")))]
+fn peer_uid(stream: &UnixStream) -> std::io::Result<u32> {
+    let mut uid: libc::uid_t = 0;
+    let mut gid: libc::gid_t = 0;
+    // SAFETY: `uid` and `gid` are valid for writes.
+    if unsafe { libc::getpeereid(stream.as_raw_fd(), &mut uid, &mut gid) } != 0 {
+        return Err(std::io::Error::last_os_error());
+    }
+    Ok(uid)
+}
+
+/// The transport selected by `launch`.
+pub fn transport_for(launch: &KernelLaunch) -> Box<dyn KernelTransport> {
+    match &launch.socket {
+        #[cfg(unix)]
+        Some(path) => Box::new(UnixSocketTransport::new(path.clone(), launch.clone())),
+        #[cfg(not(unix))]
+        Some(_) => {
+            eprintln!("[kernel] socket transport needs Unix; using stdio");
+            Box::new(StdioTransport::new(launch.clone()))
+        }
+        None => Box::new(StdioTransport::new(launch.clone())),
+    }
+}
+
+impl KernelProcess {
+    /// Connect through `transport` and start the reader threads, then
+    /// perform the `initialize` handshake. The connection is torn down if the
+    /// handshake fails or the kernel speaks an unsupported protocol version.
+    pub fn start(transport: &dyn KernelTransport, hooks: KernelHooks) -> Result<Self, KernelError> {
+        let Connection {
+            reader,
+            writer,
+            child,
+            stderr,
+            lifeline,
+            disconnect,
+        } = transport.connect()?;
+
+        let KernelHooks {
+            on_notification,
+            on_exit,
+            log,
+            metrics,
+            recorder,
+        } = hooks;
+
+        let stdout_log = log.clone();
+        if let Some(stderr) = stderr {
+            thread::Builder::new()
+                .name("kernel-stderr".to_string())
+                .spawn(move || stderr_loop(BufReader::new(stderr), log))
+                .map_err(|e| KernelError::SpawnFailed(format!("stderr thread: {e}")))?;
+        }
+
+        let pending = SharedPending::default();
+        let reader_pending = pending.clone();
+        thread::Builder::new()
+            .name("kernel-stdout".to_string())
+            .spawn(move || {
+                read_loop(
+                    BufReader::new(reader),
+                    reader_pending,
+                    on_notification,
+                    &stdout_log,
+                );
+                on_exit();
+            })
+            .map_err(|e| KernelError::SpawnFailed(format!("reader thread: {e}")))?;
+
+        let mut proc = Self {
+            child: child.map(Mutex::new),
+            writer: Mutex::new(Some(writer)),
+            framing: Framing::Line,
+            lifeline: Mutex::new(lifeline),
+            disconnect,
+            pending,
+            next_id: AtomicU64::new(1),
+            info: KernelInfo::default(),
+            metrics,
+            recorder,
+            epoch: 0,
+        };
+
+        match proc.handshake() {
+            Ok(info) => {
+                proc.framing = info.framing;
+                proc.info = info;
+                Ok(proc)
+            }
+            Err(e) => {
+                proc.kill();
+                Err(e)
+            }
+        }
+    }
+
+    /// Negotiated protocol version, kernel version and capabilities.
+    pub fn info(&self) -> &KernelInfo {
+        &self.info
+    }
+
+    /// The supervisor restart epoch this process belongs to.
+    pub fn epoch(&self) -> u64 {
+        self.epoch
+    }
+
+    fn handshake(&self) -> Result<KernelInfo, KernelError> {
+        let opts = RequestOptions {
+            timeout: Some(HANDSHAKE_TIMEOUT),
+            ..Default::default()
+        };
+        let params = json!({
+            "protocol_version": PROTOCOL_VERSION,
+            "min_protocol_version": MIN_PROTOCOL_VERSION,
+            "client": "cairn-tauri",
+            "client_version": env!("CARGO_PKG_VERSION"),
+            "framing": Framing::OFFERED,
+        });
+        let envelope = self.request_with("initialize", params, opts)?;
+
+        if let Some(err) = envelope.get("error") {
+            let message = err
+                .get("message")
+                .and_then(Value::as_str)
+                .unwrap_or("unknown error");
+            return Err(KernelError::HandshakeFailed(message.to_string()));
+        }
+        let result = envelope
+            .get("result")
+            .cloned()
+            .ok_or_else(|| KernelError::HandshakeFailed("missing result".to_string()))?;
+        let info: KernelInfo = serde_json::from_value(result)
+            .map_err(|e| KernelError::HandshakeFailed(format!("malformed reply: {e}")))?;
+
+        if !(MIN_PROTOCOL_VERSION..=PROTOCOL_VERSION).contains(&info.protocol_version) {
+            return Err(KernelError::IncompatibleProtocol {
+                kernel: info.protocol_version,
+                min: MIN_PROTOCOL_VERSION,
+                max: PROTOCOL_VERSION,
+            });
+        }
+        Ok(info)
+    }
+
+    /// Kill and reap the process, or drop the connection to an attached
+    /// kernel; either way the reader then sees EOF and exits.
+    fn kill(&self) {
+        match &self.child {
+            Some(child) => {
+                if let Ok(mut child) = child.lock() {
+                    let _ = child.kill();
+                    let _ = child.wait();
+                }
+            }
+            None => self.close_connection(),
+        }
+    }
+
+    fn close_connection(&self) {
+        if let Some(disconnect) = &self.disconnect {
+            disconnect();
+        }
+    }
+
+    /// Stop the kernel, escalating only as far as needed.
+    ///
+    /// Closing stdin makes the Python read loop return once the request in
+    /// hand is done. If the process has not exited within `grace` it gets
+    /// SIGTERM, then `TERM_GRACE` later SIGKILL. The child is always reaped,
+    /// so no zombie is left behind. Safe to call more than once.
+    ///
+    /// A kernel this shell attached to is shared with other clients, so it is
+    /// only disconnected from, never stopped.
+    pub fn shutdown(&self, grace: Duration) {
+        if let Ok(mut writer) = self.writer.lock() {
+            writer.take();
+        }
+        if let Ok(mut lifeline) = self.lifeline.lock() {
+            lifeline.take();
+        }
+        let Some(child) = &self.child else {
+            self.close_connection();
+            return;
+        };
+        if self.exit_status(grace).is_some() {
+            return;
+        }
+
+        #[cfg(unix)]
+        {
+            let pid = child.lock().map(|c| c.id()).ok();
+            if let Some(pid) = pid.and_then(|p| libc::pid_t::try_from(p).ok()) {
+                eprintln!("[kernel] did not exit within {grace:?}, sending SIGTERM");
+                // SAFETY: kill(2) with a pid we spawned and have not reaped yet.
+                unsafe {
+                    libc::kill(pid, libc::SIGTERM);
+                }
+                if self.exit_status(TERM_GRACE).is_some() {
+                    return;
+                }
+            }
+        }
+
+        eprintln!("[kernel] still running, killing");
+        self.kill();
+    }
+
+    /// Wait up to `grace` for the process to exit and return its status.
+    /// Always `None` for an attached kernel.
+    ///
+    /// Used after stdout has closed, when the process is already on its way
+    /// out; reaping it here also keeps it from lingering as a zombie.
+    pub fn exit_status(&self, grace: Duration) -> Option<ExitStatus> {
+        let child = self.child.as_ref()?;
+        let deadline = Instant::now() + grace;
+        loop {
+            if let Ok(Some(status)) = child.lock().ok()?.try_wait() {
+                return Some(status);
+            }
+            if Instant::now() >= deadline {
+                return None;
+            }
+            thread::sleep(Duration::from_millis(50));
+        }
+    }
+
+    /// Send a request and block until its response arrives or the method's
+    /// default timeout elapses.
+    ///
+    /// Safe to call from many threads at once; responses are matched by id,
+    /// so a slow call does not hold up faster ones issued after it.
+    pub fn request(&self, method: &str, params: Value) -> Result<Value, KernelError> {
+        self.request_with(method, params, RequestOptions::default())
+    }
+
+    /// Like `request`, with an explicit timeout and/or cancel key.
+    ///
+    /// On timeout the kernel is sent a cancel notification for the id and any
+    /// late response is discarded by the reader.
+    pub fn request_with(
+        &self,
+        method: &str,
+        params: Value,
+        opts: RequestOptions,
+    ) -> Result<Value, KernelError> {
+        self.request_stream(method, params, opts, |_| {})
+    }
+
+    /// Like `request_with`, passing each streamed `partial` chunk to
+    /// `on_partial` as it arrives. Returns the final response envelope.
+    ///
+    /// The timeout bounds the gap between messages rather than the whole
+    /// call, so a long generation that keeps producing chunks is not cut off.
+    pub fn request_stream(
+        &self,
+        method: &str,
+        params: Value,
+        opts: RequestOptions,
+        mut on_partial: impl FnMut(Value),
+    ) -> Result<Value, KernelError> {
+        self.call(method, params, opts, |chunk| {
+            if let Chunk::Partial(value) = chunk {
+                on_partial(value);
+            }
+        })
+    }
+
+    /// Like `request_with`, also returning the raw bytes the kernel sends in
+    /// binary frames ahead of its reply (see `blob.rs`).
+    pub fn request_bytes(
+        &self,
+        method: &str,
+        params: Value,
+        opts: RequestOptions,
+    ) -> Result<(Value, Vec<u8>), KernelError> {
+        if !self.supports_blobs() {
+            return Err(KernelError::Unsupported("binary transfers"));
         }
+        let mut bytes = Vec::new();
+        let envelope = self.call(method, params, opts, |chunk| {
+            if let Chunk::Bytes(data) = chunk {
+                bytes.extend_from_slice(&data);
+            }
+        })?;
+        Ok((envelope, bytes))
+    }
 
-        let id = self.next_id;
-        self.next_id += 1;
+    /// Whether binary frames can be exchanged with this kernel: it reported
+    /// the `blobs` capability and accepted `content-length` framing.
+    pub fn supports_blobs(&self) -> bool {
+        self.framing == Framing::ContentLength && self.info.has_capability(BLOBS_CAPABILITY)
+    }
+
+    /// Write one chunk of the upload `blob_id` as a binary frame.
+    pub fn send_blob_chunk(&self, blob_id: &str, data: &[u8]) -> Result<(), KernelError> {
+        if !self.supports_blobs() {
+            return Err(KernelError::Unsupported("binary transfers"));
+        }
+        self.write_bytes(&encode_blob_chunk(blob_id, data))
+            .map(drop)
+    }
+
+    /// Run `exchange` and record its outcome in the metrics.
+    fn call(
+        &self,
+        method: &str,
+        params: Value,
+        opts: RequestOptions,
+        on_chunk: impl FnMut(Chunk),
+    ) -> Result<Value, KernelError> {
+        let started = Instant::now();
+        let mut queue_wait = None;
+        let result = self.exchange(method, params, opts, on_chunk, started, &mut queue_wait);
+        self.metrics
+            .record(method, started.elapsed(), queue_wait, failed(&result));
+        result
+    }
+
+    /// Send one request and wait for its final reply. `queue_wait` is set to
+    /// the time from `started` until the request got the writer.
+    fn exchange(
+        &self,
+        method: &str,
+        params: Value,
+        opts: RequestOptions,
+        mut on_chunk: impl FnMut(Chunk),
+        started: Instant,
+        queue_wait: &mut Option<Duration>,
+    ) -> Result<Value, KernelError> {
+        if let Some(child) = &self.child {
+            let mut child = child.lock().map_err(|_| KernelError::Exited)?;
+            if child.try_wait().map_err(|_| KernelError::Exited)?.is_some() {
+                return Err(KernelError::Exited);
+            }
+        }
+
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        let timeout = opts.timeout.unwrap_or_else(|| timeout_for(method));
+        let rx = self.register(id, opts.cancel_key)?;
 
         let req = json!({
             "jsonrpc": "2.0",
@@ -111,32 +992,1220 @@ impl KernelProcess {
             "params": params
         });
 
-        let line = serde_json::to_string(&req).unwrap_or_else(|_| "{}".to_string());
-        self.stdin
-            .write_all(line.as_bytes())
-            .and_then(|_| self.stdin.write_all(b"\n"))
-            .and_then(|_| self.stdin.flush())
-            .map_err(|e| KernelError::StdinWriteFailed(e.to_string()))?;
+        let message = serde_json::to_string(&req).unwrap_or_else(|_| "{}".to_string());
+        match self.write_message(&message) {
+            Ok(writing) => *queue_wait = Some(writing.duration_since(started)),
+            Err(e) => {
+                self.forget(id);
+                return Err(e);
+            }
+        }
+
+        let recording = self.recorder.is_some();
+        let mut partials = Vec::new();
+        let result = self.await_reply(id, &rx, timeout, None, |chunk| {
+            if let (true, Chunk::Partial(value)) = (recording, &chunk) {
+                partials.push(value.clone());
+            }
+            on_chunk(chunk)
+        });
+        self.record(&req, partials, &result);
+        result
+    }
+
+    /// Add a completed exchange to the cassette, if one is being recorded.
+    fn record(&self, request: &Value, partials: Vec<Value>, result: &Result<Value, KernelError>) {
+        if let (Some(recorder), Ok(response)) = (&self.recorder, result) {
+            recorder.record(&Exchange::new(request, partials, response));
+        }
+    }
 
-        // Read responses until we see the matching id.
-        let mut buf = String::new();
+    /// Block until the final reply for `id` arrives, allowing at most
+    /// `timeout` between messages and, if given, nothing past `deadline`.
+    fn await_reply(
+        &self,
+        id: u64,
+        rx: &Receiver<Reply>,
+        timeout: Duration,
+        deadline: Option<Instant>,
+        mut on_chunk: impl FnMut(Chunk),
+    ) -> Result<Value, KernelError> {
         loop {
-            buf.clear();
-            let n = self
-                .stdout
-                .read_line(&mut buf)
-                .map_err(|e| KernelError::StdoutReadFailed(e.to_string()))?;
-            if n == 0 {
-                return Err(KernelError::Exited);
+            let wait = deadline.map_or(timeout, |d| {
+                timeout.min(d.saturating_duration_since(Instant::now()))
+            });
+            match rx.recv_timeout(wait) {
+                Ok(Reply::Chunk(chunk)) => on_chunk(chunk),
+                Ok(Reply::Done(result)) => return result,
+                Err(RecvTimeoutError::Timeout) => {
+                    // Only notify the kernel if the response did not race in
+                    // between the timeout firing and us removing the waiter.
+                    if self.forget(id) {
+                        self.notify_cancel(id);
+                    }
+                    return Err(KernelError::Timeout(timeout));
+                }
+                Err(RecvTimeoutError::Disconnected) => return Err(KernelError::Exited),
             }
+        }
+    }
 
-            let parsed: Value = serde_json::from_str(buf.trim())
-                .map_err(|e| KernelError::InvalidJson(e.to_string()))?;
+    /// Send several requests at once and return their response envelopes in
+    /// the same order, each with its own error if it failed.
+    ///
+    /// A kernel that reports the `batch` capability gets one JSON-RPC batch
+    /// array on a single line. Otherwise every request is written before any
+    /// reply is awaited, so they are still pipelined rather than sent one
+    /// round trip at a time. Either way each call is cancelled and recorded
+    /// in the metrics on its own.
+    ///
+    /// The whole batch shares one deadline: `opts.timeout`, or else the
+    /// longest `timeout_for` among its methods, from when it was sent. Calls
+    /// still unanswered by then fail with `KernelError::Timeout`. All calls
+    /// are registered under `opts.cancel_key`, so one `cancel` abandons
+    /// every call still in flight.
+    pub fn request_batch(
+        &self,
+        calls: Vec<(String, Value)>,
+        opts: RequestOptions,
+    ) -> Vec<Result<Value, KernelError>> {
+        let started = Instant::now();
+        let timeout = opts.timeout.unwrap_or_else(|| {
+            calls
+                .iter()
+                .map(|(method, _)| timeout_for(method))
+                .max()
+                .unwrap_or(DEFAULT_REQUEST_TIMEOUT)
+        });
+        let deadline = started + timeout;
+        let exited = match &self.child {
+            Some(child) => child
+                .lock()
+                .map_or(true, |mut c| !matches!(c.try_wait(), Ok(None))),
+            None => false,
+        };
 
-            let resp_id = parsed.get("id");
-            if resp_id == Some(&Value::Number(id.into())) {
-                return Ok(parsed);
+        // Register every waiter before anything is written, so no reply can
+        // arrive ahead of its waiter.
+        let mut slots = Vec::new();
+        let mut requests = Vec::new();
+        for (method, params) in calls {
+            if exited {
+                slots.push((method, Err(KernelError::Exited)));
+                continue;
+            }
+            let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+            match self.register(id, opts.cancel_key.clone()) {
+                Ok(rx) => {
+                    requests.push(json!({
+                        "jsonrpc": "2.0",
+                        "id": id,
+                        "method": method,
+                        "params": params
+                    }));
+                    slots.push((method, Ok((id, rx))));
+                }
+                Err(e) => slots.push((method, Err(e))),
             }
         }
+
+        // Write failures are per message: the whole batch, or one request.
+        let mut write_errors: HashMap<u64, KernelError> = HashMap::new();
+        let mut queue_waits: HashMap<u64, Duration> = HashMap::new();
+        let id_of = |req: &Value| req.get("id").and_then(Value::as_u64).unwrap_or(0);
+        if requests.len() > 1 && self.info.has_capability(BATCH_CAPABILITY) {
+            let message = serde_json::to_string(&requests).unwrap_or_else(|_| "[]".to_string());
+            match self.write_message(&message) {
+                Ok(writing) => queue_waits.extend(
+                    requests
+                        .iter()
+                        .map(|r| (id_of(r), writing.duration_since(started))),
+                ),
+                Err(e) => write_errors.extend(requests.iter().map(|r| (id_of(r), e.clone()))),
+            }
+        } else {
+            for req in &requests {
+                match self.write_message(&req.to_string()) {
+                    Ok(writing) => {
+                        queue_waits.insert(id_of(req), writing.duration_since(started));
+                    }
+                    Err(e) => {
+                        write_errors.insert(id_of(req), e);
+                    }
+                }
+            }
+        }
+
+        slots
+            .into_iter()
+            .map(|(method, slot)| {
+                let queue_wait = slot
+                    .as_ref()
+                    .ok()
+                    .and_then(|(id, _)| queue_waits.get(id).copied());
+                let result = match slot {
+                    Err(e) => Err(e),
+                    Ok((id, _)) if write_errors.contains_key(&id) => {
+                        self.forget(id);
+                        Err(write_errors.remove(&id).unwrap_or(KernelError::Exited))
+                    }
+                    Ok((id, rx)) => {
+                        let result = self.await_reply(id, &rx, timeout, Some(deadline), |_| {});
+                        if let Some(request) = requests.iter().find(|r| id_of(r) == id) {
+                            self.record(request, Vec::new(), &result);
+                        }
+                        result
+                    }
+                };
+                self.metrics
+                    .record(&method, started.elapsed(), queue_wait, failed(&result));
+                result
+            })
+            .collect()
+    }
+
+    /// Cancel the in-flight requests registered under `cancel_key`: one
+    /// request, or every call of a batch.
+    ///
+    /// Each blocked caller receives `KernelError::Cancelled` immediately and
+    /// the kernel is sent a cancel notification per request. Returns `false`
+    /// if no such request is in flight (it may already have completed).
+    pub fn cancel(&self, cancel_key: &str) -> bool {
+        let waiters: Vec<(u64, Waiter)> = self
+            .pending
+            .lock()
+            .map(|mut table| {
+                let ids = table.cancel_keys.remove(cancel_key).unwrap_or_default();
+                ids.into_iter()
+                    .filter_map(|id| table.waiters.remove(&id).map(|w| (id, w)))
+                    .collect()
+            })
+            .unwrap_or_default();
+
+        for (id, waiter) in &waiters {
+            let _ = waiter.tx.send(Reply::Done(Err(KernelError::Cancelled)));
+            self.notify_cancel(*id);
+        }
+        !waiters.is_empty()
+    }
+
+    fn register(
+        &self,
+        id: u64,
+        cancel_key: Option<String>,
+    ) -> Result<Receiver<Reply>, KernelError> {
+        let (tx, rx) = mpsc::channel();
+        let mut table = self.pending.lock().map_err(|_| KernelError::Exited)?;
+        if table.closed {
+            return Err(KernelError::Exited);
+        }
+        if let Some(key) = &cancel_key {
+            table.cancel_keys.entry(key.clone()).or_default().push(id);
+        }
+        table.waiters.insert(id, Waiter { tx, cancel_key });
+        Ok(rx)
+    }
+
+    /// Drop the waiter for `id`. Returns `true` if it was still registered.
+    fn forget(&self, id: u64) -> bool {
+        self.pending
+            .lock()
+            .map(|mut table| table.take(id).is_some())
+            .unwrap_or(false)
+    }
+
+    /// Best-effort notification; the caller has already been released, so a
+    /// write failure here is not reported.
+    fn notify_cancel(&self, id: u64) {
+        let note = json!({
+            "jsonrpc": "2.0",
+            "method": CANCEL_METHOD,
+            "params": { "id": id }
+        });
+        let _ = self.write_message(&note.to_string());
+    }
+
+    /// Write one complete message in the negotiated framing. The writer lock
+    /// is held only for the write itself so concurrent requests never
+    /// interleave partial messages.
+    fn write_message(&self, message: &str) -> Result<Instant, KernelError> {
+        self.write_bytes(&self.framing.encode(message))
+    }
+
+    /// Write one already framed message. Returns when the writer was
+    /// acquired, which is where a request's queue wait ends.
+    fn write_bytes(&self, frame: &[u8]) -> Result<Instant, KernelError> {
+        let mut guard = self
+            .writer
+            .lock()
+            .map_err(|_| KernelError::StdinWriteFailed("writer lock poisoned".to_string()))?;
+        let acquired = Instant::now();
+        let writer = guard
+            .as_mut()
+            .ok_or_else(|| KernelError::StdinWriteFailed("stdin closed".to_string()))?;
+        writer
+            .write_all(frame)
+            .and_then(|_| writer.flush())
+            .map(|_| acquired)
+            .map_err(|e| KernelError::StdinWriteFailed(e.to_string()))
+    }
+}
+
+impl Drop for KernelProcess {
+    fn drop(&mut self) {
+        // Usually a no-op: the supervisor shuts the kernel down explicitly, or
+        // it has already exited and been reaped after a crash.
+        self.shutdown(SHUTDOWN_GRACE);
+    }
+}
+
+/// Messages delivered over the Tauri channel of `kernel_request_stream`.
+///
+/// Zero or more `partial` events are followed by exactly one `result`
+/// (the unwrapped JSON-RPC `result`) or `error` (a `CommandError`: the
+/// kernel returned an error object, or the call never completed).
+#[derive(Debug, Serialize)]
+#[serde(tag = "event", content = "data", rename_all = "snake_case")]
+pub enum StreamEvent {
+    Partial(Value),
+    Result(Value),
+    Error(CommandError),
+}
+
+/// One item of a `kernel_request_batch` reply, in the position of the call
+/// it answers: the unwrapped JSON-RPC `result`, or why that call failed.
+#[derive(Debug, Serialize)]
+#[serde(tag = "status", rename_all = "snake_case")]
+pub enum BatchOutcome {
+    Ok { result: Value },
+    Error { error: CommandError },
+}
+
+impl From<Result<Value, CommandError>> for BatchOutcome {
+    fn from(result: Result<Value, CommandError>) -> Self {
+        match result {
+            Ok(result) => Self::Ok { result },
+            Err(error) => Self::Error { error },
+        }
+    }
+}
+
+// =============================================================================
+// Notifications
+// =============================================================================
+
+/// Kernel notifications are emitted as `cairn://kernel/<method>`.
+pub const KERNEL_NOTIFICATION_PREFIX: &str = "cairn://kernel/";
+
+/// Tauri event name for a kernel notification. Characters Tauri does not
+/// allow in event names are replaced with `_`.
+pub fn notification_event(method: &str) -> String {
+    let sanitized: String = method
+        .chars()
+        .map(|c| match c {
+            'a'..='z' | 'A'..='Z' | '0'..='9' | '-' | '/' | ':' | '_' => c,
+            _ => '_',
+        })
+        .collect();
+    format!("{KERNEL_NOTIFICATION_PREFIX}{sanitized}")
+}
+
+/// Whether a subscription topic covers `method`.
+///
+/// Topics are exact method names, a namespace wildcard such as `chat/*`
+/// (matching `chat/status` and `chat/stream/delta`), or `*` for everything.
+fn topic_matches(topic: &str, method: &str) -> bool {
+    if topic == "*" || topic == method {
+        return true;
+    }
+    match topic.strip_suffix("/*") {
+        Some(ns) => method
+            .strip_prefix(ns)
+            .is_some_and(|rest| rest.starts_with('/')),
+        None => false,
+    }
+}
+
+/// Which windows want which notification topics.
+#[derive(Default)]
+pub struct Subscriptions {
+    by_window: HashMap<String, HashSet<String>>,
+}
+
+impl Subscriptions {
+    pub fn subscribe(&mut self, window: &str, topics: &[String]) {
+        self.by_window
+            .entry(window.to_string())
+            .or_default()
+            .extend(topics.iter().cloned());
+    }
+
+    /// Drop the given topics for `window`, or all of them if `topics` is empty.
+    pub fn unsubscribe(&mut self, window: &str, topics: &[String]) {
+        if topics.is_empty() {
+            self.by_window.remove(window);
+            return;
+        }
+        if let Some(set) = self.by_window.get_mut(window) {
+            for topic in topics {
+                set.remove(topic);
+            }
+            if set.is_empty() {
+                self.by_window.remove(window);
+            }
+        }
+    }
+
+    /// Windows with at least one topic matching `method`.
+    pub fn windows_for(&self, method: &str) -> Vec<String> {
+        self.by_window
+            .iter()
+            .filter(|(_, topics)| topics.iter().any(|t| topic_matches(t, method)))
+            .map(|(window, _)| window.clone())
+            .collect()
+    }
+}
+
+// =============================================================================
+// Supervisor
+// =============================================================================
+
+/// Event emitted whenever the supervisor changes state.
+pub const KERNEL_STATUS_EVENT: &str = "cairn://kernel-status";
+
+/// Where the supervisor delivers events; implemented over the Tauri app
+/// handle in `main.rs`.
+pub trait KernelEventSink: Send + Sync {
+    /// Broadcast a lifecycle change to every window.
+    fn status(&self, event: &KernelStatusEvent);
+    /// Deliver a kernel notification to a single window.
+    fn notify(&self, window: &str, event: &str, payload: &Value);
+    /// See every kernel notification once, subscribed to or not.
+    fn notification(&self, _method: &str, _params: &Value) {}
+}
+
+/// Kernel lifecycle as reported to the UI.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum KernelStatus {
+    Starting,
+    Ready,
+    Crashed,
+    Restarting,
+}
+
+/// Payload for `cairn://kernel-status` events.
+#[derive(Debug, Clone, Serialize)]
+pub struct KernelStatusEvent {
+    pub status: KernelStatus,
+    /// Incremented on every start attempt; identifies one process lifetime.
+    pub epoch: u64,
+    /// Exit status for `crashed`, retry delay for `restarting`, spawn or
+    /// handshake error for a failed start.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub detail: Option<String>,
+}
+
+/// Bounds on automatic restarts after a crash.
+#[derive(Debug, Clone)]
+pub struct RestartPolicy {
+    /// Restarts allowed within `window` before the supervisor gives up.
+    pub max_restarts: usize,
+    pub window: Duration,
+    /// Delay before the first restart; doubles for each further restart in
+    /// the window, up to `max_backoff`.
+    pub initial_backoff: Duration,
+    pub max_backoff: Duration,
+}
+
+impl Default for RestartPolicy {
+    fn default() -> Self {
+        Self {
+            max_restarts: 5,
+            window: Duration::from_secs(5 * 60),
+            initial_backoff: Duration::from_millis(500),
+            max_backoff: Duration::from_secs(30),
+        }
+    }
+}
+
+impl RestartPolicy {
+    fn backoff(&self, attempt: usize) -> Duration {
+        let shift = attempt.saturating_sub(1).min(16) as u32;
+        self.initial_backoff
+            .saturating_mul(1 << shift)
+            .min(self.max_backoff)
+    }
+}
+
+enum Phase {
+    /// Never started, or explicitly reset; the next request starts it.
+    Idle,
+    /// A process is being started and handshaked, without the state lock
+    /// held; requests wait on `phase_changed` for the outcome.
+    Starting,
+    Running(Arc<KernelProcess>),
+    /// Crashed; a restart is scheduled on a background thread.
+    Restarting,
+    /// Crashed too often within the policy window, or the kernel speaks an
+    /// incompatible protocol. Requests fail with `KernelError::NotStarted`
+    /// until `start` is called explicitly.
+    Failed,
+}
+
+struct SupervisorState {
+    phase: Phase,
+    epoch: u64,
+    /// Times of automatic restarts still inside the policy window.
+    restarts: VecDeque<Instant>,
+    /// The process of this epoch exited while still `Starting`.
+    exited_while_starting: Option<u64>,
+}
+
+/// Owns the kernel process across crashes.
+///
+/// When the reader thread reports that stdout closed, the supervisor reaps
+/// the process, emits `crashed` with its exit status and, within the
+/// `RestartPolicy` budget, schedules a restart with exponential backoff.
+/// Notification subscriptions live here too, so they survive restarts.
+///
+/// The state lock is never held while starting a process or emitting
+/// events: handshakes can take seconds, and event sinks take locks of
+/// their own.
+pub struct KernelSupervisor {
+    state: Mutex<SupervisorState>,
+    /// Signalled when a start leaves `Phase::Starting`.
+    phase_changed: Condvar,
+    policy: RestartPolicy,
+    sink: Mutex<Option<Arc<dyn KernelEventSink>>>,
+    subscriptions: Mutex<Subscriptions>,
+    log: Arc<KernelLog>,
+    metrics: Arc<KernelMetrics>,
+    /// Used for every (re)start; replaced once the app's dirs are known.
+    launch: Mutex<KernelLaunch>,
+    /// Replaces the transport `launch` selects, e.g. with a `FakeKernel`.
+    transport: Mutex<Option<Arc<dyn KernelTransport>>>,
+    recorder: Mutex<Option<Arc<Recorder>>>,
+}
+
+impl KernelSupervisor {
+    pub fn new(policy: RestartPolicy) -> Arc<Self> {
+        Arc::new(Self {
+            state: Mutex::new(SupervisorState {
+                phase: Phase::Idle,
+                epoch: 0,
+                restarts: VecDeque::new(),
+                exited_while_starting: None,
+            }),
+            phase_changed: Condvar::new(),
+            policy,
+            sink: Mutex::new(None),
+            subscriptions: Mutex::new(Subscriptions::default()),
+            log: Arc::new(KernelLog::new(DEFAULT_CAPACITY)),
+            metrics: Arc::new(KernelMetrics::new()),
+            launch: Mutex::new(KernelLaunch::default()),
+            transport: Mutex::new(None),
+            recorder: Mutex::new(None),
+        })
+    }
+
+    /// Captured stderr of every kernel this supervisor has started.
+    pub fn log(&self) -> &KernelLog {
+        &self.log
+    }
+
+    /// Request metrics across every kernel this supervisor has started.
+    pub fn metrics(&self) -> &KernelMetrics {
+        &self.metrics
+    }
+
+    /// Set how future kernels are launched. A running kernel is unaffected
+    /// until it restarts.
+    pub fn set_launch(&self, launch: KernelLaunch) {
+        if let Ok(mut slot) = self.launch.lock() {
+            *slot = launch;
+        }
+    }
+
+    /// Connect through `transport` on every (re)start instead of the one
+    /// the launch config selects.
+    pub fn set_transport(&self, transport: Arc<dyn KernelTransport>) {
+        if let Ok(mut slot) = self.transport.lock() {
+            *slot = Some(transport);
+        }
+    }
+
+    /// Record every exchange with kernels started from now on.
+    pub fn set_recorder(&self, recorder: Recorder) {
+        if let Ok(mut slot) = self.recorder.lock() {
+            *slot = Some(Arc::new(recorder));
+        }
+    }
+
+    /// Install the sink that receives status events and notifications.
+    /// Events raised before this is set are dropped.
+    pub fn set_event_sink(&self, sink: impl KernelEventSink + 'static) {
+        if let Ok(mut slot) = self.sink.lock() {
+            *slot = Some(Arc::new(sink));
+        }
+    }
+
+    pub fn subscribe(&self, window: &str, topics: &[String]) {
+        if let Ok(mut subs) = self.subscriptions.lock() {
+            subs.subscribe(window, topics);
+        }
+    }
+
+    pub fn unsubscribe(&self, window: &str, topics: &[String]) {
+        if let Ok(mut subs) = self.subscriptions.lock() {
+            subs.unsubscribe(window, topics);
+        }
+    }
+
+    /// The running kernel, if any, without starting one.
+    pub fn current(&self) -> Option<Arc<KernelProcess>> {
+        match &self.state.lock().ok()?.phase {
+            Phase::Running(proc) => Some(proc.clone()),
+            _ => None,
+        }
+    }
+
+    /// Return the running kernel, spawning it on first use, or waiting for
+    /// a start already under way.
+    ///
+    /// While a crash restart is pending, or after the restart budget is
+    /// exhausted, this fails with `KernelError::NotStarted` rather than
+    /// bypassing the backoff.
+    pub fn get_or_start(self: &Arc<Self>) -> Result<Arc<KernelProcess>, KernelError> {
+        let mut st = self.settled_state()?;
+        match &st.phase {
+            Phase::Running(proc) => Ok(proc.clone()),
+            Phase::Restarting | Phase::Failed => Err(KernelError::NotStarted),
+            // `settled_state` has waited out any `Starting`.
+            Phase::Idle | Phase::Starting => {
+                let epoch = begin_start(&mut st);
+                drop(st);
+                self.spawn(epoch)
+            }
+        }
+    }
+
+    /// The state lock, once no start is under way.
+    fn settled_state(&self) -> Result<MutexGuard<'_, SupervisorState>, KernelError> {
+        let st = self.state.lock().map_err(|_| KernelError::NotStarted)?;
+        self.phase_changed
+            .wait_while(st, |st| matches!(st.phase, Phase::Starting))
+            .map_err(|_| KernelError::NotStarted)
+    }
+
+    /// Shut the running kernel down gracefully (see `KernelProcess::shutdown`)
+    /// and cancel any pending restart. The next request starts a fresh one.
+    /// Returns `false` if no kernel was running.
+    pub fn stop(&self, grace: Duration) -> bool {
+        let phase = match self.state.lock() {
+            Ok(mut st) => std::mem::replace(&mut st.phase, Phase::Idle),
+            Err(_) => return false,
+        };
+        // A start under way is abandoned; `spawn` shuts its process down.
+        self.phase_changed.notify_all();
+        match phase {
+            Phase::Running(proc) => {
+                proc.shutdown(grace);
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// Start the kernel if it is not running, clearing a previous give-up.
+    pub fn start(self: &Arc<Self>) -> Result<Arc<KernelProcess>, KernelError> {
+        let mut st = self.settled_state()?;
+        if let Phase::Running(proc) = &st.phase {
+            return Ok(proc.clone());
+        }
+        st.restarts.clear();
+        let epoch = begin_start(&mut st);
+        drop(st);
+        self.spawn(epoch)
+    }
+
+    /// Start and handshake the process for `epoch`, claimed with
+    /// `begin_start`, then install it unless the start was overtaken by
+    /// `stop` in the meantime.
+    fn spawn(self: &Arc<Self>, epoch: u64) -> Result<Arc<KernelProcess>, KernelError> {
+        self.emit(KernelStatus::Starting, epoch, None);
+
+        let weak: Weak<Self> = Arc::downgrade(self);
+        let on_notification: NotificationHandler = Arc::new({
+            let weak = weak.clone();
+            move |method, params| {
+                if let Some(sup) = weak.upgrade() {
+                    sup.dispatch_notification(method, &params);
+                }
+            }
+        });
+        let on_exit = Box::new(move || {
+            if let Some(sup) = weak.upgrade() {
+                sup.handle_exit(epoch);
+            }
+        });
+        let hooks = KernelHooks {
+            on_notification,
+            on_exit,
+            log: self.log.clone(),
+            metrics: self.metrics.clone(),
+            recorder: self.recorder.lock().ok().and_then(|r| r.clone()),
+        };
+        let transport = match self.transport.lock().ok().and_then(|t| t.clone()) {
+            Some(transport) => transport,
+            None => {
+                let launch = self.launch.lock().map(|l| l.clone()).unwrap_or_default();
+                Arc::from(transport_for(&launch))
+            }
+        };
+        let started = KernelProcess::start(transport.as_ref(), hooks);
+
+        let mut st = self.state.lock().map_err(|_| KernelError::NotStarted)?;
+        if st.epoch != epoch || !matches!(st.phase, Phase::Starting) {
+            drop(st);
+            if let Ok(proc) = started {
+                proc.shutdown(Duration::from_secs(1));
+            }
+            return Err(KernelError::NotStarted);
+        }
+        let proc = match started {
+            Ok(mut proc) => {
+                proc.epoch = epoch;
+                Arc::new(proc)
+            }
+            Err(e) => {
+                st.phase = match e {
+                    KernelError::IncompatibleProtocol { .. } => Phase::Failed,
+                    _ => Phase::Idle,
+                };
+                drop(st);
+                self.phase_changed.notify_all();
+                self.emit(KernelStatus::Crashed, epoch, Some(e.to_string()));
+                return Err(e);
+            }
+        };
+        st.phase = Phase::Running(proc.clone());
+        let exited = st.exited_while_starting.take() == Some(epoch);
+        drop(st);
+        self.phase_changed.notify_all();
+
+        self.emit(KernelStatus::Ready, epoch, None);
+        if exited {
+            self.handle_exit(epoch);
+        }
+        Ok(proc)
+    }
+
+    /// Called from the reader thread of the process started in `epoch`.
+    fn handle_exit(self: &Arc<Self>, epoch: u64) {
+        let proc = match self.state.lock() {
+            Ok(mut st) if st.epoch == epoch => match &st.phase {
+                Phase::Running(proc) => proc.clone(),
+                // Handled by `spawn` once the process is installed.
+                Phase::Starting => {
+                    st.exited_while_starting = Some(epoch);
+                    return;
+                }
+                _ => return,
+            },
+            _ => return,
+        };
+
+        let detail = proc
+            .exit_status(Duration::from_secs(2))
+            .map(|s| s.to_string())
+            .unwrap_or_else(|| "exit status unavailable".to_string());
+        drop(proc);
+
+        let Ok(mut st) = self.state.lock() else {
+            return;
+        };
+        if st.epoch != epoch || !matches!(st.phase, Phase::Running(_)) {
+            return;
+        }
+        eprintln!("[kernel] process exited ({detail})");
+        let scheduled = self.schedule_restart(&mut st);
+        drop(st);
+        self.emit(KernelStatus::Crashed, epoch, Some(detail));
+        self.emit(scheduled.status, scheduled.epoch, scheduled.detail);
+    }
+
+    /// Move to `Restarting` and spawn the delayed restart, or to `Failed`
+    /// if the policy window is already full. Returns the event to emit
+    /// once the state lock is released.
+    fn schedule_restart(self: &Arc<Self>, st: &mut SupervisorState) -> KernelStatusEvent {
+        let now = Instant::now();
+        let window = self.policy.window;
+        st.restarts.retain(|t| now.duration_since(*t) < window);
+
+        if st.restarts.len() >= self.policy.max_restarts {
+            st.phase = Phase::Failed;
+            return KernelStatusEvent {
+                status: KernelStatus::Crashed,
+                epoch: st.epoch,
+                detail: Some(format!(
+                    "restart limit reached ({} in {:?})",
+                    self.policy.max_restarts, window
+                )),
+            };
+        }
+
+        st.restarts.push_back(now);
+        let delay = self.policy.backoff(st.restarts.len());
+        st.phase = Phase::Restarting;
+        let event = KernelStatusEvent {
+            status: KernelStatus::Restarting,
+            epoch: st.epoch,
+            detail: Some(format!("retrying in {}ms", delay.as_millis())),
+        };
+
+        let sup = self.clone();
+        let spawned = thread::Builder::new()
+            .name("kernel-restart".to_string())
+            .spawn(move || {
+                thread::sleep(delay);
+                sup.restart();
+            });
+        if spawned.is_err() {
+            st.phase = Phase::Failed;
+        }
+        event
+    }
+
+    fn restart(self: &Arc<Self>) {
+        let epoch = match self.state.lock() {
+            Ok(mut st) if matches!(st.phase, Phase::Restarting) => begin_start(&mut st),
+            _ => return,
+        };
+        if self.spawn(epoch).is_ok() {
+            return;
+        }
+        // A failed start leaves the phase `Idle` (retry) or `Failed` (give up).
+        let Ok(mut st) = self.state.lock() else {
+            return;
+        };
+        if st.epoch == epoch && matches!(st.phase, Phase::Idle) {
+            let scheduled = self.schedule_restart(&mut st);
+            drop(st);
+            self.emit(scheduled.status, scheduled.epoch, scheduled.detail);
+        }
+    }
+
+    fn sink(&self) -> Option<Arc<dyn KernelEventSink>> {
+        self.sink.lock().ok()?.clone()
+    }
+
+    /// Forward a notification to every window subscribed to its method.
+    fn dispatch_notification(&self, method: &str, params: &Value) {
+        let Some(sink) = self.sink() else {
+            return;
+        };
+        sink.notification(method, params);
+        let windows = match self.subscriptions.lock() {
+            Ok(subs) => subs.windows_for(method),
+            Err(_) => return,
+        };
+        let event = notification_event(method);
+        for window in windows {
+            sink.notify(&window, &event, params);
+        }
+    }
+
+    fn emit(&self, status: KernelStatus, epoch: u64, detail: Option<String>) {
+        let event = KernelStatusEvent {
+            status,
+            epoch,
+            detail,
+        };
+        if let Some(sink) = self.sink() {
+            sink.status(&event);
+        }
+    }
+}
+
+/// Claim the next epoch and mark a start under way. The epoch is claimed
+/// up front, so a late exit callback from a process that failed its
+/// handshake can never match a later, healthy one.
+fn begin_start(st: &mut SupervisorState) -> u64 {
+    st.epoch += 1;
+    st.phase = Phase::Starting;
+    st.epoch
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cassette::{exchange, handshake, FakeKernel};
+
+    #[test]
+    fn test_topic_matching() {
+        assert!(topic_matches("*", "chat/status"));
+        assert!(topic_matches("chat/status", "chat/status"));
+        assert!(topic_matches("chat/*", "chat/status"));
+        assert!(topic_matches("chat/*", "chat/stream/delta"));
+        assert!(!topic_matches("chat/*", "chatter/status"));
+        assert!(!topic_matches("chat/*", "chat"));
+        assert!(!topic_matches("chat/status", "chat/status/extra"));
+    }
+
+    #[test]
+    fn test_subscriptions_per_window() {
+        let mut subs = Subscriptions::default();
+        subs.subscribe("main", &["cc/session/*".to_string()]);
+        subs.subscribe("dashboard", &["cairn/chat_status".to_string()]);
+
+        assert_eq!(
+            subs.windows_for("cc/session/output"),
+            vec!["main".to_string()]
+        );
+        assert_eq!(
+            subs.windows_for("cairn/chat_status"),
+            vec!["dashboard".to_string()]
+        );
+        assert!(subs.windows_for("play/acts/changed").is_empty());
+
+        subs.unsubscribe("main", &[]);
+        assert!(subs.windows_for("cc/session/output").is_empty());
+    }
+
+    #[test]
+    fn test_batch_reply_and_stray_stdout() {
+        let pending = SharedPending::default();
+        let (tx1, rx1) = mpsc::channel();
+        let (tx2, rx2) = mpsc::channel();
+        {
+            let mut table = pending.lock().unwrap();
+            for (id, tx) in [(1, tx1), (2, tx2)] {
+                let waiter = Waiter {
+                    tx,
+                    cancel_key: None,
+                };
+                table.waiters.insert(id, waiter);
+            }
+        }
+        let notes = Arc::new(Mutex::new(Vec::new()));
+        let seen = notes.clone();
+        let on_notification: NotificationHandler =
+            Arc::new(move |method, _| seen.lock().unwrap().push(method.to_string()));
+
+        let stdout = concat!(
+            "Loading model...\n",
+            "{'not': 'json'}\n",
+            r#"[{"jsonrpc":"2.0","id":2,"result":"b"},{"jsonrpc":"2.0","method":"x/y"},"#,
+            r#"{"jsonrpc":"2.0","id":1,"error":{"code":-32601,"message":"nope"}}]"#,
+            "\n"
+        );
+        let log = KernelLog::new(10);
+        read_loop(stdout.as_bytes(), pending.clone(), on_notification, &log);
+
+        let Ok(Reply::Done(Ok(first))) = rx1.recv() else {
+            panic!("no reply for id 1");
+        };
+        assert_eq!(first["error"]["code"], -32601);
+        let Ok(Reply::Done(Ok(second))) = rx2.recv() else {
+            panic!("no reply for id 2");
+        };
+        assert_eq!(second["result"], "b");
+        assert_eq!(*notes.lock().unwrap(), vec!["x/y".to_string()]);
+        // Neither stray line failed the requests in flight.
+        let stray = log.query(None, None, None);
+        assert_eq!(stray.len(), 2);
+        assert_eq!(stray[0].source.as_deref(), Some("stdout"));
+    }
+
+    #[test]
+    fn test_binary_frames_reach_their_request() {
+        let pending = SharedPending::default();
+        let (tx, rx) = mpsc::channel();
+        pending.lock().unwrap().waiters.insert(
+            4,
+            Waiter {
+                tx,
+                cancel_key: None,
+            },
+        );
+
+        // A frame naming no request is logged and skipped; the reader goes on.
+        let mut stdout =
+            b"Content-Length: 2\r\nContent-Type: application/octet-stream\r\n\r\n??".to_vec();
+        stdout.extend(
+            b"Content-Length: 3\r\nContent-Type: application/octet-stream\r\nRequest-Id: 4\r\n\r\n\x89PN",
+        );
+        stdout.extend(Framing::ContentLength.encode(r#"{"jsonrpc":"2.0","id":4,"result":{}}"#));
+        let log = KernelLog::new(10);
+        read_loop(stdout.as_slice(), pending, Arc::new(|_, _| {}), &log);
+        assert_eq!(log.query(None, None, None).len(), 1);
+
+        let Ok(Reply::Chunk(Chunk::Bytes(data))) = rx.recv() else {
+            panic!("bytes not delivered");
+        };
+        assert_eq!(data, b"\x89PN");
+        assert!(matches!(rx.recv(), Ok(Reply::Done(Ok(_)))));
+    }
+
+    fn test_hooks(recorder: Option<Arc<Recorder>>) -> KernelHooks {
+        KernelHooks {
+            on_notification: Arc::new(|_, _| {}),
+            on_exit: Box::new(|| {}),
+            log: Arc::new(KernelLog::new(10)),
+            metrics: Arc::new(KernelMetrics::new()),
+            recorder,
+        }
+    }
+
+    #[test]
+    fn test_out_of_order_replies_reach_their_callers() {
+        let mut exchanges = vec![handshake()];
+        for n in 0..3 {
+            exchanges.push(exchange("x/echo", json!({ "n": n }), json!(n * 10)));
+        }
+        let fake = FakeKernel::new(exchanges).reply_in_reverse(3);
+        let proc = Arc::new(KernelProcess::start(&fake, test_hooks(None)).unwrap());
+
+        let callers: Vec<_> = (0..3)
+            .map(|n| {
+                let proc = proc.clone();
+                thread::spawn(move || proc.request("x/echo", json!({ "n": n })))
+            })
+            .collect();
+        for (n, caller) in callers.into_iter().enumerate() {
+            let reply = caller.join().unwrap().unwrap();
+            assert_eq!(reply["result"], n * 10);
+        }
+    }
+
+    #[test]
+    fn test_batch_shares_one_deadline() {
+        let mut exchanges = vec![handshake()];
+        for n in 0..3 {
+            exchanges.push(exchange("x/echo", json!({ "n": n }), json!(n)));
+        }
+        // Replies are held for a fourth request that never comes.
+        let fake = FakeKernel::new(exchanges).reply_in_reverse(4);
+        let proc = KernelProcess::start(&fake, test_hooks(None)).unwrap();
+
+        let calls = (0..3)
+            .map(|n| ("x/echo".to_string(), json!({ "n": n })))
+            .collect();
+        let started = Instant::now();
+        let opts = RequestOptions {
+            timeout: Some(Duration::from_millis(200)),
+            ..Default::default()
+        };
+        let replies = proc.request_batch(calls, opts);
+        let elapsed = started.elapsed();
+        assert_eq!(replies.len(), 3);
+        assert!(replies
+            .iter()
+            .all(|r| matches!(r, Err(KernelError::Timeout(_)))));
+        assert!(elapsed < Duration::from_millis(400), "{elapsed:?}");
+    }
+
+    #[test]
+    fn test_one_cancel_key_cancels_the_whole_batch() {
+        let mut exchanges = vec![handshake()];
+        for n in 0..3 {
+            exchanges.push(exchange("x/echo", json!({ "n": n }), json!(n)));
+        }
+        // Replies are held for a fourth request that never comes.
+        let fake = FakeKernel::new(exchanges).reply_in_reverse(4);
+        let proc = Arc::new(KernelProcess::start(&fake, test_hooks(None)).unwrap());
+
+        let caller = {
+            let proc = proc.clone();
+            thread::spawn(move || {
+                let calls = (0..3)
+                    .map(|n| ("x/echo".to_string(), json!({ "n": n })))
+                    .collect();
+                let opts = RequestOptions {
+                    cancel_key: Some("batch-1".to_string()),
+                    ..Default::default()
+                };
+                proc.request_batch(calls, opts)
+            })
+        };
+        thread::sleep(Duration::from_millis(100));
+        assert!(proc.cancel("batch-1"));
+        let replies = caller.join().unwrap();
+        assert!(replies
+            .iter()
+            .all(|r| matches!(r, Err(KernelError::Cancelled))));
+        assert!(!proc.cancel("batch-1"));
+    }
+
+    #[test]
+    fn test_queue_wait_covers_the_wait_for_the_writer() {
+        let fake = FakeKernel::new(vec![handshake(), exchange("x/echo", json!({}), json!(1))]);
+        let proc = Arc::new(KernelProcess::start(&fake, test_hooks(None)).unwrap());
+
+        let held = proc.writer.lock().unwrap();
+        let caller = {
+            let proc = proc.clone();
+            thread::spawn(move || proc.request("x/echo", json!({})))
+        };
+        thread::sleep(Duration::from_millis(150));
+        drop(held);
+        assert_eq!(caller.join().unwrap().unwrap()["result"], 1);
+
+        let snapshot = proc.metrics.snapshot();
+        let echo = &snapshot.methods[0];
+        assert!(echo.queue_wait.max_ms >= 150.0, "{:?}", echo.queue_wait);
+        assert!(echo.latency.max_ms >= echo.queue_wait.max_ms);
+    }
+
+    #[test]
+    fn test_recorded_session_replays() {
+        let mut streamed = exchange("x/stream", json!({}), json!("done"));
+        streamed.partials = vec![json!("a"), json!("b")];
+        let fake = FakeKernel::new(vec![
+            handshake(),
+            exchange("x/login", json!({ "password": "[redacted]" }), json!(1)),
+            streamed,
+        ]);
+
+        let path = std::env::temp_dir().join(format!("cairn-replay-{}.jsonl", std::process::id()));
+        let recorder = Arc::new(Recorder::create(&path).unwrap());
+        let proc = KernelProcess::start(&fake, test_hooks(Some(recorder))).unwrap();
+        let login = proc
+            .request("x/login", json!({ "password": "hunter2" }))
+            .unwrap();
+        assert_eq!(login["result"], 1);
+        let mut partials = Vec::new();
+        let reply = proc
+            .request_stream("x/stream", json!({}), RequestOptions::default(), |p| {
+                partials.push(p)
+            })
+            .unwrap();
+        assert_eq!(reply["result"], "done");
+        assert_eq!(partials, vec![json!("a"), json!("b")]);
+        let missing = proc.request("x/missing", json!({})).unwrap();
+        assert_eq!(missing["error"]["code"], crate::cassette::NOT_RECORDED);
+        drop(proc);
+
+        let recorded = crate::cassette::load(&path).unwrap();
+        let _ = std::fs::remove_file(&path);
+        let methods: Vec<&str> = recorded.iter().map(|e| e.method.as_str()).collect();
+        assert_eq!(methods, ["initialize", "x/login", "x/stream", "x/missing"]);
+        assert_eq!(recorded[1].params, json!({ "password": "[redacted]" }));
+        assert_eq!(recorded[2].partials, vec![json!("a"), json!("b")]);
+
+        // Replaying the recording gives the same answers.
+        let replayed = KernelProcess::start(&FakeKernel::new(recorded), test_hooks(None)).unwrap();
+        let login = replayed
+            .request("x/login", json!({ "password": "other" }))
+            .unwrap();
+        assert_eq!(login["result"], 1);
+    }
+
+    /// Holds each connect until the test lets it through.
+    struct GatedTransport {
+        inner: FakeKernel,
+        gate: Mutex<Receiver<()>>,
+        connects: AtomicU64,
+    }
+
+    impl KernelTransport for GatedTransport {
+        fn connect(&self) -> Result<Connection, KernelError> {
+            self.connects.fetch_add(1, Ordering::SeqCst);
+            let _ = self.gate.lock().unwrap().recv();
+            self.inner.connect()
+        }
+    }
+
+    /// Records each status with whether the supervisor, asked from inside
+    /// the sink, had a kernel running.
+    struct StatusProbe {
+        sup: Weak<KernelSupervisor>,
+        seen: Arc<Mutex<Vec<(KernelStatus, bool)>>>,
+    }
+
+    impl KernelEventSink for StatusProbe {
+        fn status(&self, event: &KernelStatusEvent) {
+            let running = self.sup.upgrade().and_then(|s| s.current()).is_some();
+            self.seen.lock().unwrap().push((event.status, running));
+        }
+
+        fn notify(&self, _: &str, _: &str, _: &Value) {}
+    }
+
+    #[test]
+    fn test_start_does_not_hold_the_supervisor() {
+        let sup = KernelSupervisor::new(RestartPolicy::default());
+        let (release, gate) = mpsc::channel();
+        let transport = Arc::new(GatedTransport {
+            inner: FakeKernel::new(vec![handshake()]),
+            gate: Mutex::new(gate),
+            connects: AtomicU64::new(0),
+        });
+        sup.set_transport(transport.clone());
+        let seen = Arc::new(Mutex::new(Vec::new()));
+        sup.set_event_sink(StatusProbe {
+            sup: Arc::downgrade(&sup),
+            seen: seen.clone(),
+        });
+
+        let starters: Vec<_> = (0..2)
+            .map(|_| {
+                let sup = sup.clone();
+                thread::spawn(move || sup.get_or_start())
+            })
+            .collect();
+        while transport.connects.load(Ordering::SeqCst) == 0 {
+            thread::sleep(Duration::from_millis(1));
+        }
+        // Mid-start, the supervisor still answers.
+        assert!(sup.current().is_none());
+        release.send(()).unwrap();
+
+        let started: Vec<_> = starters
+            .into_iter()
+            .map(|s| s.join().unwrap().unwrap())
+            .collect();
+        assert!(Arc::ptr_eq(&started[0], &started[1]));
+        assert_eq!(transport.connects.load(Ordering::SeqCst), 1);
+        assert_eq!(
+            *seen.lock().unwrap(),
+            [(KernelStatus::Starting, false), (KernelStatus::Ready, true)]
+        );
+        sup.stop(Duration::from_secs(1));
+    }
+
+    #[test]
+    fn test_notification_event_name() {
+        assert_eq!(
+            notification_event("cc/session/output"),
+            "cairn://kernel/cc/session/output"
+        );
+        assert_eq!(notification_event("a.b c"), "cairn://kernel/a_b_c");
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_socket_must_be_private() {
+        use std::os::unix::fs::PermissionsExt;
+        use std::os::unix::net::UnixListener;
+
+        let dir = std::env::temp_dir().join(format!("cairn-sock-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("kernel.sock");
+        let _ = std::fs::remove_file(&path);
+
+        let err = connect_verified(&path).unwrap_err();
+        assert!(is_absent(&err));
+
+        let _listener = UnixListener::bind(&path).unwrap();
+        std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o666)).unwrap();
+        let err = connect_verified(&path).unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::PermissionDenied);
+
+        std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o600)).unwrap();
+        assert!(connect_verified(&path).is_ok());
+
+        let _ = std::fs::remove_dir_all(&dir);
     }
 }
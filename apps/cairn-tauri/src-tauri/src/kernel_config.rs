@@ -0,0 +1,519 @@
+//! How the Python kernel is launched.
+//!
+//! Settings come from `<app config dir>/kernel.json`, then environment
+//! overrides, then built-in discovery. All fields of the file are optional:
+//!
+//! ```json
+//! {
+//!   "python": "/opt/cairn/venv/bin/python",
+//!   "module": "cairn.ui_rpc_server",
+//!   "script": null,
+//!   "args": ["--verbose"],
+//!   "env_allowlist": ["OLLAMA_HOST"],
+//!   "env": { "CAIRN_LOG_LEVEL": "DEBUG" },
+//!   "working_dir": "/opt/cairn",
+//!   "python_path": ["/opt/cairn/src"],
+//!   "transport": "socket",
+//!   "socket_path": "/run/user/1000/cairn/kernel.sock"
+//! }
+//! ```
+//!
+//! Environment overrides: `CAIRN_PYTHON`, `CAIRN_KERNEL_MODULE`,
+//! `CAIRN_KERNEL_SCRIPT`, `CAIRN_KERNEL_ARGS` (whitespace separated),
+//! `CAIRN_KERNEL_CWD`, `CAIRN_KERNEL_PYTHONPATH` (path-list separated),
+//! `CAIRN_KERNEL_TRANSPORT` (`stdio` or `socket`) and `CAIRN_KERNEL_SOCKET`
+//! (a socket path; implies the socket transport).
+//!
+//! Interpreter discovery, highest priority first:
+//! 1. `python` from env or file
+//! 2. a sidecar kernel executable next to the app binary (`cairn-kernel`),
+//!    run directly with no interpreter
+//! 3. a bundled runtime under the resource dir (`kernel/python/bin/python3`),
+//!    with `kernel/site-packages` added to `PYTHONPATH`
+//! 4. `.venv/bin/python` found by walking up from the executable (dev)
+//! 5. `python` on `PATH`
+
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::ffi::OsString;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+/// Name of the launch config file inside the app config dir.
+pub const CONFIG_FILE_NAME: &str = "kernel.json";
+
+/// Module run with `python -m` when neither a module nor a script is set.
+pub const DEFAULT_MODULE: &str = "cairn.ui_rpc_server";
+
+/// Parent environment variables that always reach the kernel, even with an
+/// allowlist configured: locale, home/user lookup, and what Polkit and the
+/// session bus need to show the authentication dialog.
+const BASE_ENV: &[&str] = &[
+    "PATH",
+    "HOME",
+    "USER",
+    "LOGNAME",
+    "LANG",
+    "LC_ALL",
+    "TMPDIR",
+    "XDG_RUNTIME_DIR",
+    "XDG_CONFIG_HOME",
+    "XDG_DATA_HOME",
+    "DBUS_SESSION_BUS_ADDRESS",
+    "DISPLAY",
+    "WAYLAND_DISPLAY",
+];
+
+#[cfg(windows)]
+const SIDECAR_NAME: &str = "cairn-kernel.exe";
+#[cfg(not(windows))]
+const SIDECAR_NAME: &str = "cairn-kernel";
+
+/// How the shell talks to the kernel. See `kernel::KernelTransport`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TransportKind {
+    /// Spawn a private kernel and speak over its stdin/stdout.
+    #[default]
+    Stdio,
+    /// Attach to the kernel listening on a Unix socket, spawning one in
+    /// socket mode if none is running.
+    Socket,
+}
+
+impl TransportKind {
+    fn parse(value: &str) -> Option<Self> {
+        match value {
+            "stdio" => Some(Self::Stdio),
+            "socket" => Some(Self::Socket),
+            _ => None,
+        }
+    }
+}
+
+/// Contents of `kernel.json`, after environment overrides.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct LaunchConfig {
+    /// Interpreter to run. Discovered when unset (see module docs).
+    pub python: Option<PathBuf>,
+    /// Module for `python -m`. Ignored when `script` is set.
+    pub module: Option<String>,
+    /// Entry script to run instead of a module.
+    pub script: Option<PathBuf>,
+    /// Extra arguments appended after the module or script.
+    pub args: Vec<String>,
+    /// When set, the kernel starts from a cleared environment and only these
+    /// parent variables (plus a small base set) are passed through.
+    pub env_allowlist: Option<Vec<String>>,
+    /// Variables set explicitly for the kernel.
+    pub env: BTreeMap<String, String>,
+    pub working_dir: Option<PathBuf>,
+    /// Entries prepended to `PYTHONPATH`.
+    pub python_path: Vec<PathBuf>,
+    pub transport: TransportKind,
+    /// Socket for `TransportKind::Socket`; defaults to `default_socket_path`.
+    pub socket_path: Option<PathBuf>,
+}
+
+impl LaunchConfig {
+    /// Read `<config_dir>/kernel.json`. A missing file is not an error.
+    pub fn load(config_dir: &Path) -> Result<Self, String> {
+        let path = config_dir.join(CONFIG_FILE_NAME);
+        match std::fs::read_to_string(&path) {
+            Ok(text) => {
+                serde_json::from_str(&text).map_err(|e| format!("invalid {}: {e}", path.display()))
+            }
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(Self::default()),
+            Err(e) => Err(format!("cannot read {}: {e}", path.display())),
+        }
+    }
+
+    /// Apply `CAIRN_*` overrides read through `var`.
+    pub fn apply_env(&mut self, var: impl Fn(&str) -> Option<String>) {
+        let var = |name: &str| {
+            var(name)
+                .map(|v| v.trim().to_string())
+                .filter(|v| !v.is_empty())
+        };
+
+        if let Some(p) = var("CAIRN_PYTHON") {
+            self.python = Some(PathBuf::from(p));
+        }
+        if let Some(m) = var("CAIRN_KERNEL_MODULE") {
+            self.module = Some(m);
+            self.script = None;
+        }
+        if let Some(s) = var("CAIRN_KERNEL_SCRIPT") {
+            self.script = Some(PathBuf::from(s));
+        }
+        if let Some(a) = var("CAIRN_KERNEL_ARGS") {
+            self.args = a.split_whitespace().map(str::to_string).collect();
+        }
+        if let Some(d) = var("CAIRN_KERNEL_CWD") {
+            self.working_dir = Some(PathBuf::from(d));
+        }
+        if let Some(p) = var("CAIRN_KERNEL_PYTHONPATH") {
+            self.python_path = std::env::split_paths(&p).collect();
+        }
+        if let Some(t) = var("CAIRN_KERNEL_TRANSPORT") {
+            match TransportKind::parse(&t) {
+                Some(kind) => self.transport = kind,
+                None => eprintln!("[kernel] ignoring unknown CAIRN_KERNEL_TRANSPORT={t}"),
+            }
+        }
+        if let Some(p) = var("CAIRN_KERNEL_SOCKET") {
+            self.transport = TransportKind::Socket;
+            self.socket_path = Some(PathBuf::from(p));
+        }
+    }
+}
+
+/// Where a packaged build keeps its kernel, if it ships one.
+#[derive(Debug, Clone, Default)]
+pub struct BundleLayout {
+    /// Directory holding the app executable (Tauri puts sidecars here).
+    pub exe_dir: Option<PathBuf>,
+    /// Tauri resource directory.
+    pub resource_dir: Option<PathBuf>,
+}
+
+impl BundleLayout {
+    pub fn detect(resource_dir: Option<PathBuf>) -> Self {
+        let exe_dir = std::env::current_exe()
+            .ok()
+            .and_then(|exe| exe.parent().map(Path::to_path_buf));
+        Self {
+            exe_dir,
+            resource_dir,
+        }
+    }
+
+    fn sidecar(&self) -> Option<PathBuf> {
+        let candidate = self.exe_dir.as_ref()?.join(SIDECAR_NAME);
+        candidate.is_file().then_some(candidate)
+    }
+
+    fn bundled_python(&self) -> Option<(PathBuf, PathBuf)> {
+        let root = self.resource_dir.as_ref()?.join("kernel");
+        let python = if cfg!(windows) {
+            root.join("python").join("python.exe")
+        } else {
+            root.join("python").join("bin").join("python3")
+        };
+        python
+            .is_file()
+            .then(|| (python, root.join("site-packages")))
+    }
+}
+
+/// `$XDG_RUNTIME_DIR/cairn/kernel.sock`, or a per-user directory under the
+/// temp dir when there is no runtime dir.
+pub fn default_socket_path() -> PathBuf {
+    let dir = match std::env::var_os("XDG_RUNTIME_DIR").filter(|d| !d.is_empty()) {
+        Some(runtime) => PathBuf::from(runtime).join("cairn"),
+        None => std::env::temp_dir().join(format!("cairn-{}", current_uid())),
+    };
+    dir.join("kernel.sock")
+}
+
+#[cfg(unix)]
+fn current_uid() -> u32 {
+    // SAFETY: getuid(2) cannot fail.
+    unsafe { libc::getuid() }
+}
+
+#[cfg(not(unix))]
+fn current_uid() -> u32 {
+    0
+}
+
+fn find_repo_venv_python() -> Option<PathBuf> {
+    // Walk upward from the current executable looking for `.venv/bin/python`.
+    // This makes `tauri dev` work reliably in a monorepo-style checkout.
+    let exe = std::env::current_exe().ok()?;
+    let mut dir = exe.parent()?.to_path_buf();
+
+    for _ in 0..12 {
+        let candidate = dir.join(".venv").join("bin").join("python");
+        if candidate.is_file() {
+            return Some(candidate);
+        }
+        if !dir.pop() {
+            break;
+        }
+    }
+
+    None
+}
+
+/// A fully resolved kernel command line and environment.
+#[derive(Debug, Clone, PartialEq)]
+pub struct KernelLaunch {
+    pub program: PathBuf,
+    pub args: Vec<String>,
+    /// `None` inherits the parent environment; `Some` starts from a cleared
+    /// one containing only the listed variables.
+    pub allowed_env: Option<Vec<String>>,
+    pub env: BTreeMap<String, String>,
+    pub python_path: Vec<PathBuf>,
+    pub working_dir: Option<PathBuf>,
+    /// Kernel socket; `None` for the stdio transport.
+    pub socket: Option<PathBuf>,
+}
+
+impl Default for KernelLaunch {
+    /// Environment overrides and dev discovery only; used until the app's
+    /// config and resource dirs are known.
+    fn default() -> Self {
+        let mut config = LaunchConfig::default();
+        config.apply_env(|name| std::env::var(name).ok());
+        Self::resolve(config, &BundleLayout::detect(None))
+    }
+}
+
+impl KernelLaunch {
+    pub fn resolve(config: LaunchConfig, layout: &BundleLayout) -> Self {
+        let mut python_path = config.python_path;
+
+        let mut entry_args = Vec::new();
+        let program = if let Some(python) = config.python {
+            python
+        } else if let Some(sidecar) = layout.sidecar() {
+            // A frozen kernel is its own entry point.
+            sidecar
+        } else if let Some((python, site_packages)) = layout.bundled_python() {
+            python_path.push(site_packages);
+            python
+        } else {
+            find_repo_venv_python().unwrap_or_else(|| PathBuf::from("python"))
+        };
+
+        let is_sidecar = layout.sidecar().is_some_and(|s| s == program);
+        if !is_sidecar {
+            match config.script {
+                Some(script) => entry_args.push(script.to_string_lossy().into_owned()),
+                None => {
+                    entry_args.push("-m".to_string());
+                    entry_args.push(config.module.unwrap_or_else(|| DEFAULT_MODULE.to_string()));
+                }
+            }
+        }
+        entry_args.extend(config.args);
+
+        let allowed_env = config.env_allowlist.map(|mut list| {
+            list.extend(BASE_ENV.iter().map(|v| v.to_string()));
+            list.sort();
+            list.dedup();
+            list
+        });
+
+        Self {
+            program,
+            args: entry_args,
+            allowed_env,
+            env: config.env,
+            python_path,
+            working_dir: config.working_dir,
+            socket: match config.transport {
+                TransportKind::Stdio => None,
+                TransportKind::Socket => {
+                    Some(config.socket_path.unwrap_or_else(default_socket_path))
+                }
+            },
+        }
+    }
+
+    /// Load `kernel.json` from `config_dir`, apply env overrides and resolve
+    /// against the bundle layout. A broken config file is reported and
+    /// ignored rather than preventing the kernel from starting.
+    pub fn load(config_dir: Option<&Path>, layout: &BundleLayout) -> Self {
+        let mut config = match config_dir.map(LaunchConfig::load) {
+            Some(Ok(config)) => config,
+            Some(Err(e)) => {
+                eprintln!("[kernel] {e}; using defaults");
+                LaunchConfig::default()
+            }
+            None => LaunchConfig::default(),
+        };
+        config.apply_env(|name| std::env::var(name).ok());
+        Self::resolve(config, layout)
+    }
+
+    /// Build the `Command`; stdio is left for the caller to configure.
+    pub fn command(&self) -> Command {
+        let mut cmd = Command::new(&self.program);
+        cmd.args(&self.args);
+
+        if let Some(allowed) = &self.allowed_env {
+            cmd.env_clear();
+            for name in allowed {
+                if let Some(value) = std::env::var_os(name) {
+                    cmd.env(name, value);
+                }
+            }
+        }
+        cmd.envs(&self.env);
+
+        if !self.python_path.is_empty() {
+            let mut entries = self.python_path.clone();
+            let inherited =
+                self.env.get("PYTHONPATH").map(OsString::from).or_else(|| {
+                    match &self.allowed_env {
+                        Some(allowed) if !allowed.iter().any(|v| v == "PYTHONPATH") => None,
+                        _ => std::env::var_os("PYTHONPATH"),
+                    }
+                });
+            if let Some(existing) = inherited {
+                entries.extend(std::env::split_paths(&existing));
+            }
+            if let Ok(joined) = std::env::join_paths(entries) {
+                cmd.env("PYTHONPATH", joined);
+            }
+        }
+
+        if let Some(dir) = &self.working_dir {
+            cmd.current_dir(dir);
+        }
+        cmd
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn no_bundle() -> BundleLayout {
+        BundleLayout {
+            exe_dir: None,
+            resource_dir: None,
+        }
+    }
+
+    #[test]
+    fn test_parse_config_file_fields() {
+        let config: LaunchConfig = serde_json::from_str(
+            r#"{ "python": "/opt/py", "args": ["-X", "dev"], "env": { "A": "1" } }"#,
+        )
+        .unwrap();
+        assert_eq!(config.python, Some(PathBuf::from("/opt/py")));
+        assert_eq!(config.args, vec!["-X", "dev"]);
+        assert_eq!(config.env.get("A").map(String::as_str), Some("1"));
+
+        assert!(serde_json::from_str::<LaunchConfig>(r#"{ "pyhton": "x" }"#).is_err());
+    }
+
+    #[test]
+    fn test_env_overrides_file() {
+        let mut config = LaunchConfig {
+            python: Some(PathBuf::from("/from/file")),
+            script: Some(PathBuf::from("run.py")),
+            ..Default::default()
+        };
+        config.apply_env(|name| match name {
+            "CAIRN_PYTHON" => Some("/from/env".to_string()),
+            "CAIRN_KERNEL_MODULE" => Some("cairn.alt".to_string()),
+            "CAIRN_KERNEL_ARGS" => Some("--a  --b".to_string()),
+            "CAIRN_KERNEL_CWD" => Some("   ".to_string()),
+            _ => None,
+        });
+        assert_eq!(config.python, Some(PathBuf::from("/from/env")));
+        assert_eq!(config.module.as_deref(), Some("cairn.alt"));
+        assert_eq!(config.script, None);
+        assert_eq!(config.args, vec!["--a", "--b"]);
+        assert_eq!(config.working_dir, None);
+    }
+
+    #[test]
+    fn test_resolve_module_and_script() {
+        let launch = KernelLaunch::resolve(
+            LaunchConfig {
+                python: Some(PathBuf::from("/py")),
+                args: vec!["--x".to_string()],
+                ..Default::default()
+            },
+            &no_bundle(),
+        );
+        assert_eq!(launch.program, PathBuf::from("/py"));
+        assert_eq!(launch.args, vec!["-m", DEFAULT_MODULE, "--x"]);
+
+        let launch = KernelLaunch::resolve(
+            LaunchConfig {
+                python: Some(PathBuf::from("/py")),
+                script: Some(PathBuf::from("/srv/kernel.py")),
+                ..Default::default()
+            },
+            &no_bundle(),
+        );
+        assert_eq!(launch.args, vec!["/srv/kernel.py"]);
+    }
+
+    #[test]
+    fn test_socket_transport_selection() {
+        let launch = KernelLaunch::resolve(
+            LaunchConfig {
+                python: Some(PathBuf::from("/py")),
+                ..Default::default()
+            },
+            &no_bundle(),
+        );
+        assert_eq!(launch.socket, None);
+
+        let mut config = LaunchConfig {
+            python: Some(PathBuf::from("/py")),
+            ..Default::default()
+        };
+        config.apply_env(|name| match name {
+            "CAIRN_KERNEL_SOCKET" => Some("/run/k.sock".to_string()),
+            _ => None,
+        });
+        let launch = KernelLaunch::resolve(config, &no_bundle());
+        assert_eq!(launch.socket, Some(PathBuf::from("/run/k.sock")));
+
+        let config: LaunchConfig = serde_json::from_str(r#"{ "transport": "socket" }"#).unwrap();
+        assert_eq!(config.transport, TransportKind::Socket);
+        let launch = KernelLaunch::resolve(config, &no_bundle());
+        assert_eq!(launch.socket, Some(default_socket_path()));
+    }
+
+    #[test]
+    fn test_allowlist_always_includes_base_env() {
+        let launch = KernelLaunch::resolve(
+            LaunchConfig {
+                python: Some(PathBuf::from("/py")),
+                env_allowlist: Some(vec!["OLLAMA_HOST".to_string(), "PATH".to_string()]),
+                ..Default::default()
+            },
+            &no_bundle(),
+        );
+        let allowed = launch.allowed_env.unwrap();
+        assert!(allowed.contains(&"OLLAMA_HOST".to_string()));
+        assert!(allowed.contains(&"HOME".to_string()));
+        assert_eq!(allowed.iter().filter(|v| *v == "PATH").count(), 1);
+    }
+
+    #[test]
+    fn test_bundled_runtime_layout() {
+        let root = std::env::temp_dir().join(format!("cairn-bundle-{}", std::process::id()));
+        let bin = root.join("kernel").join("python").join("bin");
+        std::fs::create_dir_all(&bin).unwrap();
+        std::fs::write(bin.join("python3"), b"").unwrap();
+
+        let layout = BundleLayout {
+            exe_dir: None,
+            resource_dir: Some(root.clone()),
+        };
+        let launch = KernelLaunch::resolve(LaunchConfig::default(), &layout);
+        let _ = std::fs::remove_dir_all(&root);
+
+        if cfg!(windows) {
+            return;
+        }
+        assert_eq!(launch.program, bin.join("python3"));
+        assert_eq!(
+            launch.python_path,
+            vec![root.join("kernel").join("site-packages")]
+        );
+        assert_eq!(launch.args, vec!["-m", DEFAULT_MODULE]);
+    }
+}
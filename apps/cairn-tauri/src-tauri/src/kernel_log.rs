@@ -0,0 +1,308 @@
+//! Captured kernel stderr.
+//!
+//! The kernel's stderr is read line by line on a background thread (see
+//! `kernel.rs`). Each line is tagged with a level parsed from the Python log
+//! format, kept in a bounded in-memory ring buffer for the diagnostics panel,
+//! and appended to a size-rotated file under the app log dir so logs survive
+//! in packaged builds.
+//!
+//! Recognised line shapes:
+//!   `2026-01-01T12:00:00+0000 INFO cairn.db: message`  — `logging_setup.py`
+//!   `WARNING:cairn.db:message`                          — stdlib default format
+//!   `[JS] message`                                      — `debug/log` from the webview
+//!   `[stdout] message`                                  — non-protocol kernel stdout
+//! Indented lines and `Traceback` headers inherit the previous line's level.
+
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::fs::{self, File, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Prefix for kernel stdout that is not a protocol message (see `framing.rs`).
+pub const STDOUT_PREFIX: &str = "[stdout] ";
+
+/// Lines kept in memory for `kernel_logs`.
+pub const DEFAULT_CAPACITY: usize = 2000;
+
+/// Rotate `kernel.log` once it grows past this size.
+const MAX_FILE_BYTES: u64 = 5 * 1024 * 1024;
+
+/// Rotated files kept alongside `kernel.log` (`kernel.log.1` .. `.N`).
+const BACKUP_COUNT: u32 = 3;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LogLevel {
+    Debug,
+    Info,
+    Warning,
+    Error,
+    Critical,
+}
+
+impl LogLevel {
+    fn parse(word: &str) -> Option<Self> {
+        match word {
+            "DEBUG" => Some(Self::Debug),
+            "INFO" => Some(Self::Info),
+            "WARNING" | "WARN" => Some(Self::Warning),
+            "ERROR" => Some(Self::Error),
+            "CRITICAL" | "FATAL" => Some(Self::Critical),
+            _ => None,
+        }
+    }
+}
+
+/// One captured stderr line.
+#[derive(Debug, Clone, Serialize)]
+pub struct LogLine {
+    /// Capture time, milliseconds since the Unix epoch.
+    pub timestamp_ms: u64,
+    pub level: LogLevel,
+    /// Logger name (`cairn.db`), `js` for `[JS]` lines, `stdout` for stray
+    /// kernel stdout, or absent.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub source: Option<String>,
+    pub message: String,
+}
+
+/// Split a raw stderr line into level, source and message.
+fn parse_line(raw: &str, previous: Option<LogLevel>) -> (LogLevel, Option<String>, String) {
+    if let Some(rest) = raw.strip_prefix("[JS] ") {
+        return (LogLevel::Info, Some("js".to_string()), rest.to_string());
+    }
+    if let Some(rest) = raw.strip_prefix(STDOUT_PREFIX) {
+        return (
+            LogLevel::Warning,
+            Some("stdout".to_string()),
+            rest.to_string(),
+        );
+    }
+
+    // `<timestamp> LEVEL logger: message`
+    let mut words = raw.splitn(3, ' ');
+    if let (Some(_ts), Some(level), Some(rest)) = (words.next(), words.next(), words.next()) {
+        if let Some(level) = LogLevel::parse(level) {
+            return match rest.split_once(": ") {
+                Some((name, msg)) => (level, Some(name.to_string()), msg.to_string()),
+                None => (level, None, rest.to_string()),
+            };
+        }
+    }
+
+    // `LEVEL:logger:message`
+    let mut parts = raw.splitn(3, ':');
+    if let (Some(level), Some(name), Some(msg)) = (parts.next(), parts.next(), parts.next()) {
+        if let Some(level) = LogLevel::parse(level) {
+            return (level, Some(name.to_string()), msg.to_string());
+        }
+    }
+
+    let continuation = raw.starts_with(char::is_whitespace) || raw.starts_with("Traceback");
+    let level = match previous {
+        Some(level) if continuation => level,
+        _ => LogLevel::Info,
+    };
+    (level, None, raw.to_string())
+}
+
+fn now_ms() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis() as u64)
+        .unwrap_or(0)
+}
+
+/// `kernel.log` plus numbered backups, rotated by size.
+struct RotatingFile {
+    path: PathBuf,
+    file: File,
+    size: u64,
+}
+
+impl RotatingFile {
+    fn open(dir: &Path) -> std::io::Result<Self> {
+        fs::create_dir_all(dir)?;
+        let path = dir.join("kernel.log");
+        let file = OpenOptions::new().create(true).append(true).open(&path)?;
+        let size = file.metadata()?.len();
+        Ok(Self { path, file, size })
+    }
+
+    fn backup(&self, n: u32) -> PathBuf {
+        let mut name = self.path.clone().into_os_string();
+        name.push(format!(".{n}"));
+        PathBuf::from(name)
+    }
+
+    fn write_line(&mut self, line: &str) -> std::io::Result<()> {
+        if self.size + line.len() as u64 + 1 > MAX_FILE_BYTES {
+            self.rotate()?;
+        }
+        writeln!(self.file, "{line}")?;
+        self.size += line.len() as u64 + 1;
+        Ok(())
+    }
+
+    fn rotate(&mut self) -> std::io::Result<()> {
+        for n in (1..BACKUP_COUNT).rev() {
+            let from = self.backup(n);
+            if from.exists() {
+                fs::rename(&from, self.backup(n + 1))?;
+            }
+        }
+        fs::rename(&self.path, self.backup(1))?;
+        self.file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)?;
+        self.size = 0;
+        Ok(())
+    }
+}
+
+struct LogInner {
+    lines: VecDeque<LogLine>,
+    capacity: usize,
+    file: Option<RotatingFile>,
+}
+
+/// Ring buffer of recent kernel stderr lines, shared across kernel restarts.
+pub struct KernelLog {
+    inner: Mutex<LogInner>,
+}
+
+impl KernelLog {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            inner: Mutex::new(LogInner {
+                lines: VecDeque::with_capacity(capacity),
+                capacity,
+                file: None,
+            }),
+        }
+    }
+
+    /// Also append every line to `<dir>/kernel.log`, rotating by size.
+    pub fn set_file_dir(&self, dir: &Path) -> std::io::Result<()> {
+        let file = RotatingFile::open(dir)?;
+        if let Ok(mut inner) = self.inner.lock() {
+            inner.file = Some(file);
+        }
+        Ok(())
+    }
+
+    /// Record one raw stderr line.
+    pub fn push(&self, raw: &str) {
+        let Ok(mut inner) = self.inner.lock() else {
+            return;
+        };
+
+        if let Some(file) = inner.file.as_mut() {
+            if let Err(e) = file.write_line(raw) {
+                eprintln!("[kernel-log] file write failed, disabling: {e}");
+                inner.file = None;
+            }
+        }
+
+        let previous = inner.lines.back().map(|l| l.level);
+        let (level, source, message) = parse_line(raw, previous);
+        if inner.lines.len() == inner.capacity {
+            inner.lines.pop_front();
+        }
+        inner.lines.push_back(LogLine {
+            timestamp_ms: now_ms(),
+            level,
+            source,
+            message,
+        });
+    }
+
+    /// Recent lines at or above `min_level`, captured at or after `since_ms`,
+    /// oldest first. `limit` keeps only the newest N matches.
+    pub fn query(
+        &self,
+        min_level: Option<LogLevel>,
+        since_ms: Option<u64>,
+        limit: Option<usize>,
+    ) -> Vec<LogLine> {
+        let Ok(inner) = self.inner.lock() else {
+            return Vec::new();
+        };
+        let mut matched: Vec<LogLine> = inner
+            .lines
+            .iter()
+            .filter(|l| min_level.is_none_or(|min| l.level >= min))
+            .filter(|l| since_ms.is_none_or(|since| l.timestamp_ms >= since))
+            .cloned()
+            .collect();
+        if let Some(limit) = limit {
+            let excess = matched.len().saturating_sub(limit);
+            matched.drain(..excess);
+        }
+        matched
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_configured_format() {
+        let (level, source, msg) = parse_line(
+            "2026-01-01T12:00:00+0000 WARNING cairn.db: slow query",
+            None,
+        );
+        assert_eq!(level, LogLevel::Warning);
+        assert_eq!(source.as_deref(), Some("cairn.db"));
+        assert_eq!(msg, "slow query");
+    }
+
+    #[test]
+    fn test_parse_default_format_and_js() {
+        let (level, source, _) = parse_line("ERROR:cairn.rpc:boom", None);
+        assert_eq!(level, LogLevel::Error);
+        assert_eq!(source.as_deref(), Some("cairn.rpc"));
+
+        let (level, source, msg) = parse_line("[JS] clicked", None);
+        assert_eq!(level, LogLevel::Info);
+        assert_eq!(source.as_deref(), Some("js"));
+        assert_eq!(msg, "clicked");
+
+        let (level, source, msg) = parse_line("[stdout] Loading model", None);
+        assert_eq!(level, LogLevel::Warning);
+        assert_eq!(source.as_deref(), Some("stdout"));
+        assert_eq!(msg, "Loading model");
+    }
+
+    #[test]
+    fn test_traceback_inherits_level() {
+        let (level, _, _) = parse_line("  File \"x.py\", line 1", Some(LogLevel::Error));
+        assert_eq!(level, LogLevel::Error);
+        let (level, _, _) = parse_line("[ui_rpc_server] Backend ready", Some(LogLevel::Error));
+        assert_eq!(level, LogLevel::Info);
+    }
+
+    #[test]
+    fn test_ring_buffer_and_query() {
+        let log = KernelLog::new(3);
+        log.push("DEBUG:a:one");
+        log.push("ERROR:a:two");
+        log.push("INFO:a:three");
+        log.push("ERROR:a:four");
+
+        let all = log.query(None, None, None);
+        assert_eq!(all.len(), 3);
+        assert_eq!(all[0].message, "two");
+
+        let errors = log.query(Some(LogLevel::Error), None, None);
+        assert_eq!(errors.len(), 2);
+
+        let newest = log.query(None, None, Some(1));
+        assert_eq!(newest[0].message, "four");
+    }
+}
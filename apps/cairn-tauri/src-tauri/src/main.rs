@@ -1,17 +1,102 @@
 #![cfg_attr(not(debug_assertions), windows_subsystem = "windows")]
 
+mod audit;
 mod auth;
+mod blob;
+mod cache;
+mod cassette;
+mod error;
+mod framing;
 mod kernel;
+mod kernel_config;
+mod kernel_log;
+mod metrics;
+mod policy;
 mod pty;
+mod schema;
+#[cfg(target_os = "linux")]
+mod screen_lock;
 
-use auth::{AuthResult, AuthState, SessionInfo};
-use kernel::{KernelError, KernelProcess};
+use audit::{AuditLog, PendingCall, Verification};
+use auth::{
+    AuthResult, AuthState, ExpiryReason, KernelSession, SessionExpiredEvent, SessionInfo,
+    SessionPolicy, TokenHash, KERNEL_PROBE_FAILURE_LIMIT, SESSION_EXPIRED_EVENT,
+    SESSION_EXPIRING_EVENT, SESSION_REAP_INTERVAL,
+};
+use blob::{BlobKind, BlobTickets, UploadGrants, UploadProgress, BLOB_SCHEME, UPLOAD_METHODS};
+use cache::{stale_after, ResponseCache};
+use cassette::{FakeKernel, Recorder, RECORD_ENV, REPLAY_ENV};
+use error::{rpc_result, CommandError};
+use kernel::{
+    BatchOutcome, KernelEventSink, KernelInfo, KernelStatus, KernelStatusEvent, KernelSupervisor,
+    RequestOptions, RestartPolicy, StreamEvent, KERNEL_STATUS_EVENT, SHUTDOWN_GRACE,
+};
+use kernel_config::{BundleLayout, KernelLaunch};
+use kernel_log::{LogLevel, LogLine};
+use metrics::{MetricsSnapshot, METRICS_FILE_ENV, METRICS_FILE_INTERVAL};
+use policy::{Confirmation, Policy, PolicyError};
+use schema::SchemaRegistry;
+use serde::Deserialize;
 use serde_json::{json, Value};
-use std::sync::{Arc, Mutex};
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::Duration;
 
-use tauri::{Manager, State};
+use tauri::ipc::Channel;
+use tauri::{Emitter, Manager, State};
+use zeroize::Zeroize;
 
-struct KernelState(Arc<Mutex<Option<KernelProcess>>>);
+/// Managed state for the Python kernel.
+///
+/// The supervisor hands out `Arc<KernelProcess>` handles; requests are issued
+/// without holding any shared lock, so concurrent commands are multiplexed
+/// over the same process, and a crashed kernel is restarted in place.
+struct KernelState(Arc<KernelSupervisor>);
+
+/// Delivers supervisor events to the webviews.
+struct AppEventSink(tauri::AppHandle);
+
+impl KernelEventSink for AppEventSink {
+    fn status(&self, event: &KernelStatusEvent) {
+        // Sessions die with the kernel holding their keys.
+        match event.status {
+            KernelStatus::Ready => end_kernel_sessions(&self.0, event.epoch),
+            KernelStatus::Crashed => end_kernel_sessions(&self.0, event.epoch + 1),
+            KernelStatus::Starting | KernelStatus::Restarting => {}
+        }
+        let _ = self.0.emit(KERNEL_STATUS_EVENT, event);
+    }
+
+    fn notify(&self, window: &str, event: &str, payload: &Value) {
+        let _ = self.0.emit_to(window, event, payload);
+    }
+
+    fn notification(&self, method: &str, params: &Value) {
+        // Chat tool use writes after `cairn/chat_async` has returned.
+        if let Some(cache) = self.0.try_state::<CacheState>() {
+            cache.0.invalidate_for(method, params);
+        }
+    }
+}
+
+/// Managed state for the kernel method allowlist, its rate limits, and the
+/// param schemas checked before a call is forwarded.
+struct PolicyState {
+    methods: Policy,
+    schemas: SchemaRegistry,
+}
+
+/// Managed state for cached and coalesced kernel reads (see `cache.rs`).
+struct CacheState(Arc<ResponseCache>);
+
+/// Managed state for the shell's own audit log of kernel calls.
+struct AuditState(Arc<AuditLog>);
+
+/// Managed state for outstanding `cairn-blob://` tickets (see `blob.rs`).
+struct BlobState(Arc<BlobTickets>);
+
+/// Managed state for files picked for upload in the shell's dialog.
+struct UploadState(UploadGrants);
 
 /// Managed state for the active PTY session (ReOS terminal).
 struct PtyStateWrapper(pty::PtyState);
@@ -33,7 +118,7 @@ async fn auth_login(
     state: State<'_, KernelState>,
     auth_state: State<'_, AuthState>,
     username: String,
-) -> Result<AuthResult, String> {
+) -> Result<AuthResult, CommandError> {
     // Validate username format (prevent injection)
     if username.is_empty() || username.len() > 32 {
         return Ok(AuthResult {
@@ -41,90 +126,143 @@ async fn auth_login(
             session_token: None,
             username: None,
             error: Some("Invalid username".to_string()),
+            session_ref: None,
         });
     }
 
     // Forward to Python kernel for Polkit authentication
     let state_clone = state.0.clone();
-    let result = tauri::async_runtime::spawn_blocking(move || {
-        let mut guard = state_clone.lock().map_err(|_| "lock poisoned".to_string())?;
-        if guard.is_none() {
-            let proc = KernelProcess::start().map_err(|e| e.to_string())?;
-            *guard = Some(proc);
-        }
-
-        let proc = guard
-            .as_mut()
-            .ok_or_else(|| KernelError::NotStarted.to_string())?;
+    let (envelope, epoch) = tauri::async_runtime::spawn_blocking(move || {
+        let proc = state_clone.get_or_start()?;
 
         // Call Python's auth/login endpoint (Polkit handles auth via system dialog)
-        proc.request(
+        let envelope = proc.request(
             "auth/login",
             json!({
                 "username": username,
             }),
-        )
-        .map_err(|e| e.to_string())
+        )?;
+        Ok::<_, CommandError>((envelope, proc.epoch()))
     })
     .await
-    .map_err(|e| format!("auth_login join error: {e}"))??;
+    .map_err(|e| CommandError::join("auth_login", e))??;
 
     // Parse response from Python - extract the 'result' field from JSON-RPC envelope
-    let result_inner = result
-        .get("result")
-        .ok_or_else(|| "No result field in JSON-RPC response".to_string())?;
-    let auth_result: AuthResult = serde_json::from_value(result_inner.clone())
-        .map_err(|e| format!("Failed to parse auth response: {e}"))?;
+    let mut auth_result: AuthResult = serde_json::from_value(rpc_result(envelope)?)
+        .map_err(|e| CommandError::Internal(format!("Failed to parse auth response: {e}")))?;
 
     // If successful, store the session in Rust
     if auth_result.success {
         if let (Some(token), Some(uname)) = (&auth_result.session_token, &auth_result.username) {
-            let session = auth::create_session(token.clone(), uname.clone());
-            let mut store = auth_state.0.lock().map_err(|_| "lock poisoned")?;
-            store.insert(session);
+            let session_ref = auth_result.session_ref.take().ok_or_else(|| {
+                CommandError::Internal("auth/login returned no session_ref".to_string())
+            })?;
+            let mut session = auth::create_session(uname.clone());
+            session.kernel = Some(KernelSession { epoch, session_ref });
+            let mut store = auth_state.0.lock()?;
+            store.insert(token, session);
         }
     }
 
+    // The token is wiped when Tauri drops the result after serializing it.
+    // The kernel's reply line and the serialized IPC response are buffers
+    // we do not own, so those copies are beyond reach.
     Ok(auth_result)
 }
 
 /// Log out and destroy a session (zeroizes key material)
+///
+/// The session's hash and the token passed in are wiped here; the kernel
+/// is told to drop the session and its derived key as well.
 #[tauri::command]
-fn auth_logout(auth_state: State<'_, AuthState>, session_token: String) -> Result<(), String> {
-    let mut store = auth_state.0.lock().map_err(|_| "lock poisoned")?;
-    if store.remove(&session_token) {
-        Ok(())
-    } else {
-        Err("Session not found".to_string())
-    }
+async fn auth_logout<R: tauri::Runtime>(
+    app: tauri::AppHandle<R>,
+    auth_state: State<'_, AuthState>,
+    mut session_token: String,
+) -> Result<(), CommandError> {
+    let removed = auth_state.0.lock()?.remove(&session_token);
+    session_token.zeroize();
+    let session = removed.ok_or_else(|| CommandError::Auth("Session not found".to_string()))?;
+
+    tauri::async_runtime::spawn_blocking(move || {
+        if let Err(e) = kernel_session_call(&app, session.kernel.as_ref(), "auth/logout") {
+            eprintln!("[auth] kernel logout failed: {e}");
+        }
+    })
+    .await
+    .map_err(|e| CommandError::join("auth_logout", e))
 }
 
 /// Validate a session token
+///
+/// A session the kernel no longer knows is ended here as well.
 #[tauri::command]
-fn auth_validate(auth_state: State<'_, AuthState>, session_token: String) -> Result<bool, String> {
-    let store = auth_state.0.lock().map_err(|_| "lock poisoned")?;
-    Ok(store.get(&session_token).is_some())
+async fn auth_validate<R: tauri::Runtime>(
+    app: tauri::AppHandle<R>,
+    auth_state: State<'_, AuthState>,
+    session_token: String,
+) -> Result<bool, CommandError> {
+    let hash = {
+        let store = auth_state.0.lock()?;
+        if store.get(&session_token).is_none() {
+            return Ok(false);
+        }
+        store.hash(&session_token)
+    };
+    let (app_clone, hash_clone) = (app.clone(), hash.clone());
+    let known = tauri::async_runtime::spawn_blocking(move || {
+        kernel_knows_session(&app_clone, &hash_clone, "auth/validate", "valid")
+    })
+    .await
+    .map_err(|e| CommandError::join("auth_validate", e))?;
+    if known == Some(false) {
+        end_session(&app, &hash, ExpiryReason::Kernel);
+        return Ok(false);
+    }
+    Ok(true)
 }
 
-/// Refresh session activity timestamp
+/// Refresh session activity timestamp, here and in the kernel
 #[tauri::command]
-fn auth_refresh(auth_state: State<'_, AuthState>, session_token: String) -> Result<(), String> {
-    let mut store = auth_state.0.lock().map_err(|_| "lock poisoned")?;
-    match store.get_mut(&session_token) {
-        Some(session) => {
-            session.refresh();
-            Ok(())
+async fn auth_refresh<R: tauri::Runtime>(
+    app: tauri::AppHandle<R>,
+    auth_state: State<'_, AuthState>,
+    session_token: String,
+) -> Result<(), CommandError> {
+    let hash = {
+        let mut store = auth_state.0.lock()?;
+        let hash = store.hash(&session_token);
+        match store.get_mut(&session_token) {
+            Some(session) => session.refresh(),
+            None => {
+                return Err(CommandError::Auth(
+                    "Session not found or expired".to_string(),
+                ))
+            }
         }
-        None => Err("Session not found or expired".to_string()),
+        hash
+    };
+    let (app_clone, hash_clone) = (app.clone(), hash.clone());
+    let known = tauri::async_runtime::spawn_blocking(move || {
+        kernel_knows_session(&app_clone, &hash_clone, "auth/refresh", "success")
+    })
+    .await
+    .map_err(|e| CommandError::join("auth_refresh", e))?;
+    if known == Some(false) {
+        end_session(&app, &hash, ExpiryReason::Kernel);
+        return Err(CommandError::Auth(
+            "Session ended by the kernel".to_string(),
+        ));
     }
+    Ok(())
 }
 
 /// Get the current system username
 #[tauri::command]
-fn get_system_username() -> Result<String, String> {
+fn get_system_username() -> Result<String, CommandError> {
     std::env::var("USER")
         .or_else(|_| std::env::var("USERNAME"))
-        .map_err(|_| "Could not determine username".to_string())
+        .map_err(|_| CommandError::Internal("Could not determine username".to_string()))
 }
 
 /// Get current session info (for UI display)
@@ -132,89 +270,973 @@ fn get_system_username() -> Result<String, String> {
 fn auth_get_session(
     auth_state: State<'_, AuthState>,
     session_token: String,
-) -> Result<SessionInfo, String> {
-    let store = auth_state.0.lock().map_err(|_| "lock poisoned")?;
-    auth::validate_session(&store, &session_token).ok_or_else(|| "Session not found".to_string())
+) -> Result<SessionInfo, CommandError> {
+    let store = auth_state.0.lock()?;
+    auth::validate_session(&store, &session_token)
+        .ok_or_else(|| CommandError::Auth("Session not found".to_string()))
+}
+
+/// Send one of the kernel's `auth/*` methods for a session, named by its
+/// `session_ref`, if the kernel that holds it is still running. `Ok(None)`
+/// when it is not, or the session has no kernel: a kernel that has exited
+/// holds no keys, and none is started just to be told so.
+fn kernel_session_call<R: tauri::Runtime>(
+    app: &tauri::AppHandle<R>,
+    kernel: Option<&KernelSession>,
+    method: &str,
+) -> Result<Option<Value>, CommandError> {
+    let Some(kernel) = kernel else {
+        return Ok(None);
+    };
+    let Some(proc) = app.state::<KernelState>().0.current() else {
+        return Ok(None);
+    };
+    if kernel.epoch != proc.epoch() {
+        return Ok(None);
+    }
+    let params = json!({ "session_ref": kernel.session_ref });
+    let opts = RequestOptions {
+        timeout: Some(Duration::from_secs(5)),
+        ..Default::default()
+    };
+    Ok(Some(rpc_result(proc.request_with(method, params, opts)?)?))
+}
+
+/// Whether the kernel still holds the session stored under `hash`, asking
+/// it with `method` and reading the boolean `field` of the result.
+///
+/// Sessions the kernel never saw (dev sessions) are always known; ones whose
+/// kernel has exited never are. `None` if the kernel could not be asked
+/// (busy, hung or answering with an error): the reaper keeps asking and ends
+/// the session after `KERNEL_PROBE_FAILURE_LIMIT` sweeps.
+fn kernel_knows_session<R: tauri::Runtime>(
+    app: &tauri::AppHandle<R>,
+    hash: &TokenHash,
+    method: &str,
+    field: &str,
+) -> Option<bool> {
+    let kernel = match app.state::<AuthState>().0.lock() {
+        Ok(store) => match store.get_hashed(hash) {
+            Some(s) => s.kernel.clone(),
+            None => return Some(false),
+        },
+        Err(_) => return Some(false),
+    };
+    if kernel.is_none() {
+        return Some(true);
+    }
+    match kernel_session_call(app, kernel.as_ref(), method) {
+        Ok(Some(result)) => Some(result[field].as_bool().unwrap_or(false)),
+        Ok(None) => Some(false),
+        Err(e) => {
+            eprintln!("[auth] kernel {method} failed: {e}");
+            None
+        }
+    }
+}
+
+/// End the session stored under `hash` and tell every window why.
+fn end_session<R: tauri::Runtime>(
+    app: &tauri::AppHandle<R>,
+    hash: &TokenHash,
+    reason: ExpiryReason,
+) {
+    let removed = match app.state::<AuthState>().0.lock() {
+        Ok(mut store) => store.remove_hashed(hash),
+        Err(_) => return,
+    };
+    if let Some(session) = removed {
+        let _ = app.emit(
+            SESSION_EXPIRED_EVENT,
+            SessionExpiredEvent::new(&session, reason),
+        );
+    }
+}
+
+/// End the sessions held by kernels older than `epoch`, after a crash or
+/// once a new kernel is up.
+fn end_kernel_sessions<R: tauri::Runtime>(app: &tauri::AppHandle<R>, epoch: u64) {
+    let ended = match app.state::<AuthState>().0.lock() {
+        Ok(mut store) => store.end_kernel_sessions(epoch),
+        Err(_) => return,
+    };
+    for session in ended {
+        let event = SessionExpiredEvent::new(&session, ExpiryReason::Kernel);
+        let _ = app.emit(SESSION_EXPIRED_EVENT, event);
+    }
+}
+
+/// End every session because the desktop locked or is going to sleep, tell
+/// the kernel to zeroize their keys, and emit `LOCKED_EVENT`.
+#[cfg(target_os = "linux")]
+fn lock_sessions<R: tauri::Runtime>(app: &tauri::AppHandle<R>, cause: screen_lock::LockCause) {
+    let sessions = match app.state::<AuthState>().0.lock() {
+        Ok(mut store) => store.drain(),
+        Err(_) => return,
+    };
+    for session in &sessions {
+        if let Err(e) = kernel_session_call(app, session.kernel.as_ref(), "auth/logout") {
+            eprintln!("[screen-lock] kernel logout failed: {e}");
+        }
+    }
+    let event = screen_lock::LockedEvent {
+        cause,
+        sessions: sessions.len(),
+    };
+    let _ = app.emit(screen_lock::LOCKED_EVENT, event);
+}
+
+/// Drop expired sessions from the store, tell the kernel to forget their
+/// derived keys, and let every window know so the lock screen can show.
+/// Sessions about to expire get a `SESSION_EXPIRING_EVENT` first.
+///
+/// Live sessions are then checked with the kernel, whose own idle timeout
+/// may have ended them. Ones used since the last sweep are refreshed there
+/// so its idle timer follows real activity; the rest are only validated,
+/// which does not extend them.
+///
+/// Run every `SESSION_REAP_INTERVAL` by the `session-reaper` thread.
+fn reap_expired_sessions<R: tauri::Runtime>(app: &tauri::AppHandle<R>) {
+    let (expired, expiring) = match app.state::<AuthState>().0.lock() {
+        Ok(mut store) => (store.cleanup_expired(), store.take_expiring()),
+        Err(_) => return,
+    };
+    for warning in expiring {
+        let _ = app.emit(SESSION_EXPIRING_EVENT, warning);
+    }
+    for (session, reason) in expired {
+        if let Err(e) = kernel_session_call(app, session.kernel.as_ref(), "auth/logout") {
+            eprintln!("[auth] kernel logout of expired session failed: {e}");
+        }
+        let _ = app.emit(
+            SESSION_EXPIRED_EVENT,
+            SessionExpiredEvent::new(&session, reason),
+        );
+    }
+
+    let Some(epoch) = app.state::<KernelState>().0.current().map(|p| p.epoch()) else {
+        return;
+    };
+    end_kernel_sessions(app, epoch);
+    let sessions: Vec<(TokenHash, bool)> = match app.state::<AuthState>().0.lock() {
+        Ok(store) => store
+            .kernel_sessions(epoch)
+            .into_iter()
+            .map(|hash| {
+                let active = store
+                    .get_hashed(&hash)
+                    .is_some_and(|s| s.last_activity.elapsed() < SESSION_REAP_INTERVAL);
+                (hash, active)
+            })
+            .collect(),
+        Err(_) => return,
+    };
+    for (hash, active) in sessions {
+        let known = if active {
+            kernel_knows_session(app, &hash, "auth/refresh", "success")
+        } else {
+            kernel_knows_session(app, &hash, "auth/validate", "valid")
+        };
+        let failures = match app.state::<AuthState>().0.lock() {
+            Ok(mut store) => store.record_kernel_probe(&hash, known.is_some()),
+            Err(_) => return,
+        };
+        if known == Some(false) || failures >= KERNEL_PROBE_FAILURE_LIMIT {
+            end_session(app, &hash, ExpiryReason::Kernel);
+        }
+    }
 }
 
 // =============================================================================
 // Kernel Commands (now session-aware)
 // =============================================================================
 
+/// Start the kernel if it is not already running. Spawning it and waiting
+/// for its handshake runs on the blocking pool.
 #[tauri::command]
-fn kernel_start(state: State<'_, KernelState>) -> Result<(), String> {
-    let mut guard = state.0.lock().map_err(|_| "lock poisoned".to_string())?;
-    if guard.is_some() {
-        return Ok(());
-    }
-    let proc = KernelProcess::start().map_err(|e| e.to_string())?;
-    *guard = Some(proc);
+async fn kernel_start(
+    state: State<'_, KernelState>,
+    auth_state: State<'_, AuthState>,
+    session_token: String,
+) -> Result<(), CommandError> {
+    require_session(&auth_state, &session_token)?;
+
+    let state = state.0.clone();
+    tauri::async_runtime::spawn_blocking(move || state.start())
+        .await
+        .map_err(|e| CommandError::join("kernel_start", e))??;
     Ok(())
 }
 
-/// Send a request to the Python kernel
+/// Stop the kernel gracefully: close its stdin, wait for it to exit, then
+/// escalate to SIGTERM and SIGKILL. The next kernel request starts a new one.
+/// A kernel attached to over a socket is shared, so it is only disconnected.
 ///
-/// # Security
-/// - Requires valid session token
-/// - Session info is injected into params for audit logging
-/// - Credentials never reach the kernel
+/// Returns `false` if no kernel was running.
 #[tauri::command]
-async fn kernel_request(
+async fn kernel_stop(
     state: State<'_, KernelState>,
     auth_state: State<'_, AuthState>,
     session_token: String,
-    method: String,
+) -> Result<bool, CommandError> {
+    require_session(&auth_state, &session_token)?;
+
+    let state = state.0.clone();
+    tauri::async_runtime::spawn_blocking(move || state.stop(SHUTDOWN_GRACE))
+        .await
+        .map_err(|e| CommandError::join("kernel_stop", e))
+}
+
+/// Protocol version, kernel version and capabilities negotiated with the
+/// running kernel, or `None` if no kernel is running.
+#[tauri::command]
+fn kernel_info(state: State<'_, KernelState>) -> Option<KernelInfo> {
+    state.0.current().map(|proc| proc.info().clone())
+}
+
+/// Check that `session_token` names a live session.
+fn require_session(
+    auth_state: &AuthState,
+    session_token: &str,
+) -> Result<SessionInfo, CommandError> {
+    let store = auth_state.0.lock()?;
+    auth::validate_session(&store, session_token).ok_or_else(CommandError::invalid_session)
+}
+
+/// No params at all is the same as an empty object.
+fn normalize_params(params: Value) -> Value {
+    match params {
+        Value::Null => json!({}),
+        other => other,
+    }
+}
+
+/// Check `method` against the policy table and `params` against its schema
+/// for an already validated session, refresh the session's activity, and
+/// return `params` with `__session` injected for kernel-side audit logging,
+/// plus any confirmation the policy requires.
+///
+/// Shared by every command that forwards a method call to the kernel.
+fn authorize_kernel_call(
+    auth_state: &AuthState,
+    policy: &PolicyState,
+    session_info: &SessionInfo,
+    session_token: &str,
+    method: &str,
     params: Value,
-) -> Result<Value, String> {
-    // Validate session first (zero trust)
-    let session_info = {
-        let store = auth_state.0.lock().map_err(|_| "lock poisoned")?;
-        auth::validate_session(&store, &session_token)
-            .ok_or_else(|| "Invalid or expired session".to_string())?
-    };
+) -> Result<(Value, Option<Confirmation>), CommandError> {
+    // Unknown methods never reach the kernel.
+    let confirmation = policy.methods.check(method, session_info, &params)?;
+
+    // Malformed params are rejected here rather than serialized to the kernel.
+    policy.schemas.validate(method, &params)?;
 
     // Refresh session activity
     {
-        let mut store = auth_state.0.lock().map_err(|_| "lock poisoned")?;
-        if let Some(session) = store.get_mut(&session_token) {
+        let mut store = auth_state.0.lock()?;
+        if let Some(session) = store.get_mut(session_token) {
             session.refresh();
         }
     }
 
     // Inject session info into params for kernel-side audit logging
-    let mut enriched_params = match params {
-        Value::Object(map) => Value::Object(map),
-        Value::Null => json!({}),
-        other => json!({ "value": other }),
+    let mut enriched_params = params;
+    if let Value::Object(ref mut map) = enriched_params {
+        map.insert("__session".to_string(), session_param(session_info));
+    }
+
+    Ok((enriched_params, confirmation))
+}
+
+/// The `__session` value the kernel expects on every non-auth call.
+fn session_param(session_info: &SessionInfo) -> Value {
+    json!({
+        "username": session_info.username,
+        "session_id": session_info.session_id,
+    })
+}
+
+/// Ask the user, in a native dialog the webview cannot script, to approve a
+/// call the policy table flags. Blocks, so call it off the main thread.
+fn confirm_call<R: tauri::Runtime>(
+    app: &tauri::AppHandle<R>,
+    confirmation: &Confirmation,
+) -> Result<(), CommandError> {
+    use tauri_plugin_dialog::{DialogExt, MessageDialogButtons, MessageDialogKind};
+
+    let mut text = confirmation.prompt.to_string();
+    if let Some(detail) = &confirmation.detail {
+        text.push_str("\n\n");
+        text.push_str(detail);
+    }
+    let approved = app
+        .dialog()
+        .message(text)
+        .title("Cairn")
+        .kind(MessageDialogKind::Warning)
+        .buttons(MessageDialogButtons::OkCancel)
+        .blocking_show();
+    if approved {
+        Ok(())
+    } else {
+        Err(PolicyError::Declined(confirmation.method.clone()).into())
+    }
+}
+
+/// Send a request to the Python kernel and return its `result`.
+///
+/// A JSON-RPC error from the kernel comes back as `CommandError::Rpc` with
+/// its code, message and data; transport failures as `CommandError::Kernel`.
+///
+/// `request_id` is an optional caller-chosen handle; passing the same value
+/// to `kernel_cancel` abandons the call while it is in flight. Each call is
+/// bounded by the method's timeout (see `kernel::timeout_for`).
+///
+/// # Security
+/// - Requires valid session token
+/// - Method must be allowed by the policy table (see `policy.rs`), which may
+///   also rate limit it or require a native confirmation
+/// - Params must be an object matching the method's schema (see `schema.rs`);
+///   failures come back as `CommandError::InvalidParams` with per-field errors
+/// - Session info is injected into params for audit logging
+/// - Credentials never reach the kernel
+///
+/// Reads listed in `cache.rs` are served from a short per-session cache and
+/// coalesced with identical calls already in flight.
+#[tauri::command]
+#[allow(clippy::too_many_arguments)]
+async fn kernel_request<R: tauri::Runtime>(
+    app: tauri::AppHandle<R>,
+    state: State<'_, KernelState>,
+    auth_state: State<'_, AuthState>,
+    policy: State<'_, PolicyState>,
+    audit: State<'_, AuditState>,
+    cache: State<'_, CacheState>,
+    session_token: String,
+    method: String,
+    params: Value,
+    request_id: Option<String>,
+) -> Result<Value, CommandError> {
+    // Validate session first (zero trust); every call past this is audited.
+    let session_info = require_session(&auth_state, &session_token)?;
+    let params = normalize_params(params);
+    let mut call = audit.0.begin(&session_info, &method, &params);
+
+    let (enriched_params, confirmation) = match authorize_kernel_call(
+        &auth_state,
+        &policy,
+        &session_info,
+        &session_token,
+        &method,
+        params,
+    ) {
+        Ok(authorized) => authorized,
+        Err(e) => return call.finish(Err(e)),
     };
 
+    // Forward to kernel on background thread, through the read cache.
+    let state = state.0.clone();
+    let cache = cache.0.clone();
+    let session_id = session_info.session_id;
+    tauri::async_runtime::spawn_blocking(move || {
+        let result = (|| {
+            if let Some(confirmation) = &confirmation {
+                confirm_call(&app, confirmation)?;
+            }
+            cache.call(&session_id, &method, enriched_params, |params| {
+                let proc = state.get_or_start()?;
+                call.epoch = Some(proc.epoch());
+                let opts = RequestOptions {
+                    cancel_key: request_id,
+                    ..Default::default()
+                };
+                rpc_result(proc.request_with(&method, params, opts)?)
+            })
+        })();
+        call.finish(result)
+    })
+    .await
+    .map_err(|e| CommandError::join("kernel_request", e))?
+}
+
+/// Send a request whose response the kernel may stream back in chunks.
+///
+/// Params carry `__stream: true` alongside `__session` so the kernel knows
+/// the caller accepts partial results. Chunks, then the final result or a
+/// `CommandError`, are delivered on `on_event` (see `StreamEvent`);
+/// the command itself resolves once the stream has ended. Kernels that do
+/// not stream simply produce a single `result` event.
+#[tauri::command]
+#[allow(clippy::too_many_arguments)]
+async fn kernel_request_stream(
+    app: tauri::AppHandle,
+    state: State<'_, KernelState>,
+    auth_state: State<'_, AuthState>,
+    policy: State<'_, PolicyState>,
+    audit: State<'_, AuditState>,
+    cache: State<'_, CacheState>,
+    session_token: String,
+    method: String,
+    params: Value,
+    request_id: Option<String>,
+    on_event: Channel<StreamEvent>,
+) -> Result<(), CommandError> {
+    let session_info = require_session(&auth_state, &session_token)?;
+    let params = normalize_params(params);
+    let mut call = audit.0.begin(&session_info, &method, &params);
+
+    let (mut enriched_params, confirmation) = match authorize_kernel_call(
+        &auth_state,
+        &policy,
+        &session_info,
+        &session_token,
+        &method,
+        params,
+    ) {
+        Ok(authorized) => authorized,
+        Err(e) => return call.finish(Err(e)),
+    };
     if let Value::Object(ref mut map) = enriched_params {
-        map.insert(
-            "__session".to_string(),
-            json!({
-                "username": session_info.username,
-                "session_id": session_info.session_id,
-            }),
-        );
+        map.insert("__stream".to_string(), Value::Bool(true));
+    }
+
+    // Streamed calls are never cached, but may make cached reads stale.
+    let stale = stale_after(&method, &enriched_params);
+    let state = state.0.clone();
+    let cache = cache.0.clone();
+    tauri::async_runtime::spawn_blocking(move || {
+        let outcome = (|| {
+            if let Some(confirmation) = &confirmation {
+                confirm_call(&app, confirmation)?;
+            }
+            let proc = state.get_or_start()?;
+            call.epoch = Some(proc.epoch());
+            let opts = RequestOptions {
+                cancel_key: request_id,
+                ..Default::default()
+            };
+            let envelope = proc.request_stream(&method, enriched_params, opts, |chunk| {
+                let _ = on_event.send(StreamEvent::Partial(chunk));
+            })?;
+            rpc_result(envelope)
+        })();
+        cache.invalidate(&stale);
+        let outcome = call.finish(outcome);
+        let last = match outcome {
+            Ok(result) => StreamEvent::Result(result),
+            Err(e) => StreamEvent::Error(e),
+        };
+        on_event
+            .send(last)
+            .map_err(|e| CommandError::Internal(format!("stream channel closed: {e}")))
+    })
+    .await
+    .map_err(|e| CommandError::join("kernel_request_stream", e))?
+}
+
+/// Most calls a single `kernel_request_batch` may carry.
+const MAX_BATCH_CALLS: usize = 64;
+
+/// One call in a `kernel_request_batch`.
+#[derive(Debug, Deserialize)]
+struct BatchCall {
+    method: String,
+    #[serde(default)]
+    params: Value,
+}
+
+/// A batch item on its way through `kernel_request_batch`.
+struct BatchItem {
+    method: String,
+    call: PendingCall,
+    /// Authorized params and required confirmation; `Err` if refused.
+    authorized: Result<(Value, Option<Confirmation>), CommandError>,
+}
+
+/// Send several requests in one go and return their results in input order.
+///
+/// The session is validated once; each call is then checked against the
+/// policy table and its schema, audited and cached exactly as it would be
+/// through `kernel_request`, and fails on its own without affecting the
+/// rest. Calls that still need the kernel are sent as one JSON-RPC batch,
+/// or pipelined if the kernel does not support batches.
+///
+/// `request_id` is an optional caller-chosen handle; passing the same value
+/// to `kernel_cancel` abandons every call of the batch still in flight.
+#[tauri::command]
+#[allow(clippy::too_many_arguments)]
+async fn kernel_request_batch(
+    app: tauri::AppHandle,
+    state: State<'_, KernelState>,
+    auth_state: State<'_, AuthState>,
+    policy: State<'_, PolicyState>,
+    audit: State<'_, AuditState>,
+    cache: State<'_, CacheState>,
+    session_token: String,
+    calls: Vec<BatchCall>,
+    request_id: Option<String>,
+) -> Result<Vec<BatchOutcome>, CommandError> {
+    let session_info = require_session(&auth_state, &session_token)?;
+    if calls.is_empty() || calls.len() > MAX_BATCH_CALLS {
+        return Err(CommandError::InvalidArgument(format!(
+            "a batch must hold between 1 and {MAX_BATCH_CALLS} calls"
+        )));
     }
 
-    // Forward to kernel on background thread
+    let items: Vec<BatchItem> = calls
+        .into_iter()
+        .map(|BatchCall { method, params }| {
+            let params = normalize_params(params);
+            let call = audit.0.begin(&session_info, &method, &params);
+            let authorized = authorize_kernel_call(
+                &auth_state,
+                &policy,
+                &session_info,
+                &session_token,
+                &method,
+                params,
+            );
+            BatchItem {
+                method,
+                call,
+                authorized,
+            }
+        })
+        .collect();
+
     let state = state.0.clone();
+    let cache = cache.0.clone();
+    let session_id = session_info.session_id;
+    tauri::async_runtime::spawn_blocking(move || {
+        let mut items = items;
+        let mut results: Vec<Option<Result<Value, CommandError>>> = Vec::new();
+        // (index, method, params) of each call that still needs the kernel.
+        let mut sent: Vec<(usize, String, Value)> = Vec::new();
+        for (index, item) in items.iter().enumerate() {
+            let result = match &item.authorized {
+                Err(e) => Some(Err(e.clone())),
+                Ok((params, confirmation)) => {
+                    match confirmation
+                        .as_ref()
+                        .map_or(Ok(()), |c| confirm_call(&app, c))
+                    {
+                        Err(e) => Some(Err(e)),
+                        Ok(()) => {
+                            let hit = cache.get(&session_id, &item.method, params);
+                            if hit.is_none() {
+                                sent.push((index, item.method.clone(), params.clone()));
+                            }
+                            hit.map(Ok)
+                        }
+                    }
+                }
+            };
+            results.push(result);
+        }
+
+        if !sent.is_empty() {
+            let generation = cache.generation();
+            let calls = sent
+                .iter()
+                .map(|(_, m, p)| (m.clone(), p.clone()))
+                .collect();
+            let replies: Vec<Result<Value, CommandError>> = match state.get_or_start() {
+                Ok(proc) => {
+                    for (index, ..) in &sent {
+                        items[*index].call.epoch = Some(proc.epoch());
+                    }
+                    let opts = RequestOptions {
+                        cancel_key: request_id,
+                        ..Default::default()
+                    };
+                    proc.request_batch(calls, opts)
+                        .into_iter()
+                        .map(|reply| rpc_result(reply?))
+                        .collect()
+                }
+                Err(e) => {
+                    let e = CommandError::from(e);
+                    sent.iter().map(|_| Err(e.clone())).collect()
+                }
+            };
+            // In input order, so a read after a write in the same batch is
+            // not cached.
+            for ((index, method, params), result) in sent.into_iter().zip(replies) {
+                if let Ok(value) = &result {
+                    cache.insert(&session_id, &method, &params, value.clone(), generation);
+                }
+                cache.invalidate_for(&method, &params);
+                results[index] = Some(result);
+            }
+        }
+
+        items
+            .into_iter()
+            .zip(results)
+            .map(|(item, result)| {
+                let result = result.unwrap_or_else(|| {
+                    Err(CommandError::Internal(
+                        "batch item was not sent".to_string(),
+                    ))
+                });
+                BatchOutcome::from(item.call.finish(result))
+            })
+            .collect()
+    })
+    .await
+    .map_err(|e| CommandError::join("kernel_request_batch", e))
+}
+
+/// A filter for `pick_upload_file`'s dialog, e.g. `Documents` with
+/// `["pdf", "txt"]`.
+#[derive(Debug, Deserialize)]
+struct FileFilter {
+    name: String,
+    extensions: Vec<String>,
+}
+
+/// Let the user pick a file to upload, in a native dialog the webview cannot
+/// script, and return its path for `kernel_upload`. `None` if the dialog was
+/// cancelled.
+///
+/// `kernel_upload` only accepts paths picked here, each once, so a
+/// compromised webview cannot send arbitrary files to the kernel.
+#[tauri::command]
+async fn pick_upload_file<R: tauri::Runtime>(
+    app: tauri::AppHandle<R>,
+    auth_state: State<'_, AuthState>,
+    session_token: String,
+    filters: Vec<FileFilter>,
+) -> Result<Option<String>, CommandError> {
+    use tauri_plugin_dialog::DialogExt;
+
+    require_session(&auth_state, &session_token)?;
     tauri::async_runtime::spawn_blocking(move || {
-        let mut guard = state.lock().map_err(|_| "lock poisoned".to_string())?;
-        if guard.is_none() {
-            let proc = KernelProcess::start().map_err(|e| e.to_string())?;
-            *guard = Some(proc);
+        let mut dialog = app.dialog().file().set_title("Cairn");
+        for filter in &filters {
+            let extensions: Vec<&str> = filter.extensions.iter().map(String::as_str).collect();
+            dialog = dialog.add_filter(&filter.name, &extensions);
         }
+        let Some(picked) = dialog.blocking_pick_file() else {
+            return Ok(None);
+        };
+        let path = picked
+            .into_path()
+            .map_err(|e| CommandError::InvalidArgument(format!("not a local file: {e}")))?;
+        let granted = app
+            .state::<UploadState>()
+            .0
+            .grant(&path)
+            .map_err(|e| CommandError::InvalidArgument(format!("cannot read {path:?}: {e}")))?;
+        Ok(Some(granted.to_string_lossy().into_owned()))
+    })
+    .await
+    .map_err(|e| CommandError::join("pick_upload_file", e))?
+}
 
-        let proc = guard
-            .as_mut()
-            .ok_or_else(|| KernelError::NotStarted.to_string())?;
-        proc.request(&method, enriched_params)
-            .map_err(|e| e.to_string())
+/// Upload the file at `path` into the kernel and call `method` with the
+/// resulting `blob_id` in its params.
+///
+/// `path` must come from `pick_upload_file`, and `method` must be one of
+/// `blob::UPLOAD_METHODS`. The bytes are streamed as binary frames rather
+/// than passed through JSON, and `on_progress` receives an `UploadProgress`
+/// after every chunk. Otherwise the call is checked, audited and forwarded
+/// like `kernel_request`. The file is read by the shell; the kernel never
+/// sees `path`.
+#[tauri::command]
+#[allow(clippy::too_many_arguments)]
+async fn kernel_upload<R: tauri::Runtime>(
+    app: tauri::AppHandle<R>,
+    state: State<'_, KernelState>,
+    auth_state: State<'_, AuthState>,
+    policy: State<'_, PolicyState>,
+    audit: State<'_, AuditState>,
+    cache: State<'_, CacheState>,
+    uploads: State<'_, UploadState>,
+    session_token: String,
+    path: String,
+    method: String,
+    params: Value,
+    on_progress: Channel<UploadProgress>,
+) -> Result<Value, CommandError> {
+    let session_info = require_session(&auth_state, &session_token)?;
+    let mut params = normalize_params(params);
+    let mut audited = params.clone();
+    if let Value::Object(ref mut map) = audited {
+        map.insert("file_path".to_string(), Value::String(path.clone()));
+    }
+    let mut call = audit.0.begin(&session_info, &method, &audited);
+
+    if !UPLOAD_METHODS.contains(&method.as_str()) {
+        let e = CommandError::InvalidArgument(format!("{method} does not take an upload"));
+        return call.finish(Err(e));
+    }
+    let Some(path) = uploads.0.take(&path) else {
+        let e =
+            CommandError::InvalidArgument(format!("{path} was not picked with pick_upload_file"));
+        return call.finish(Err(e));
+    };
+    // Stands in for the id the upload will produce, so the params are
+    // checked against the method's schema before any bytes are sent.
+    if let Value::Object(ref mut map) = params {
+        map.remove("file_path");
+        map.insert("blob_id".to_string(), Value::String("pending".to_string()));
+    }
+    let (mut enriched_params, confirmation) = match authorize_kernel_call(
+        &auth_state,
+        &policy,
+        &session_info,
+        &session_token,
+        &method,
+        params,
+    ) {
+        Ok(authorized) => authorized,
+        Err(e) => return call.finish(Err(e)),
+    };
+
+    let stale = stale_after(&method, &enriched_params);
+    let state = state.0.clone();
+    let cache = cache.0.clone();
+    let session = session_param(&session_info);
+    tauri::async_runtime::spawn_blocking(move || {
+        let result = (|| {
+            if let Some(confirmation) = &confirmation {
+                confirm_call(&app, confirmation)?;
+            }
+            let proc = state.get_or_start()?;
+            call.epoch = Some(proc.epoch());
+            let blob_id = blob::upload(&proc, &path, &session, |progress| {
+                let _ = on_progress.send(progress);
+            })?;
+            enriched_params["blob_id"] = Value::String(blob_id);
+            rpc_result(proc.request(&method, enriched_params)?)
+        })();
+        cache.invalidate(&stale);
+        call.finish(result)
     })
     .await
-    .map_err(|e| format!("kernel_request join error: {e}"))?
+    .map_err(|e| CommandError::join("kernel_upload", e))?
+}
+
+/// A `cairn-blob://` URL the webview can load an attachment, a document's
+/// original file or an uploaded blob from, e.g. as an `<img>` source.
+///
+/// The URL is valid for `blob::TICKET_TTL` while the session lasts; the
+/// bytes are fetched from the kernel each time it is loaded.
+#[tauri::command]
+fn blob_url(
+    auth_state: State<'_, AuthState>,
+    blobs: State<'_, BlobState>,
+    session_token: String,
+    kind: BlobKind,
+    id: String,
+) -> Result<String, CommandError> {
+    require_session(&auth_state, &session_token)?;
+    if id.is_empty() {
+        return Err(CommandError::InvalidArgument("id is required".to_string()));
+    }
+    let session = auth_state.0.lock()?.hash(&session_token);
+    Ok(blobs.0.issue(session, kind, &id))
+}
+
+/// Answer a `cairn-blob://` request from the webview (see `blob.rs`).
+/// Blocks on the kernel, so call it off the main thread.
+fn serve_blob(
+    app: &tauri::AppHandle,
+    request: &tauri::http::Request<Vec<u8>>,
+) -> tauri::http::Response<Vec<u8>> {
+    use tauri::http::{header, Response, StatusCode};
+
+    let text = |status: StatusCode, message: String| {
+        Response::builder()
+            .status(status)
+            .header(header::CONTENT_TYPE, "text/plain; charset=utf-8")
+            .body(message.into_bytes())
+            .unwrap_or_default()
+    };
+
+    let ticket = app.state::<BlobState>().0.resolve(request.uri().path());
+    let Some(ticket) = ticket else {
+        return text(
+            StatusCode::NOT_FOUND,
+            "unknown or expired blob URL".to_string(),
+        );
+    };
+    let session_info = match app.state::<AuthState>().0.lock() {
+        Ok(store) => auth::validate_hashed_session(&store, &ticket.session),
+        Err(_) => None,
+    };
+    let Some(session_info) = session_info else {
+        return text(
+            StatusCode::FORBIDDEN,
+            CommandError::invalid_session().to_string(),
+        );
+    };
+
+    let params = json!({ "kind": ticket.kind, "id": ticket.id });
+    let mut call = app
+        .state::<AuditState>()
+        .0
+        .begin(&session_info, "blob/fetch", &params);
+    let kernel = app.state::<KernelState>().0.clone();
+    let fetched = (|| {
+        let proc = kernel.get_or_start()?;
+        call.epoch = Some(proc.epoch());
+        blob::fetch(
+            &proc,
+            &session_param(&session_info),
+            ticket.kind,
+            &ticket.id,
+        )
+    })();
+    // Loaded from the app's own origin, which differs from this one; no
+    // other origin may read the bytes.
+    let dev_origin = cfg!(debug_assertions)
+        .then(|| app.config().build.dev_url.as_ref())
+        .flatten()
+        .map(|url| url.origin().ascii_serialization());
+    let origin = request
+        .headers()
+        .get(header::ORIGIN)
+        .and_then(|value| value.to_str().ok())
+        .filter(|origin| blob::is_app_origin(origin, dev_origin.as_deref()));
+    match call.finish(fetched) {
+        Ok(fetched) => {
+            let mut response = Response::builder()
+                .header(header::CONTENT_DISPOSITION, fetched.content_disposition())
+                .header(header::CONTENT_TYPE, fetched.mime)
+                .header(header::CACHE_CONTROL, "no-store")
+                .header(header::VARY, "Origin");
+            if let Some(origin) = origin {
+                response = response.header(header::ACCESS_CONTROL_ALLOW_ORIGIN, origin);
+            }
+            response.body(fetched.data).unwrap_or_default()
+        }
+        Err(CommandError::Rpc { message, .. }) => text(StatusCode::NOT_FOUND, message),
+        Err(e) => text(StatusCode::BAD_GATEWAY, e.to_string()),
+    }
+}
+
+/// Recent kernel stderr lines for the diagnostics panel.
+///
+/// Filters: `level` keeps lines at or above that level, `since_ms` keeps
+/// lines captured at or after that Unix time in milliseconds, and `limit`
+/// keeps only the newest N matches. Lines are returned oldest first.
+#[tauri::command]
+fn kernel_logs(
+    state: State<'_, KernelState>,
+    auth_state: State<'_, AuthState>,
+    session_token: String,
+    level: Option<LogLevel>,
+    since_ms: Option<u64>,
+    limit: Option<usize>,
+) -> Result<Vec<LogLine>, CommandError> {
+    require_session(&auth_state, &session_token)?;
+    Ok(state.0.log().query(level, since_ms, limit))
+}
+
+/// Request counts, error counts, and latency and queue-wait percentiles since
+/// the app started, per method policy pattern, for the latency dashboard.
+#[tauri::command]
+fn kernel_metrics(
+    state: State<'_, KernelState>,
+    auth_state: State<'_, AuthState>,
+    session_token: String,
+) -> Result<MetricsSnapshot, CommandError> {
+    require_session(&auth_state, &session_token)?;
+    Ok(state.0.metrics().snapshot())
+}
+
+/// Cancel an in-flight `kernel_request` or `kernel_request_batch` by the
+/// `request_id` it was sent with.
+///
+/// The pending call fails with a "cancelled" error and the kernel is told to
+/// drop it. Returns `false` if nothing with that id is in flight.
+#[tauri::command]
+fn kernel_cancel(
+    state: State<'_, KernelState>,
+    auth_state: State<'_, AuthState>,
+    session_token: String,
+    request_id: String,
+) -> Result<bool, CommandError> {
+    require_session(&auth_state, &session_token)?;
+
+    Ok(state.0.current().is_some_and(|p| p.cancel(&request_id)))
+}
+
+/// Subscribe the calling window to kernel notification topics.
+///
+/// Topics are method names (`cairn/chat_status`), namespace wildcards
+/// (`cc/session/*`) or `*`. Matching notifications arrive as
+/// `cairn://kernel/<method>` events on this window only.
+#[tauri::command]
+fn kernel_subscribe(
+    window: tauri::WebviewWindow,
+    state: State<'_, KernelState>,
+    auth_state: State<'_, AuthState>,
+    session_token: String,
+    topics: Vec<String>,
+) -> Result<(), CommandError> {
+    require_session(&auth_state, &session_token)?;
+    if topics.iter().any(|t| t.trim().is_empty()) {
+        return Err(CommandError::InvalidArgument(
+            "Subscription topics must not be empty".to_string(),
+        ));
+    }
+    state.0.subscribe(window.label(), &topics);
+    Ok(())
+}
+
+/// Remove notification topics for the calling window (all of them if
+/// `topics` is empty).
+#[tauri::command]
+fn kernel_unsubscribe(
+    window: tauri::WebviewWindow,
+    state: State<'_, KernelState>,
+    auth_state: State<'_, AuthState>,
+    session_token: String,
+    topics: Vec<String>,
+) -> Result<(), CommandError> {
+    require_session(&auth_state, &session_token)?;
+    state.0.unsubscribe(window.label(), &topics);
+    Ok(())
+}
+
+// =============================================================================
+// Audit Commands
+// =============================================================================
+
+/// Check the shell's audit log: every entry links to the one before it and
+/// nothing this process wrote is missing from the end (see `audit.rs`).
+#[tauri::command]
+async fn audit_verify(
+    auth_state: State<'_, AuthState>,
+    audit: State<'_, AuditState>,
+    session_token: String,
+) -> Result<Verification, CommandError> {
+    require_session(&auth_state, &session_token)?;
+
+    let audit = audit.0.clone();
+    tauri::async_runtime::spawn_blocking(move || audit.verify())
+        .await
+        .map_err(|e| CommandError::join("audit_verify", e))?
+        .map_err(|e| CommandError::Internal(format!("audit log: {e}")))
+}
+
+/// The audit log as JSON lines, for archiving or verifying elsewhere.
+#[tauri::command]
+async fn audit_export(
+    auth_state: State<'_, AuthState>,
+    audit: State<'_, AuditState>,
+    session_token: String,
+) -> Result<String, CommandError> {
+    require_session(&auth_state, &session_token)?;
+
+    let audit = audit.0.clone();
+    tauri::async_runtime::spawn_blocking(move || audit.export())
+        .await
+        .map_err(|e| CommandError::join("audit_export", e))?
+        .map_err(|e| CommandError::Internal(format!("audit log: {e}")))
 }
 
 // =============================================================================
@@ -224,25 +1246,24 @@ async fn kernel_request(
 /// Create a dev session for testing without authentication.
 /// Only available in debug builds.
 #[tauri::command]
-fn dev_create_session(
+async fn dev_create_session(
     state: State<'_, KernelState>,
     auth_state: State<'_, AuthState>,
-) -> Result<AuthResult, String> {
+) -> Result<AuthResult, CommandError> {
     #[cfg(not(debug_assertions))]
     {
-        return Err("Dev session only available in debug builds".to_string());
+        return Err(CommandError::Auth(
+            "Dev session only available in debug builds".to_string(),
+        ));
     }
 
     #[cfg(debug_assertions)]
     {
         // Start the kernel if not already running
-        {
-            let mut guard = state.0.lock().map_err(|_| "lock poisoned".to_string())?;
-            if guard.is_none() {
-                let proc = KernelProcess::start().map_err(|e| e.to_string())?;
-                *guard = Some(proc);
-            }
-        }
+        let kernel = state.0.clone();
+        tauri::async_runtime::spawn_blocking(move || kernel.get_or_start())
+            .await
+            .map_err(|e| CommandError::join("dev_create_session", e))??;
 
         // Get system username
         let username = std::env::var("USER")
@@ -253,15 +1274,16 @@ fn dev_create_session(
         let token = auth::generate_session_token();
 
         // Store the session
-        let session = auth::create_session(token.clone(), username.clone());
-        let mut store = auth_state.0.lock().map_err(|_| "lock poisoned")?;
-        store.insert(session);
+        let session = auth::create_session(username.clone());
+        let mut store = auth_state.0.lock()?;
+        store.insert(&token, session);
 
         Ok(AuthResult {
             success: true,
-            session_token: Some(token),
+            session_token: Some(zeroize::Zeroizing::new(token)),
             username: Some(username),
             error: None,
+            session_ref: None,
         })
     }
 }
@@ -282,21 +1304,17 @@ async fn pty_start(
     session_token: String,
     cols: u16,
     rows: u16,
-) -> Result<(), String> {
+) -> Result<(), CommandError> {
     // Zero-trust: validate session before touching PTY.
-    {
-        let store = auth_state.0.lock().map_err(|_| "lock poisoned")?;
-        auth::validate_session(&store, &session_token)
-            .ok_or_else(|| "Invalid or expired session".to_string())?;
-    }
+    require_session(&auth_state, &session_token)?;
 
-    let process = tauri::async_runtime::spawn_blocking(move || {
-        pty::PtyProcess::start(app, cols, rows)
-    })
-    .await
-    .map_err(|e| format!("pty_start join error: {e}"))??;
+    let process =
+        tauri::async_runtime::spawn_blocking(move || pty::PtyProcess::start(app, cols, rows))
+            .await
+            .map_err(|e| CommandError::join("pty_start", e))?
+            .map_err(CommandError::Pty)?;
 
-    let mut guard = pty_wrapper.0 .0.lock().map_err(|_| "lock poisoned")?;
+    let mut guard = pty_wrapper.0 .0.lock()?;
     *guard = Some(process);
     Ok(())
 }
@@ -308,16 +1326,12 @@ fn pty_write(
     auth_state: State<'_, AuthState>,
     session_token: String,
     data: String,
-) -> Result<(), String> {
-    {
-        let store = auth_state.0.lock().map_err(|_| "lock poisoned")?;
-        auth::validate_session(&store, &session_token)
-            .ok_or_else(|| "Invalid or expired session".to_string())?;
-    }
-    let mut guard = pty_wrapper.0 .0.lock().map_err(|_| "lock poisoned")?;
+) -> Result<(), CommandError> {
+    require_session(&auth_state, &session_token)?;
+    let mut guard = pty_wrapper.0 .0.lock()?;
     match guard.as_mut() {
-        Some(proc) => proc.write_data(&data),
-        None => Err("No PTY session running".to_string()),
+        Some(proc) => proc.write_data(&data).map_err(CommandError::Pty),
+        None => Err(CommandError::Pty("No PTY session running".to_string())),
     }
 }
 
@@ -329,16 +1343,12 @@ fn pty_resize(
     session_token: String,
     cols: u16,
     rows: u16,
-) -> Result<(), String> {
-    {
-        let store = auth_state.0.lock().map_err(|_| "lock poisoned")?;
-        auth::validate_session(&store, &session_token)
-            .ok_or_else(|| "Invalid or expired session".to_string())?;
-    }
-    let guard = pty_wrapper.0 .0.lock().map_err(|_| "lock poisoned")?;
+) -> Result<(), CommandError> {
+    require_session(&auth_state, &session_token)?;
+    let guard = pty_wrapper.0 .0.lock()?;
     match guard.as_ref() {
-        Some(proc) => proc.resize(cols, rows),
-        None => Err("No PTY session running".to_string()),
+        Some(proc) => proc.resize(cols, rows).map_err(CommandError::Pty),
+        None => Err(CommandError::Pty("No PTY session running".to_string())),
     }
 }
 
@@ -348,13 +1358,9 @@ fn pty_stop(
     pty_wrapper: State<'_, PtyStateWrapper>,
     auth_state: State<'_, AuthState>,
     session_token: String,
-) -> Result<(), String> {
-    {
-        let store = auth_state.0.lock().map_err(|_| "lock poisoned")?;
-        auth::validate_session(&store, &session_token)
-            .ok_or_else(|| "Invalid or expired session".to_string())?;
-    }
-    let mut guard = pty_wrapper.0 .0.lock().map_err(|_| "lock poisoned")?;
+) -> Result<(), CommandError> {
+    require_session(&auth_state, &session_token)?;
+    let mut guard = pty_wrapper.0 .0.lock()?;
     *guard = None; // Drop impl cleans up child process and reader thread.
     Ok(())
 }
@@ -366,8 +1372,16 @@ fn pty_stop(
 fn main() {
     tauri::Builder::default()
         .plugin(tauri_plugin_dialog::init())
-        .manage(KernelState(Arc::new(Mutex::new(None))))
+        .manage(KernelState(KernelSupervisor::new(RestartPolicy::default())))
         .manage(AuthState::new())
+        .manage(PolicyState {
+            methods: Policy::new(),
+            schemas: SchemaRegistry::load().expect("embedded kernel method schemas are valid"),
+        })
+        .manage(AuditState(Arc::new(AuditLog::new())))
+        .manage(CacheState(Arc::new(ResponseCache::new())))
+        .manage(BlobState(Arc::new(BlobTickets::new())))
+        .manage(UploadState(UploadGrants::new()))
         .manage(PtyStateWrapper(pty::PtyState::new()))
         .invoke_handler(tauri::generate_handler![
             // Auth commands
@@ -380,14 +1394,159 @@ fn main() {
             dev_create_session,
             // Kernel commands
             kernel_start,
+            kernel_stop,
+            kernel_info,
             kernel_request,
+            kernel_request_stream,
+            kernel_request_batch,
+            kernel_upload,
+            pick_upload_file,
+            kernel_cancel,
+            kernel_subscribe,
+            kernel_unsubscribe,
+            kernel_logs,
+            kernel_metrics,
+            // Blob commands
+            blob_url,
+            // Audit commands
+            audit_verify,
+            audit_export,
             // PTY commands (ReOS terminal)
             pty_start,
             pty_write,
             pty_resize,
             pty_stop,
         ])
+        .register_asynchronous_uri_scheme_protocol(BLOB_SCHEME, |ctx, request, responder| {
+            let app = ctx.app_handle().clone();
+            tauri::async_runtime::spawn_blocking(move || {
+                responder.respond(serve_blob(&app, &request));
+            });
+        })
+        .on_window_event(|window, event| {
+            if let tauri::WindowEvent::Destroyed = event {
+                window
+                    .state::<KernelState>()
+                    .0
+                    .unsubscribe(window.label(), &[]);
+            }
+        })
         .setup(|app| {
+            // Forward kernel lifecycle changes (for the status banner) and
+            // kernel notifications to the webviews.
+            let kernel = app.state::<KernelState>();
+            kernel.0.set_event_sink(AppEventSink(app.handle().clone()));
+
+            // Persist kernel stderr under the app log dir as well as in memory.
+            match app.path().app_log_dir() {
+                Ok(dir) => {
+                    if let Err(e) = kernel.0.log().set_file_dir(&dir) {
+                        eprintln!(
+                            "[kernel-log] cannot open log file in {}: {e}",
+                            dir.display()
+                        );
+                    }
+                }
+                Err(e) => eprintln!("[kernel-log] no app log dir: {e}"),
+            }
+
+            // Expire idle sessions promptly rather than on their next use.
+            let handle = app.handle().clone();
+            let spawned = std::thread::Builder::new()
+                .name("session-reaper".to_string())
+                .spawn(move || loop {
+                    std::thread::sleep(SESSION_REAP_INTERVAL);
+                    reap_expired_sessions(&handle);
+                });
+            if let Err(e) = spawned {
+                eprintln!("[auth] cannot start session reaper: {e}");
+            }
+
+            // Lock sessions when the screen locks or the machine suspends.
+            #[cfg(target_os = "linux")]
+            {
+                let handle = app.handle().clone();
+                tauri::async_runtime::spawn(async move {
+                    let mut signals = match screen_lock::subscribe_desktop().await {
+                        Ok(signals) => signals,
+                        Err(e) => {
+                            eprintln!("[screen-lock] cannot watch for screen lock: {e}");
+                            return;
+                        }
+                    };
+                    while let Some(cause) = signals.next().await {
+                        let handle = handle.clone();
+                        let _ = tauri::async_runtime::spawn_blocking(move || {
+                            lock_sessions(&handle, cause)
+                        })
+                        .await;
+                    }
+                });
+            }
+
+            // Optionally keep a Prometheus dump of the request metrics on disk,
+            // e.g. for node_exporter's textfile collector.
+            if let Some(path) = std::env::var_os(METRICS_FILE_ENV).map(PathBuf::from) {
+                let supervisor = kernel.0.clone();
+                let spawned = std::thread::Builder::new()
+                    .name("kernel-metrics".to_string())
+                    .spawn(move || loop {
+                        if let Err(e) = supervisor.metrics().write_prometheus(&path) {
+                            eprintln!("[metrics] cannot write {}: {e}", path.display());
+                        }
+                        std::thread::sleep(METRICS_FILE_INTERVAL);
+                    });
+                if let Err(e) = spawned {
+                    eprintln!("[metrics] cannot start metrics writer: {e}");
+                }
+            }
+
+            // Development aids: run against a recorded session instead of
+            // Python, and/or record this one (see `cassette.rs`).
+            if let Some(path) = std::env::var_os(REPLAY_ENV).map(PathBuf::from) {
+                match FakeKernel::load(&path) {
+                    Ok(fake) => kernel.0.set_transport(Arc::new(fake)),
+                    Err(e) => eprintln!("[cassette] cannot replay {}: {e}", path.display()),
+                }
+            }
+            if let Some(path) = std::env::var_os(RECORD_ENV).map(PathBuf::from) {
+                match Recorder::create(&path) {
+                    Ok(recorder) => kernel.0.set_recorder(recorder),
+                    Err(e) => eprintln!("[cassette] cannot record to {}: {e}", path.display()),
+                }
+            }
+
+            // The shell's own record of kernel calls.
+            match app.path().app_data_dir() {
+                Ok(dir) => {
+                    let dir = dir.join("audit");
+                    if let Err(e) = app.state::<AuditState>().0.set_dir(&dir) {
+                        eprintln!("[audit] cannot open audit log in {}: {e}", dir.display());
+                    }
+                }
+                Err(e) => eprintln!("[audit] no app data dir: {e}"),
+            }
+
+            // Resolve the kernel command from `kernel.json`, env overrides
+            // and any runtime bundled with the app.
+            let layout = BundleLayout::detect(app.path().resource_dir().ok());
+            let config_dir = app.path().app_config_dir().ok();
+            kernel
+                .0
+                .set_launch(KernelLaunch::load(config_dir.as_deref(), &layout));
+
+            // Session lifetimes from `session.json`.
+            if let Some(dir) = &config_dir {
+                match SessionPolicy::load(dir) {
+                    Ok(policy) => {
+                        if let Ok(mut store) = app.state::<AuthState>().0.lock() {
+                            store.set_policy(policy);
+                        }
+                    }
+                    Err(e) => eprintln!("[auth] {e}; using the default session policy"),
+                }
+            }
+
             // Set the window icon from the bundled PNG
             let icon_bytes = include_bytes!("../icons/icon.png");
             match tauri::image::Image::from_bytes(icon_bytes) {
@@ -409,6 +1568,415 @@ fn main() {
             }
             Ok(())
         })
-        .run(tauri::generate_context!())
-        .expect("error while running tauri application");
+        .build(tauri::generate_context!())
+        .expect("error while building tauri application")
+        .run(|app, event| {
+            // Let the kernel finish in-flight work (e.g. a SQLite write) and
+            // exit cleanly instead of being orphaned when the app closes.
+            if let tauri::RunEvent::ExitRequested { .. } = event {
+                app.state::<KernelState>().0.stop(SHUTDOWN_GRACE);
+            }
+        });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use cassette::{exchange, handshake, login_exchange, Exchange};
+    use tauri::ipc::{CallbackFn, InvokeBody};
+    use tauri::test::{get_ipc_response, mock_builder, mock_context, noop_assets, INVOKE_KEY};
+    use tauri::webview::InvokeRequest;
+
+    /// A shell wired to a replayed kernel session, and its main webview.
+    fn replay_app(exchanges: Vec<Exchange>) -> tauri::WebviewWindow<tauri::test::MockRuntime> {
+        let supervisor = KernelSupervisor::new(RestartPolicy::default());
+        supervisor.set_transport(Arc::new(FakeKernel::new(exchanges)));
+        let app = mock_builder()
+            .manage(KernelState(supervisor))
+            .manage(AuthState::new())
+            .manage(PolicyState {
+                methods: Policy::new(),
+                schemas: SchemaRegistry::load().unwrap(),
+            })
+            .manage(AuditState(Arc::new(AuditLog::new())))
+            .manage(CacheState(Arc::new(ResponseCache::new())))
+            .manage(UploadState(UploadGrants::new()))
+            .invoke_handler(tauri::generate_handler![
+                auth_login,
+                auth_logout,
+                auth_validate,
+                auth_refresh,
+                kernel_request,
+                kernel_upload
+            ])
+            .build(mock_context(noop_assets()))
+            .unwrap();
+        tauri::WebviewWindowBuilder::new(&app, "main", Default::default())
+            .build()
+            .unwrap()
+    }
+
+    fn invoke(
+        webview: &tauri::WebviewWindow<tauri::test::MockRuntime>,
+        cmd: &str,
+        args: Value,
+    ) -> Result<Value, Value> {
+        get_ipc_response(
+            webview,
+            InvokeRequest {
+                cmd: cmd.to_string(),
+                callback: CallbackFn(0),
+                error: CallbackFn(1),
+                url: "http://tauri.localhost".parse().unwrap(),
+                body: InvokeBody::Json(args),
+                headers: Default::default(),
+                invoke_key: INVOKE_KEY.to_string(),
+            },
+        )
+        .map(|body| body.deserialize::<Value>().unwrap())
+    }
+
+    #[test]
+    fn test_login_then_request_against_replayed_kernel() {
+        let acts = json!({ "acts": [{ "act_id": "act-1", "title": "Garden" }] });
+        let webview = replay_app(vec![
+            handshake(),
+            login_exchange("kellogg"),
+            exchange("play/acts/list", json!({}), acts.clone()),
+        ]);
+
+        let unknown = invoke(
+            &webview,
+            "kernel_request",
+            json!({ "sessionToken": "tok-1", "method": "play/acts/list", "params": {} }),
+        )
+        .unwrap_err();
+        assert_eq!(unknown["kind"], "auth");
+
+        let login = invoke(&webview, "auth_login", json!({ "username": "kellogg" })).unwrap();
+        assert_eq!(login["success"], true);
+        // The kernel's name for the session stays in the shell.
+        assert!(login.get("session_ref").is_none());
+        // Cassettes never hold real tokens; the shell keeps whatever it is given.
+        let token = login["session_token"].as_str().unwrap().to_string();
+
+        let listed = invoke(
+            &webview,
+            "kernel_request",
+            json!({ "sessionToken": token, "method": "play/acts/list", "params": {} }),
+        )
+        .unwrap();
+        assert_eq!(listed, acts);
+
+        let missing = invoke(
+            &webview,
+            "kernel_request",
+            json!({ "sessionToken": token, "method": "play/scenes/list", "params": { "act_id": "act-1" } }),
+        )
+        .unwrap_err();
+        assert_eq!(missing["kind"], "rpc");
+        assert_eq!(missing["code"], cassette::NOT_RECORDED);
+
+        let denied = invoke(
+            &webview,
+            "kernel_request",
+            json!({ "sessionToken": token, "method": "auth/login", "params": {} }),
+        )
+        .unwrap_err();
+        assert_eq!(denied["kind"], "policy");
+    }
+
+    #[test]
+    fn test_upload_only_accepts_picked_files() {
+        let webview = replay_app(vec![handshake(), login_exchange("kellogg")]);
+        let login = invoke(&webview, "auth_login", json!({ "username": "kellogg" })).unwrap();
+        let token = login["session_token"].as_str().unwrap().to_string();
+        let dir = std::env::temp_dir().join(format!("cairn-upload-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let file = dir.join("notes.txt");
+        std::fs::write(&file, b"notes").unwrap();
+        let upload = |path: &std::path::Path| {
+            let args = json!({
+                "sessionToken": token,
+                "path": path,
+                "method": "play/attachments/add",
+                "params": {},
+                "onProgress": "__CHANNEL__:1",
+            });
+            invoke(&webview, "kernel_upload", args).unwrap_err()
+        };
+
+        let refused = upload(&file);
+        assert_eq!(refused["kind"], "invalid_argument");
+        assert!(refused["message"]
+            .as_str()
+            .unwrap()
+            .contains("pick_upload_file"));
+
+        // Once picked, the path gets past the check, as far as the kernel,
+        // which cannot take binary uploads in this test.
+        let uploads = webview.app_handle().state::<UploadState>();
+        uploads.0.grant(&file).unwrap();
+        assert_eq!(upload(&file)["kind"], "kernel");
+        assert_eq!(upload(&file)["kind"], "invalid_argument");
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_reaper_logs_out_expired_sessions() {
+        use tauri::Listener;
+
+        let webview = replay_app(vec![
+            handshake(),
+            exchange(
+                "auth/logout",
+                json!({ "session_ref": "[redacted]" }),
+                json!({ "success": true }),
+            ),
+        ]);
+        let app = webview.app_handle();
+        let cassette =
+            std::env::temp_dir().join(format!("cairn-reaper-{}.jsonl", std::process::id()));
+        let kernel = app.state::<KernelState>().0.clone();
+        kernel.set_recorder(Recorder::create(&cassette).unwrap());
+        let epoch = kernel.start().unwrap().epoch();
+
+        let sessions = app.state::<AuthState>().0.clone();
+        let mut stale = auth::create_session("kellogg".to_string());
+        stale.last_activity -= Duration::from_secs(20 * 60);
+        stale.kernel = Some(KernelSession {
+            epoch,
+            session_ref: "ref-stale".to_string(),
+        });
+        let stale_id = {
+            let mut store = sessions.lock().unwrap();
+            store.insert("stale-token", stale);
+            store.insert("fresh-token", auth::create_session("kellogg".to_string()));
+            store.hash("stale-token").session_id()
+        };
+        let events = Arc::new(std::sync::Mutex::new(Vec::new()));
+        let seen = events.clone();
+        app.listen_any(SESSION_EXPIRED_EVENT, move |event| {
+            seen.lock().unwrap().push(event.payload().to_string());
+        });
+
+        reap_expired_sessions(app);
+        reap_expired_sessions(app);
+        kernel.stop(Duration::from_secs(1));
+
+        let events = events.lock().unwrap();
+        assert_eq!(events.len(), 1);
+        let payload: Value = serde_json::from_str(&events[0]).unwrap();
+        assert_eq!(
+            payload,
+            json!({ "username": "kellogg", "session_id": stale_id, "reason": "idle" })
+        );
+        assert!(sessions.lock().unwrap().get("fresh-token").is_some());
+
+        let recorded = cassette::load(&cassette).unwrap();
+        let _ = std::fs::remove_file(&cassette);
+        let logouts: Vec<_> = recorded
+            .iter()
+            .filter(|e| e.method == "auth/logout")
+            .collect();
+        assert_eq!(logouts.len(), 1);
+        assert_eq!(logouts[0].response["result"]["success"], true);
+    }
+
+    #[test]
+    fn test_reaper_gives_up_on_sessions_the_kernel_cannot_vouch_for() {
+        use tauri::Listener;
+
+        // auth/validate answers; auth/refresh was never recorded, so every
+        // refresh comes back as an error.
+        let webview = replay_app(vec![
+            handshake(),
+            exchange(
+                "auth/validate",
+                json!({ "session_ref": "[redacted]" }),
+                json!({ "valid": true, "username": "kellogg" }),
+            ),
+        ]);
+        let app = webview.app_handle();
+        let cassette =
+            std::env::temp_dir().join(format!("cairn-probe-{}.jsonl", std::process::id()));
+        let kernel = app.state::<KernelState>().0.clone();
+        kernel.set_recorder(Recorder::create(&cassette).unwrap());
+        let epoch = kernel.start().unwrap().epoch();
+
+        let sessions = app.state::<AuthState>().0.clone();
+        let kernel_session = |session_ref: &str| {
+            let mut session = auth::create_session("kellogg".to_string());
+            session.kernel = Some(KernelSession {
+                epoch,
+                session_ref: session_ref.to_string(),
+            });
+            session
+        };
+        let mut idle = kernel_session("ref-idle");
+        idle.last_activity -= Duration::from_secs(120);
+        let active_id = {
+            let mut store = sessions.lock().unwrap();
+            store.insert("idle-token", idle);
+            store.insert("active-token", kernel_session("ref-active"));
+            store.hash("active-token").session_id()
+        };
+        let events = Arc::new(std::sync::Mutex::new(Vec::new()));
+        let seen = events.clone();
+        app.listen_any(SESSION_EXPIRED_EVENT, move |event| {
+            seen.lock().unwrap().push(event.payload().to_string());
+        });
+
+        for _ in 1..KERNEL_PROBE_FAILURE_LIMIT {
+            reap_expired_sessions(app);
+        }
+        assert!(sessions.lock().unwrap().get("active-token").is_some());
+        reap_expired_sessions(app);
+        kernel.stop(Duration::from_secs(1));
+
+        let events = events.lock().unwrap();
+        assert_eq!(events.len(), 1);
+        let payload: Value = serde_json::from_str(&events[0]).unwrap();
+        assert_eq!(
+            payload,
+            json!({ "username": "kellogg", "session_id": active_id, "reason": "kernel" })
+        );
+        assert!(sessions.lock().unwrap().get("idle-token").is_some());
+
+        // The idle session was only validated, never refreshed.
+        let recorded = cassette::load(&cassette).unwrap();
+        let _ = std::fs::remove_file(&cassette);
+        let validations = recorded
+            .iter()
+            .filter(|e| e.method == "auth/validate")
+            .count();
+        assert_eq!(validations, KERNEL_PROBE_FAILURE_LIMIT as usize);
+    }
+
+    #[test]
+    fn test_session_lifecycle_reaches_the_kernel() {
+        let webview = replay_app(vec![
+            handshake(),
+            login_exchange("kellogg"),
+            exchange(
+                "auth/refresh",
+                json!({ "session_ref": "[redacted]" }),
+                json!({ "success": true }),
+            ),
+            exchange(
+                "auth/validate",
+                json!({ "session_ref": "[redacted]" }),
+                json!({ "valid": true, "username": "kellogg" }),
+            ),
+            exchange(
+                "auth/logout",
+                json!({ "session_ref": "[redacted]" }),
+                json!({ "success": true }),
+            ),
+            // Once logged out the kernel no longer knows the session.
+            exchange(
+                "auth/validate",
+                json!({ "session_ref": "[redacted]" }),
+                json!({ "valid": false }),
+            ),
+        ]);
+        let cassette =
+            std::env::temp_dir().join(format!("cairn-lifecycle-{}.jsonl", std::process::id()));
+        let kernel = webview.app_handle().state::<KernelState>().0.clone();
+        kernel.set_recorder(Recorder::create(&cassette).unwrap());
+
+        let login = invoke(&webview, "auth_login", json!({ "username": "kellogg" })).unwrap();
+        let token = login["session_token"].as_str().unwrap().to_string();
+        let session = json!({ "sessionToken": token });
+        invoke(&webview, "auth_refresh", session.clone()).unwrap();
+        assert_eq!(
+            invoke(&webview, "auth_validate", session.clone()).unwrap(),
+            true
+        );
+        invoke(&webview, "auth_logout", session.clone()).unwrap();
+        assert_eq!(
+            invoke(&webview, "auth_validate", session.clone()).unwrap(),
+            false
+        );
+        kernel.stop(Duration::from_secs(1));
+
+        let recorded = cassette::load(&cassette).unwrap();
+        let _ = std::fs::remove_file(&cassette);
+        let methods: Vec<&str> = recorded.iter().map(|e| e.method.as_str()).collect();
+        assert_eq!(
+            methods,
+            [
+                "initialize",
+                "auth/login",
+                "auth/refresh",
+                "auth/validate",
+                "auth/logout"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_sessions_end_when_the_kernel_stops() {
+        let webview = replay_app(vec![handshake(), login_exchange("kellogg")]);
+        let app = webview.app_handle();
+        let login = invoke(&webview, "auth_login", json!({ "username": "kellogg" })).unwrap();
+        let token = login["session_token"].as_str().unwrap().to_string();
+
+        let kernel = app.state::<KernelState>().0.clone();
+        kernel.stop(Duration::from_secs(1));
+        // Not running: the shell cannot vouch for the key, so the session is over.
+        let valid = invoke(&webview, "auth_validate", json!({ "sessionToken": token }));
+        assert_eq!(valid.unwrap(), false);
+        assert!(app
+            .state::<AuthState>()
+            .0
+            .lock()
+            .unwrap()
+            .get(&token)
+            .is_none());
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_screen_lock_ends_every_session() {
+        use tauri::Listener;
+
+        let webview = replay_app(vec![
+            handshake(),
+            login_exchange("kellogg"),
+            exchange(
+                "auth/logout",
+                json!({ "session_ref": "[redacted]" }),
+                json!({ "success": true }),
+            ),
+        ]);
+        let app = webview.app_handle();
+        let cassette =
+            std::env::temp_dir().join(format!("cairn-lock-{}.jsonl", std::process::id()));
+        let kernel = app.state::<KernelState>().0.clone();
+        kernel.set_recorder(Recorder::create(&cassette).unwrap());
+        let login = invoke(&webview, "auth_login", json!({ "username": "kellogg" })).unwrap();
+        let token = login["session_token"].as_str().unwrap().to_string();
+
+        let events = Arc::new(std::sync::Mutex::new(Vec::new()));
+        let seen = events.clone();
+        app.listen_any(screen_lock::LOCKED_EVENT, move |event| {
+            seen.lock().unwrap().push(event.payload().to_string());
+        });
+        lock_sessions(app, screen_lock::LockCause::Sleep);
+        kernel.stop(Duration::from_secs(1));
+
+        assert!(app
+            .state::<AuthState>()
+            .0
+            .lock()
+            .unwrap()
+            .get(&token)
+            .is_none());
+        let payload: Value = serde_json::from_str(&events.lock().unwrap()[0]).unwrap();
+        assert_eq!(payload, json!({ "cause": "sleep", "sessions": 1 }));
+        let recorded = cassette::load(&cassette).unwrap();
+        let _ = std::fs::remove_file(&cassette);
+        assert_eq!(recorded.last().unwrap().method, "auth/logout");
+    }
 }
@@ -0,0 +1,372 @@
+//! Per-method kernel request metrics.
+//!
+//! Every request a `KernelProcess` sends is recorded here: a count, an error
+//! count (transport failure or JSON-RPC error), and two latency histograms.
+//! `latency` runs from the call to its final reply; `queue_wait` from the
+//! call until it acquired the writer to the kernel's stdin, which grows when
+//! other requests hold the writer or the kernel stops draining its input.
+//! Requests are grouped by the
+//! `METHOD_POLICIES` pattern their method matches, not the raw method name,
+//! so the set of series is fixed however many method names the webview makes
+//! up.
+//!
+//! The supervisor owns one `KernelMetrics` for the app's lifetime, so numbers
+//! accumulate across kernel restarts. `kernel_metrics` returns a summary with
+//! p50/p95/p99 estimated from the histogram buckets; `prometheus` renders the
+//! same data in the Prometheus text format.
+
+use serde::Serialize;
+use std::collections::HashMap;
+use std::fmt::Write as _;
+use std::fs;
+use std::path::Path;
+use std::sync::Mutex;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use crate::policy;
+
+/// Histogram bucket upper bounds, in milliseconds. Spans fast local calls up
+/// to the longest per-method timeouts in `kernel.rs`.
+pub const BUCKETS_MS: &[f64] = &[
+    1.0, 2.5, 5.0, 10.0, 25.0, 50.0, 100.0, 250.0, 500.0, 1_000.0, 2_500.0, 5_000.0, 10_000.0,
+    30_000.0, 60_000.0, 120_000.0, 300_000.0,
+];
+
+/// Environment variable naming a file to keep a Prometheus dump in.
+pub const METRICS_FILE_ENV: &str = "CAIRN_METRICS_FILE";
+
+/// How often the Prometheus dump is rewritten.
+pub const METRICS_FILE_INTERVAL: Duration = Duration::from_secs(15);
+
+/// Group for methods no policy entry matches.
+pub const UNLISTED: &str = "(unlisted)";
+
+#[derive(Debug, Clone)]
+struct Histogram {
+    /// One count per bucket in `BUCKETS_MS`, plus the `+Inf` bucket.
+    counts: Vec<u64>,
+    count: u64,
+    sum_ms: f64,
+    max_ms: f64,
+}
+
+impl Default for Histogram {
+    fn default() -> Self {
+        Self {
+            counts: vec![0; BUCKETS_MS.len() + 1],
+            count: 0,
+            sum_ms: 0.0,
+            max_ms: 0.0,
+        }
+    }
+}
+
+impl Histogram {
+    fn observe(&mut self, value: Duration) {
+        let ms = value.as_secs_f64() * 1000.0;
+        let bucket = BUCKETS_MS
+            .iter()
+            .position(|bound| ms <= *bound)
+            .unwrap_or(BUCKETS_MS.len());
+        self.counts[bucket] += 1;
+        self.count += 1;
+        self.sum_ms += ms;
+        self.max_ms = self.max_ms.max(ms);
+    }
+
+    /// Estimate the `q` quantile by interpolating inside the bucket that
+    /// holds it. Never exceeds the largest value observed.
+    fn quantile(&self, q: f64) -> f64 {
+        if self.count == 0 {
+            return 0.0;
+        }
+        let rank = q * self.count as f64;
+        let mut seen = 0u64;
+        for (i, &n) in self.counts.iter().enumerate() {
+            if n == 0 || ((seen + n) as f64) < rank {
+                seen += n;
+                continue;
+            }
+            let lower = if i == 0 { 0.0 } else { BUCKETS_MS[i - 1] };
+            let upper = BUCKETS_MS.get(i).copied().unwrap_or(self.max_ms);
+            let fraction = ((rank - seen as f64) / n as f64).clamp(0.0, 1.0);
+            return (lower + (upper - lower) * fraction).min(self.max_ms);
+        }
+        self.max_ms
+    }
+
+    fn summary(&self) -> LatencySummary {
+        LatencySummary {
+            p50_ms: self.quantile(0.50),
+            p95_ms: self.quantile(0.95),
+            p99_ms: self.quantile(0.99),
+            mean_ms: if self.count == 0 {
+                0.0
+            } else {
+                self.sum_ms / self.count as f64
+            },
+            max_ms: self.max_ms,
+        }
+    }
+}
+
+#[derive(Debug, Default, Clone)]
+struct MethodStats {
+    requests: u64,
+    errors: u64,
+    latency: Histogram,
+    queue_wait: Histogram,
+}
+
+/// Latency figures for one histogram, in milliseconds.
+#[derive(Debug, Clone, Serialize)]
+pub struct LatencySummary {
+    pub p50_ms: f64,
+    pub p95_ms: f64,
+    pub p99_ms: f64,
+    pub mean_ms: f64,
+    pub max_ms: f64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct MethodMetrics {
+    /// The policy pattern the requests matched, or `UNLISTED`.
+    pub method: String,
+    pub requests: u64,
+    pub errors: u64,
+    pub latency: LatencySummary,
+    pub queue_wait: LatencySummary,
+}
+
+/// What `kernel_metrics` returns.
+#[derive(Debug, Clone, Serialize)]
+pub struct MetricsSnapshot {
+    /// Unix time in milliseconds when collection started.
+    pub since_ms: u64,
+    /// Sorted by pattern.
+    pub methods: Vec<MethodMetrics>,
+}
+
+pub struct KernelMetrics {
+    since_ms: u64,
+    methods: Mutex<HashMap<&'static str, MethodStats>>,
+}
+
+impl KernelMetrics {
+    pub fn new() -> Self {
+        Self {
+            since_ms: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|d| d.as_millis() as u64)
+                .unwrap_or(0),
+            methods: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Record one finished request to `method`. `queue_wait` is `None` if
+    /// the request failed before it was written.
+    pub fn record(
+        &self,
+        method: &str,
+        latency: Duration,
+        queue_wait: Option<Duration>,
+        error: bool,
+    ) {
+        let key = policy::lookup(method).map_or(UNLISTED, |p| p.pattern);
+        let Ok(mut methods) = self.methods.lock() else {
+            return;
+        };
+        let stats = methods.entry(key).or_default();
+        stats.requests += 1;
+        if error {
+            stats.errors += 1;
+        }
+        stats.latency.observe(latency);
+        if let Some(wait) = queue_wait {
+            stats.queue_wait.observe(wait);
+        }
+    }
+
+    pub fn snapshot(&self) -> MetricsSnapshot {
+        let methods = self
+            .sorted()
+            .into_iter()
+            .map(|(method, stats)| MethodMetrics {
+                method: method.to_string(),
+                requests: stats.requests,
+                errors: stats.errors,
+                latency: stats.latency.summary(),
+                queue_wait: stats.queue_wait.summary(),
+            })
+            .collect();
+        MetricsSnapshot {
+            since_ms: self.since_ms,
+            methods,
+        }
+    }
+
+    /// All metrics in the Prometheus text exposition format.
+    pub fn prometheus(&self) -> String {
+        let stats = self.sorted();
+        let mut out = String::new();
+        counter(
+            &mut out,
+            "cairn_kernel_requests_total",
+            "Kernel requests sent, by method pattern.",
+            stats.iter().map(|(m, s)| (*m, s.requests)),
+        );
+        counter(
+            &mut out,
+            "cairn_kernel_request_errors_total",
+            "Kernel requests that failed or returned a JSON-RPC error, by method pattern.",
+            stats.iter().map(|(m, s)| (*m, s.errors)),
+        );
+        histogram(
+            &mut out,
+            "cairn_kernel_request_duration_seconds",
+            "Time from sending a kernel request to its final reply.",
+            stats.iter().map(|(m, s)| (*m, &s.latency)),
+        );
+        histogram(
+            &mut out,
+            "cairn_kernel_request_queue_wait_seconds",
+            "Time a kernel request waited for the writer to the kernel.",
+            stats.iter().map(|(m, s)| (*m, &s.queue_wait)),
+        );
+        out
+    }
+
+    /// Replace `path` with the current Prometheus dump. Written to a sibling
+    /// temp file first so a scraper never reads a partial dump.
+    pub fn write_prometheus(&self, path: &Path) -> std::io::Result<()> {
+        let tmp = path.with_extension("prom.tmp");
+        fs::write(&tmp, self.prometheus())?;
+        fs::rename(&tmp, path)
+    }
+
+    fn sorted(&self) -> Vec<(&'static str, MethodStats)> {
+        let mut stats: Vec<_> = self
+            .methods
+            .lock()
+            .map(|m| m.iter().map(|(k, v)| (*k, v.clone())).collect())
+            .unwrap_or_default();
+        stats.sort_by(|a, b| a.0.cmp(b.0));
+        stats
+    }
+}
+
+fn counter<'a>(
+    out: &mut String,
+    name: &str,
+    help: &str,
+    values: impl Iterator<Item = (&'a str, u64)>,
+) {
+    let _ = writeln!(out, "# HELP {name} {help}\n# TYPE {name} counter");
+    for (method, value) in values {
+        let _ = writeln!(out, "{name}{{method=\"{}\"}} {value}", escape(method));
+    }
+}
+
+fn histogram<'a>(
+    out: &mut String,
+    name: &str,
+    help: &str,
+    values: impl Iterator<Item = (&'a str, &'a Histogram)>,
+) {
+    let _ = writeln!(out, "# HELP {name} {help}\n# TYPE {name} histogram");
+    for (method, h) in values {
+        let method = escape(method);
+        let mut cumulative = 0;
+        for (i, n) in h.counts.iter().enumerate() {
+            cumulative += n;
+            let le = BUCKETS_MS
+                .get(i)
+                .map(|ms| (ms / 1000.0).to_string())
+                .unwrap_or_else(|| "+Inf".to_string());
+            let _ = writeln!(
+                out,
+                "{name}_bucket{{method=\"{method}\",le=\"{le}\"}} {cumulative}"
+            );
+        }
+        let _ = writeln!(
+            out,
+            "{name}_sum{{method=\"{method}\"}} {}",
+            h.sum_ms / 1000.0
+        );
+        let _ = writeln!(out, "{name}_count{{method=\"{method}\"}} {}", h.count);
+    }
+}
+
+/// Escape a Prometheus label value.
+fn escape(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_quantiles_from_buckets() {
+        let mut h = Histogram::default();
+        for ms in 1..=100 {
+            h.observe(Duration::from_millis(ms));
+        }
+        let s = h.summary();
+        assert!((25.0..=50.0).contains(&s.p50_ms), "{s:?}");
+        assert!((90.0..=100.0).contains(&s.p95_ms), "{s:?}");
+        assert!(s.p99_ms <= 100.0 && s.p99_ms >= s.p95_ms, "{s:?}");
+        assert_eq!(s.max_ms, 100.0);
+        assert!((s.mean_ms - 50.5).abs() < 1e-9);
+
+        assert_eq!(Histogram::default().quantile(0.5), 0.0);
+    }
+
+    #[test]
+    fn test_record_and_prometheus_dump() {
+        let metrics = KernelMetrics::new();
+        metrics.record(
+            "chat/respond",
+            Duration::from_millis(1200),
+            Some(Duration::from_millis(4)),
+            false,
+        );
+        metrics.record("chat/respond", Duration::from_secs(400), None, true);
+        // Made-up names share one series instead of adding their own.
+        metrics.record("x/made-up-1", Duration::from_millis(3), None, false);
+        metrics.record("x/made-up-2", Duration::from_millis(3), None, true);
+
+        let snapshot = metrics.snapshot();
+        assert_eq!(snapshot.methods.len(), 2);
+        let unlisted = &snapshot.methods[0];
+        assert_eq!(
+            (unlisted.method.as_str(), unlisted.requests, unlisted.errors),
+            (UNLISTED, 2, 1)
+        );
+        let chat = &snapshot.methods[1];
+        assert_eq!(
+            (chat.method.as_str(), chat.requests, chat.errors),
+            ("chat/respond", 2, 1)
+        );
+        assert_eq!(chat.latency.max_ms, 400_000.0);
+
+        let text = metrics.prometheus();
+        assert!(text.contains("cairn_kernel_requests_total{method=\"chat/respond\"} 2"));
+        assert!(text.contains("cairn_kernel_request_errors_total{method=\"(unlisted)\"} 1"));
+        assert!(text.contains(
+            "cairn_kernel_request_duration_seconds_bucket{method=\"chat/respond\",le=\"2.5\"} 1"
+        ));
+        assert!(text.contains(
+            "cairn_kernel_request_duration_seconds_bucket{method=\"chat/respond\",le=\"+Inf\"} 2"
+        ));
+        assert!(text.contains(
+            "cairn_kernel_request_queue_wait_seconds_bucket{method=\"chat/respond\",le=\"0.005\"} 1"
+        ));
+        assert!(text
+            .contains("cairn_kernel_request_queue_wait_seconds_count{method=\"chat/respond\"} 1"));
+        assert!(!text.contains("made-up"));
+    }
+}
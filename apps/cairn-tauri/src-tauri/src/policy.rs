@@ -0,0 +1,567 @@
+//! Which kernel methods the webview may call, and on what terms.
+//!
+//! `kernel_request` used to forward any method string. Every call now goes
+//! through `Policy::check` first: methods missing from `METHOD_POLICIES` are
+//! rejected before they reach Python, so a compromised webview cannot reach
+//! kernel internals that the UI never uses. Per entry the table sets the
+//! session scope required, an optional rate limit, and an optional native
+//! confirmation dialog shown by the shell (not the webview).
+//!
+//! Entries are exact method names; a namespace wildcard (`copper/tasks/*`) is
+//! only used where an id is part of the method name. An exact entry wins over
+//! a wildcard, and a longer wildcard over a shorter one. `tools/call` is
+//! checked once more against `TOOL_POLICIES` by the `name` param, so tools
+//! that change state get their own confirmation.
+
+use serde_json::Value;
+use std::collections::{HashMap, VecDeque};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use thiserror::Error;
+
+use crate::auth::SessionInfo;
+
+/// How long after login a `Scope::RecentLogin` method stays callable.
+pub const RECENT_LOGIN_WINDOW: Duration = Duration::from_secs(15 * 60);
+
+/// What kind of session a method requires.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Scope {
+    /// Any valid session.
+    Session,
+    /// A session whose login happened within `RECENT_LOGIN_WINDOW`.
+    RecentLogin,
+    /// Called by the shell itself (handshake, auth); never from the webview.
+    Shell,
+}
+
+/// At most `max` calls per `per`, across all windows.
+#[derive(Debug, Clone, Copy)]
+pub struct RateLimit {
+    pub max: usize,
+    pub per: Duration,
+}
+
+/// A native confirmation the user must accept before the call is forwarded.
+#[derive(Debug, Clone, Copy)]
+pub struct Confirm {
+    pub prompt: &'static str,
+    /// String param shown under the prompt, e.g. the command about to run.
+    pub detail_param: Option<&'static str>,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct MethodPolicy {
+    pub pattern: &'static str,
+    pub scope: Scope,
+    pub rate: Option<RateLimit>,
+    pub confirm: Option<Confirm>,
+}
+
+const fn allow(pattern: &'static str) -> MethodPolicy {
+    MethodPolicy {
+        pattern,
+        scope: Scope::Session,
+        rate: None,
+        confirm: None,
+    }
+}
+
+const fn shell_only(pattern: &'static str) -> MethodPolicy {
+    MethodPolicy {
+        pattern,
+        scope: Scope::Shell,
+        rate: None,
+        confirm: None,
+    }
+}
+
+const fn per_minute(max: usize) -> Option<RateLimit> {
+    Some(RateLimit {
+        max,
+        per: Duration::from_secs(60),
+    })
+}
+
+const SAFETY_CONFIRM: Option<Confirm> = Some(Confirm {
+    prompt: "Change Cairn's safety limits?",
+    detail_param: None,
+});
+
+const fn safety_setter(pattern: &'static str) -> MethodPolicy {
+    MethodPolicy {
+        pattern,
+        scope: Scope::RecentLogin,
+        rate: per_minute(10),
+        confirm: SAFETY_CONFIRM,
+    }
+}
+
+/// The allowlist: the methods the UI calls, by exact name. Keep it sorted;
+/// add a method here when the UI starts calling it. `copper/tasks/*` is the
+/// one wildcard, because Copper task ids are part of the method name.
+pub const METHOD_POLICIES: &[MethodPolicy] = &[
+    allow("approval/respond"),
+    shell_only("auth/*"),
+    allow("autostart/get"),
+    allow("autostart/set"),
+    shell_only("blob/*"),
+    allow("blocks/create"),
+    allow("blocks/delete"),
+    allow("blocks/move"),
+    allow("blocks/page/tree"),
+    allow("blocks/property/set"),
+    allow("blocks/reorder"),
+    allow("blocks/rich_text/set"),
+    allow("blocks/search"),
+    allow("blocks/unchecked_todos"),
+    allow("blocks/update"),
+    allow("cairn/attention"),
+    allow("cairn/attention/reorder"),
+    allow("cairn/chat_async"),
+    allow("cairn/chat_status"),
+    allow("cairn/email/dismiss"),
+    allow("cairn/email/downvote"),
+    allow("cairn/email/open"),
+    allow("cairn/email/upvote"),
+    allow("cairn/thunderbird/status"),
+    MethodPolicy {
+        rate: per_minute(30),
+        ..allow("chat/respond")
+    },
+    allow("code/diff/apply"),
+    allow("code/diff/reject"),
+    allow("consciousness/persist"),
+    allow("consciousness/poll"),
+    allow("context/stats"),
+    allow("context/toggle_source"),
+    allow("conversation/archive"),
+    allow("copper/modelfiles"),
+    allow("copper/modelfiles/build"),
+    allow("copper/modelfiles/create"),
+    allow("copper/modelfiles/delete"),
+    allow("copper/modelfiles/extract"),
+    allow("copper/models"),
+    allow("copper/nodes"),
+    allow("copper/nodes/add"),
+    allow("copper/nodes/disable"),
+    allow("copper/nodes/enable"),
+    allow("copper/nodes/priority"),
+    allow("copper/nodes/remove"),
+    allow("copper/pull"),
+    allow("copper/status"),
+    allow("copper/tasks/*"),
+    allow("documents/insert"),
+    allow("health/findings"),
+    allow("health/status"),
+    shell_only("initialize"),
+    allow("lifecycle/memories/approve"),
+    allow("lifecycle/memories/by_act_page"),
+    allow("lifecycle/memories/ensure_page"),
+    allow("lifecycle/memories/reject"),
+    allow("ollama/check_installed"),
+    allow("ollama/model_info"),
+    allow("ollama/pull_start"),
+    allow("ollama/pull_status"),
+    allow("ollama/set_context"),
+    allow("ollama/set_gpu"),
+    allow("ollama/set_model"),
+    allow("ollama/set_url"),
+    allow("ollama/status"),
+    allow("ollama/test_connection"),
+    allow("personas/list"),
+    allow("personas/upsert"),
+    allow("play/acts/assign_repo"),
+    allow("play/acts/create"),
+    allow("play/acts/delete"),
+    allow("play/acts/list"),
+    allow("play/acts/set_active"),
+    allow("play/acts/update"),
+    allow("play/attachments/add"),
+    allow("play/attachments/list"),
+    allow("play/attachments/remove"),
+    allow("play/kb/list"),
+    allow("play/kb/read"),
+    allow("play/kb/write_apply"),
+    allow("play/kb/write_preview"),
+    allow("play/me/read"),
+    allow("play/me/write"),
+    allow("play/pages/content/write"),
+    allow("play/pages/create"),
+    allow("play/pages/tree"),
+    allow("play/scenes/create"),
+    allow("play/scenes/delete"),
+    allow("play/scenes/list"),
+    allow("play/scenes/list_all"),
+    allow("play/scenes/update"),
+    allow("providers/list"),
+    allow("providers/set"),
+    allow("reasoning/feedback"),
+    allow("reos/converse"),
+    allow("reos/converse/abort"),
+    MethodPolicy {
+        rate: per_minute(10),
+        confirm: Some(Confirm {
+            prompt: "Run this command on your system?",
+            detail_param: Some("command"),
+        }),
+        ..allow("reos/execute")
+    },
+    allow("reos/propose"),
+    allow("reos/telemetry/event"),
+    allow("reos/vitals"),
+    allow("riva/contract/list"),
+    allow("riva/devops/ci/repos"),
+    allow("riva/devops/ci/status"),
+    allow("riva/pm/dashboard"),
+    allow("riva/pm/epics/get"),
+    allow("riva/pm/epics/list"),
+    allow("riva/pm/epics/update"),
+    allow("riva/pm/issues/get"),
+    allow("riva/pm/issues/list"),
+    allow("riva/pm/issues/update"),
+    allow("riva/pm/research/list"),
+    allow("riva/pm/roadmap/list"),
+    allow("riva/projects/scan"),
+    allow("riva/status"),
+    shell_only("rpc/*"),
+    safety_setter("safety/set_command_length"),
+    safety_setter("safety/set_max_iterations"),
+    safety_setter("safety/set_rate_limit"),
+    safety_setter("safety/set_sudo_limit"),
+    safety_setter("safety/set_wall_clock_timeout"),
+    allow("safety/settings"),
+    allow("system/live_state"),
+    allow("thunderbird/check"),
+    allow("thunderbird/configure"),
+    allow("thunderbird/decline"),
+    MethodPolicy {
+        confirm: Some(Confirm {
+            prompt: "Reset the Thunderbird integration?",
+            detail_param: None,
+        }),
+        ..allow("thunderbird/reset")
+    },
+    MethodPolicy {
+        rate: per_minute(60),
+        ..allow("tools/call")
+    },
+];
+
+/// One tool reachable through `tools/call`.
+#[derive(Debug, Clone, Copy)]
+pub struct ToolPolicy {
+    pub name: &'static str,
+    /// Asked before tools that change state; `detail_param` names a key in
+    /// the call's `arguments`.
+    pub confirm: Option<Confirm>,
+}
+
+const fn read_tool(name: &'static str) -> ToolPolicy {
+    ToolPolicy {
+        name,
+        confirm: None,
+    }
+}
+
+const fn write_tool(
+    name: &'static str,
+    prompt: &'static str,
+    detail_param: Option<&'static str>,
+) -> ToolPolicy {
+    ToolPolicy {
+        name,
+        confirm: Some(Confirm {
+            prompt,
+            detail_param,
+        }),
+    }
+}
+
+/// The tools `tools/call` may name. Any other name is rejected.
+pub const TOOL_POLICIES: &[ToolPolicy] = &[
+    write_tool("cairn_create_act", "Create this Act?", Some("title")),
+    write_tool("cairn_create_scene", "Create this Scene?", Some("title")),
+    write_tool(
+        "cairn_delete_act",
+        "Delete this Act and everything in it?",
+        Some("act_name"),
+    ),
+    write_tool(
+        "cairn_delete_scene",
+        "Delete this Scene?",
+        Some("scene_name"),
+    ),
+    read_tool("cairn_get_calendar"),
+    read_tool("cairn_get_todos"),
+    read_tool("cairn_get_upcoming_events"),
+    read_tool("cairn_health_history"),
+    read_tool("cairn_health_report"),
+    read_tool("cairn_list_acts"),
+    read_tool("cairn_list_items"),
+    read_tool("cairn_list_scenes"),
+    read_tool("cairn_search_contacts"),
+    write_tool(
+        "cairn_set_active_act",
+        "Switch the active Act?",
+        Some("act_name"),
+    ),
+    read_tool("cairn_surface_next"),
+    read_tool("cairn_surface_today"),
+    read_tool("cairn_thunderbird_status"),
+    write_tool("cairn_undo_last", "Undo the last action?", None),
+    write_tool("cairn_update_act", "Rename this Act?", Some("new_title")),
+    write_tool(
+        "cairn_update_scene",
+        "Rename this Scene?",
+        Some("new_title"),
+    ),
+];
+
+pub fn lookup_tool(name: &str) -> Option<&'static ToolPolicy> {
+    TOOL_POLICIES.iter().find(|t| t.name == name)
+}
+
+/// Whether `method` matches an exact name or a namespace wildcard
+/// (`play/*`, which does not match `play/` itself).
+pub fn pattern_matches(pattern: &str, method: &str) -> bool {
+    match pattern.strip_suffix('*') {
+        Some(prefix) => method.starts_with(prefix) && method.len() > prefix.len(),
+        None => pattern == method,
+    }
+}
+
+/// Most specific entry for `method`, if it is allowed at all.
+pub fn lookup(method: &str) -> Option<&'static MethodPolicy> {
+    METHOD_POLICIES
+        .iter()
+        .filter(|p| pattern_matches(p.pattern, method))
+        .max_by_key(|p| (!p.pattern.ends_with('*'), p.pattern.len()))
+}
+
+#[derive(Debug, Clone, Error)]
+pub enum PolicyError {
+    #[error("method {0} is not allowed")]
+    UnknownMethod(String),
+    #[error("tool {0} is not allowed")]
+    UnknownTool(String),
+    #[error("method {0} is reserved for the shell")]
+    ShellOnly(String),
+    #[error("method {0} requires a recent login; sign in again")]
+    ReauthRequired(String),
+    #[error("method {method} is rate limited ({max} calls per {per:?})")]
+    RateLimited {
+        method: String,
+        max: usize,
+        per: Duration,
+    },
+    #[error("{0} was not confirmed")]
+    Declined(String),
+}
+
+impl PolicyError {
+    /// Stable snake_case name for the frontend to branch on.
+    pub fn reason(&self) -> &'static str {
+        match self {
+            Self::UnknownMethod(_) => "unknown_method",
+            Self::UnknownTool(_) => "unknown_tool",
+            Self::ShellOnly(_) => "shell_only",
+            Self::ReauthRequired(_) => "reauth_required",
+            Self::RateLimited { .. } => "rate_limited",
+            Self::Declined(_) => "declined",
+        }
+    }
+}
+
+/// The confirmation to show for one call, with its detail filled in.
+#[derive(Debug, Clone)]
+pub struct Confirmation {
+    pub method: String,
+    pub prompt: &'static str,
+    pub detail: Option<String>,
+}
+
+/// Policy enforcement state: the static table plus rate-limit history.
+pub struct Policy {
+    /// Call times per policy entry, oldest first.
+    calls: Mutex<HashMap<&'static str, VecDeque<Instant>>>,
+}
+
+impl Policy {
+    pub fn new() -> Self {
+        Self {
+            calls: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Admit one webview call to `method`, or say why not.
+    ///
+    /// A call that is admitted counts against the entry's rate limit even if
+    /// the user then declines its confirmation. Returns the confirmation to
+    /// show, if the entry has one.
+    pub fn check(
+        &self,
+        method: &str,
+        session: &SessionInfo,
+        params: &Value,
+    ) -> Result<Option<Confirmation>, PolicyError> {
+        let policy =
+            lookup(method).ok_or_else(|| PolicyError::UnknownMethod(method.to_string()))?;
+
+        match policy.scope {
+            Scope::Session => {}
+            Scope::RecentLogin if session.age <= RECENT_LOGIN_WINDOW => {}
+            Scope::RecentLogin => return Err(PolicyError::ReauthRequired(method.to_string())),
+            Scope::Shell => return Err(PolicyError::ShellOnly(method.to_string())),
+        }
+
+        let mut confirm = policy.confirm;
+        let mut detail_params = params;
+        if method == "tools/call" {
+            let name = params.get("name").and_then(Value::as_str).unwrap_or("");
+            let tool =
+                lookup_tool(name).ok_or_else(|| PolicyError::UnknownTool(name.to_string()))?;
+            confirm = tool.confirm;
+            detail_params = params.get("arguments").unwrap_or(&Value::Null);
+        }
+
+        if let Some(rate) = policy.rate {
+            let mut calls = self.calls.lock().unwrap_or_else(|e| e.into_inner());
+            let history = calls.entry(policy.pattern).or_default();
+            let now = Instant::now();
+            while history
+                .front()
+                .is_some_and(|t| now.duration_since(*t) >= rate.per)
+            {
+                history.pop_front();
+            }
+            if history.len() >= rate.max {
+                return Err(PolicyError::RateLimited {
+                    method: method.to_string(),
+                    max: rate.max,
+                    per: rate.per,
+                });
+            }
+            history.push_back(now);
+        }
+
+        Ok(confirm.map(|confirm| Confirmation {
+            method: method.to_string(),
+            prompt: confirm.prompt,
+            detail: confirm
+                .detail_param
+                .and_then(|key| detail_params.get(key))
+                .and_then(Value::as_str)
+                .map(str::to_string),
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn session(age: Duration) -> SessionInfo {
+        SessionInfo {
+            username: "alice".to_string(),
+            session_id: "0123456789abcdef".to_string(),
+            age,
+        }
+    }
+
+    #[test]
+    fn test_lookup_prefers_most_specific_entry() {
+        assert_eq!(lookup("reos/execute").unwrap().pattern, "reos/execute");
+        assert_eq!(lookup("reos/vitals").unwrap().pattern, "reos/vitals");
+        assert_eq!(
+            lookup("copper/tasks/t-42").unwrap().pattern,
+            "copper/tasks/*"
+        );
+        assert!(lookup("copper/tasks/").is_none());
+        // Namespaces are no longer allowed wholesale.
+        assert!(lookup("play/pages/delete").is_none());
+        assert!(lookup("cc/session/send").is_none());
+        assert!(lookup("reos/telemetry/query").is_none());
+        assert!(lookup("shell/exec").is_none());
+        assert!(lookup("safety/set_unknown").is_none());
+    }
+
+    #[test]
+    fn test_unknown_and_shell_methods_rejected() {
+        let policy = Policy::new();
+        let s = session(Duration::ZERO);
+        let err = policy.check("os/system", &s, &json!({})).unwrap_err();
+        assert_eq!(err.reason(), "unknown_method");
+        let err = policy.check("auth/login", &s, &json!({})).unwrap_err();
+        assert_eq!(err.reason(), "shell_only");
+        assert!(policy
+            .check("play/acts/list", &s, &json!({}))
+            .unwrap()
+            .is_none());
+    }
+
+    #[test]
+    fn test_recent_login_scope() {
+        let policy = Policy::new();
+        let old = session(RECENT_LOGIN_WINDOW + Duration::from_secs(1));
+        let err = policy
+            .check("safety/set_sudo_limit", &old, &json!({}))
+            .unwrap_err();
+        assert_eq!(err.reason(), "reauth_required");
+
+        let fresh = session(Duration::from_secs(5));
+        let confirm = policy
+            .check("safety/set_sudo_limit", &fresh, &json!({}))
+            .unwrap();
+        assert!(confirm.is_some());
+    }
+
+    #[test]
+    fn test_rate_limit_and_confirmation_detail() {
+        let policy = Policy::new();
+        let s = session(Duration::ZERO);
+        let params = json!({ "command": "ls -la" });
+        for _ in 0..10 {
+            let confirm = policy.check("reos/execute", &s, &params).unwrap().unwrap();
+            assert_eq!(confirm.detail.as_deref(), Some("ls -la"));
+        }
+        let err = policy.check("reos/execute", &s, &params).unwrap_err();
+        assert_eq!(err.reason(), "rate_limited");
+
+        // Other reos/* methods are not throttled by reos/execute's budget.
+        assert!(policy.check("reos/vitals", &s, &json!({})).is_ok());
+    }
+
+    #[test]
+    fn test_tools_call_checked_per_tool() {
+        let policy = Policy::new();
+        let s = session(Duration::ZERO);
+
+        let read = json!({ "name": "cairn_list_acts", "arguments": {} });
+        assert!(policy.check("tools/call", &s, &read).unwrap().is_none());
+
+        let delete = json!({
+            "name": "cairn_delete_act",
+            "arguments": { "act_name": "Career" },
+        });
+        let confirm = policy.check("tools/call", &s, &delete).unwrap().unwrap();
+        assert_eq!(confirm.method, "tools/call");
+        assert_eq!(confirm.detail.as_deref(), Some("Career"));
+
+        for params in [json!({ "name": "run_shell" }), json!({})] {
+            let err = policy.check("tools/call", &s, &params).unwrap_err();
+            assert_eq!(err.reason(), "unknown_tool");
+        }
+    }
+
+    #[test]
+    fn test_tables_are_sorted_and_unique() {
+        for pair in TOOL_POLICIES.windows(2) {
+            assert!(pair[0].name < pair[1].name, "{}", pair[1].name);
+        }
+        for pair in METHOD_POLICIES.windows(2) {
+            assert!(pair[0].pattern < pair[1].pattern, "{}", pair[1].pattern);
+        }
+    }
+}
@@ -0,0 +1,188 @@
+//! JSON Schema validation of kernel request params.
+//!
+//! `schemas/kernel_methods.json` maps kernel methods the webview may call to
+//! a schema for their `params`, mirroring the checks the Python handlers make. The shell
+//! validates params against it before a request is serialized, so a malformed
+//! call never reaches the kernel and the webview gets field-level errors
+//! instead of the kernel's first `-32602` message.
+//!
+//! Methods without an entry only have to receive an object. `$defs` at the
+//! top of the file are shared by every method schema.
+
+use serde::Serialize;
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use thiserror::Error;
+
+/// The registry, embedded at build time.
+const REGISTRY: &str = include_str!("../schemas/kernel_methods.json");
+
+/// One failed check, located by JSON pointer into `params`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct FieldError {
+    /// JSON pointer to the offending value, e.g. `/title`. A missing
+    /// required property points at where it should be.
+    pub path: String,
+    pub message: String,
+}
+
+#[derive(Debug, Clone, Error)]
+#[error("invalid params for {method}: {}", summary(.errors))]
+pub struct ParamsError {
+    pub method: String,
+    pub errors: Vec<FieldError>,
+}
+
+fn summary(errors: &[FieldError]) -> String {
+    errors
+        .iter()
+        .map(|e| match e.path.as_str() {
+            "" => e.message.clone(),
+            path => format!("{path}: {}", e.message),
+        })
+        .collect::<Vec<_>>()
+        .join("; ")
+}
+
+/// Compiled validators for every method in the registry.
+pub struct SchemaRegistry {
+    methods: HashMap<String, jsonschema::Validator>,
+    /// Applied to methods without an entry.
+    fallback: jsonschema::Validator,
+}
+
+impl SchemaRegistry {
+    /// Compile the embedded registry.
+    pub fn load() -> Result<Self, String> {
+        Self::from_json(REGISTRY)
+    }
+
+    fn from_json(source: &str) -> Result<Self, String> {
+        let registry: Value =
+            serde_json::from_str(source).map_err(|e| format!("schema registry: {e}"))?;
+        let defs = registry.get("$defs").cloned().unwrap_or_else(|| json!({}));
+        let entries = registry
+            .get("methods")
+            .and_then(Value::as_object)
+            .ok_or("schema registry: missing \"methods\" object")?;
+
+        let mut methods = HashMap::with_capacity(entries.len());
+        for (method, schema) in entries {
+            let mut schema = schema.clone();
+            if let Value::Object(map) = &mut schema {
+                map.insert("$defs".to_string(), defs.clone());
+            }
+            let validator = compile(&schema).map_err(|e| format!("schema for {method}: {e}"))?;
+            methods.insert(method.clone(), validator);
+        }
+
+        let fallback = compile(&json!({ "type": "object" }))?;
+        Ok(Self { methods, fallback })
+    }
+
+    /// Check `params` for `method`, reporting every failure at once.
+    pub fn validate(&self, method: &str, params: &Value) -> Result<(), ParamsError> {
+        let validator = self.methods.get(method).unwrap_or(&self.fallback);
+        let errors: Vec<FieldError> = validator.iter_errors(params).map(field_error).collect();
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(ParamsError {
+                method: method.to_string(),
+                errors,
+            })
+        }
+    }
+}
+
+fn compile(schema: &Value) -> Result<jsonschema::Validator, String> {
+    jsonschema::options()
+        .with_draft(jsonschema::Draft::Draft202012)
+        .build(schema)
+        .map_err(|e| e.to_string())
+}
+
+fn field_error(err: jsonschema::ValidationError<'_>) -> FieldError {
+    let mut path = err.instance_path.as_str().to_string();
+    if let jsonschema::error::ValidationErrorKind::Required { property } = &err.kind {
+        if let Some(name) = property.as_str() {
+            path.push('/');
+            path.push_str(&name.replace('~', "~0").replace('/', "~1"));
+        }
+    }
+    FieldError {
+        path,
+        message: err.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::policy;
+
+    #[test]
+    fn test_registry_compiles_and_covers_allowed_methods() {
+        let registry = SchemaRegistry::load().unwrap();
+        assert!(registry.methods.len() > 50);
+        for method in registry.methods.keys() {
+            assert!(
+                policy::lookup(method).is_some(),
+                "{method} has a schema but is not allowed by the policy table"
+            );
+        }
+    }
+
+    #[test]
+    fn test_every_allowed_method_has_a_schema() {
+        let registry = SchemaRegistry::load().unwrap();
+        // Wildcard entries (copper task ids) carry their argument in the
+        // method name; shell-only methods never come from the webview.
+        for entry in policy::METHOD_POLICIES {
+            if entry.scope == policy::Scope::Shell || entry.pattern.ends_with('*') {
+                continue;
+            }
+            assert!(
+                registry.methods.contains_key(entry.pattern),
+                "{} is allowed but has no schema in kernel_methods.json",
+                entry.pattern
+            );
+        }
+    }
+
+    #[test]
+    fn test_field_level_errors() {
+        let registry = SchemaRegistry::load().unwrap();
+        let err = registry
+            .validate(
+                "play/scenes/create",
+                &json!({ "title": "  ", "notes": 3, "link": null }),
+            )
+            .unwrap_err();
+        let paths: Vec<&str> = err.errors.iter().map(|e| e.path.as_str()).collect();
+        assert!(paths.contains(&"/act_id"), "{paths:?}");
+        assert!(paths.contains(&"/title"), "{paths:?}");
+        assert!(paths.contains(&"/notes"), "{paths:?}");
+        assert_eq!(err.errors.len(), 3);
+
+        assert!(registry
+            .validate(
+                "play/scenes/create",
+                &json!({ "act_id": "a1", "title": "Plan", "notes": null })
+            )
+            .is_ok());
+    }
+
+    #[test]
+    fn test_unlisted_methods_require_an_object() {
+        let registry = SchemaRegistry::load().unwrap();
+        assert!(registry.validate("copper/tasks/t1", &json!({})).is_ok());
+        let err = registry
+            .validate("copper/tasks/t1", &json!([1, 2]))
+            .unwrap_err();
+        assert_eq!(err.errors[0].path, "");
+        assert!(err
+            .to_string()
+            .starts_with("invalid params for copper/tasks/t1: "));
+    }
+}
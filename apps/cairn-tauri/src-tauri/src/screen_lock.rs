@@ -0,0 +1,308 @@
+//! Lock every session when the desktop locks or the machine suspends.
+//!
+//! Sessions guard decrypted data, so they should not outlive the user walking
+//! away. Three D-Bus signals count as walking away:
+//!
+//! - `ActiveChanged(true)` from `org.freedesktop.ScreenSaver` (or
+//!   `org.gnome.ScreenSaver`) on the session bus, when the screensaver starts;
+//! - `Lock` from logind on the system bus, for this process's login session
+//!   (`loginctl lock-session`, lid and idle policies);
+//! - `PrepareForSleep(true)` from logind on the system bus, just before
+//!   suspend or hibernate.
+//!
+//! `subscribe` registers for all of them and `LockSignals::next` yields one
+//! `LockCause` per signal. `main.rs` then ends the sessions, tells the kernel
+//! to drop their keys and emits `LOCKED_EVENT`. Signals are only accepted
+//! from the owners of the well-known names above.
+
+use futures_util::stream::{select_all, BoxStream, SelectAll};
+use futures_util::StreamExt;
+use serde::Serialize;
+use zbus::message::Type;
+use zbus::zvariant::OwnedObjectPath;
+use zbus::{Connection, MatchRule, Message, MessageStream};
+
+/// Event emitted to every window once the sessions have been locked.
+pub const LOCKED_EVENT: &str = "cairn://locked";
+
+const SCREENSAVERS: &[&str] = &["org.freedesktop.ScreenSaver", "org.gnome.ScreenSaver"];
+const LOGIN1: &str = "org.freedesktop.login1";
+const LOGIN1_PATH: &str = "/org/freedesktop/login1";
+const LOGIN1_MANAGER: &str = "org.freedesktop.login1.Manager";
+const LOGIN1_SESSION: &str = "org.freedesktop.login1.Session";
+
+/// What locked the sessions.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LockCause {
+    /// The screensaver started.
+    ScreenSaver,
+    /// logind locked the login session.
+    SessionLock,
+    /// The machine is about to suspend or hibernate.
+    Sleep,
+}
+
+/// Payload of `LOCKED_EVENT`
+#[derive(Debug, Clone, Serialize)]
+pub struct LockedEvent {
+    pub cause: LockCause,
+    /// How many sessions were ended.
+    pub sessions: usize,
+}
+
+/// The lock signals subscribed to by `subscribe`.
+pub struct LockSignals {
+    streams: SelectAll<BoxStream<'static, Option<LockCause>>>,
+}
+
+impl LockSignals {
+    /// Wait for the next lock signal. `None` once every bus has gone away.
+    pub async fn next(&mut self) -> Option<LockCause> {
+        while let Some(item) = self.streams.next().await {
+            if let Some(cause) = item {
+                return Some(cause);
+            }
+        }
+        None
+    }
+}
+
+/// Subscribe to the screensaver on `session_bus` and to logind on
+/// `system_bus`. Either may be absent; the match rules are in place once
+/// this returns.
+pub async fn subscribe(
+    session_bus: Option<&Connection>,
+    system_bus: Option<&Connection>,
+) -> zbus::Result<LockSignals> {
+    let mut streams = Vec::new();
+    if let Some(conn) = session_bus {
+        for &name in SCREENSAVERS {
+            let stream = signal(conn, name, name, "ActiveChanged", None).await?;
+            streams.push(when_true(stream, LockCause::ScreenSaver));
+        }
+    }
+    if let Some(conn) = system_bus {
+        let stream = signal(conn, LOGIN1, LOGIN1_MANAGER, "PrepareForSleep", None).await?;
+        streams.push(when_true(stream, LockCause::Sleep));
+
+        // Outside a logind session (or with logind absent) every session's
+        // Lock is honoured: locking too often beats not locking at all.
+        let own = own_session_path(conn).await;
+        let stream = signal(conn, LOGIN1, LOGIN1_SESSION, "Lock", own).await?;
+        streams.push(stream.map(|_| Some(LockCause::SessionLock)).boxed());
+    }
+    Ok(LockSignals {
+        streams: select_all(streams),
+    })
+}
+
+/// Connect to the desktop's buses and subscribe to both. A bus that is not
+/// available is skipped with a note on stderr.
+pub async fn subscribe_desktop() -> zbus::Result<LockSignals> {
+    let session_bus = Connection::session()
+        .await
+        .inspect_err(|e| eprintln!("[screen-lock] no session bus: {e}"))
+        .ok();
+    let system_bus = Connection::system()
+        .await
+        .inspect_err(|e| eprintln!("[screen-lock] no system bus: {e}"))
+        .ok();
+    subscribe(session_bus.as_ref(), system_bus.as_ref()).await
+}
+
+async fn signal(
+    conn: &Connection,
+    sender: &'static str,
+    interface: &'static str,
+    member: &'static str,
+    path: Option<OwnedObjectPath>,
+) -> zbus::Result<MessageStream> {
+    let mut rule = MatchRule::builder()
+        .msg_type(Type::Signal)
+        .sender(sender)?
+        .interface(interface)?
+        .member(member)?;
+    if let Some(path) = path {
+        rule = rule.path(path)?;
+    }
+    MessageStream::for_match_rule(rule.build(), conn, None).await
+}
+
+/// Yield `cause` for each signal whose boolean argument is true.
+fn when_true(stream: MessageStream, cause: LockCause) -> BoxStream<'static, Option<LockCause>> {
+    stream
+        .map(move |msg| {
+            let active = msg.ok().as_ref().and_then(bool_arg).unwrap_or(false);
+            active.then_some(cause)
+        })
+        .boxed()
+}
+
+fn bool_arg(msg: &Message) -> Option<bool> {
+    msg.body().deserialize::<bool>().ok()
+}
+
+/// logind's object for the login session this process belongs to.
+async fn own_session_path(conn: &Connection) -> Option<OwnedObjectPath> {
+    let reply = conn
+        .call_method(
+            Some(LOGIN1),
+            LOGIN1_PATH,
+            Some(LOGIN1_MANAGER),
+            "GetSessionByPID",
+            &std::process::id(),
+        )
+        .await
+        .ok()?;
+    reply.body().deserialize().ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures_util::future::{select, Either};
+    use std::io::{BufRead, BufReader};
+    use std::pin::pin;
+    use std::process::{Command, Stdio};
+    use std::time::Duration;
+
+    /// A private bus, stopped on drop.
+    struct TestBus {
+        daemon: std::process::Child,
+        address: String,
+        _dir: TempDir,
+    }
+
+    struct TempDir(std::path::PathBuf);
+
+    impl Drop for TempDir {
+        fn drop(&mut self) {
+            let _ = std::fs::remove_dir_all(&self.0);
+        }
+    }
+
+    impl Drop for TestBus {
+        fn drop(&mut self) {
+            let _ = self.daemon.kill();
+            let _ = self.daemon.wait();
+        }
+    }
+
+    /// Start a `dbus-daemon` with a permissive config, or `None` if there is
+    /// no `dbus-daemon` to run.
+    fn test_bus() -> Option<TestBus> {
+        let dir = std::env::temp_dir().join(format!("cairn-dbus-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).ok()?;
+        let config = dir.join("bus.conf");
+        std::fs::write(
+            &config,
+            format!(
+                r#"<busconfig>
+  <type>session</type>
+  <listen>unix:dir={}</listen>
+  <auth>EXTERNAL</auth>
+  <policy context="default">
+    <allow send_destination="*"/>
+    <allow receive_sender="*"/>
+    <allow own="*"/>
+  </policy>
+</busconfig>"#,
+                dir.display()
+            ),
+        )
+        .ok()?;
+        let mut daemon = Command::new("dbus-daemon")
+            .arg(format!("--config-file={}", config.display()))
+            .args(["--nofork", "--print-address"])
+            .stdout(Stdio::piped())
+            .spawn()
+            .ok()?;
+        let mut address = String::new();
+        BufReader::new(daemon.stdout.take()?)
+            .read_line(&mut address)
+            .ok()?;
+        Some(TestBus {
+            daemon,
+            address: address.trim().to_string(),
+            _dir: TempDir(dir),
+        })
+    }
+
+    async fn connect(address: &str) -> Connection {
+        zbus::connection::Builder::address(address)
+            .unwrap()
+            .build()
+            .await
+            .unwrap()
+    }
+
+    #[test]
+    fn test_lock_signals_from_screensaver_and_logind() {
+        let Some(bus) = test_bus() else {
+            eprintln!("dbus-daemon not available, skipping");
+            return;
+        };
+        tauri::async_runtime::block_on(async {
+            let watcher = connect(&bus.address).await;
+            // The same bus stands in for both the session and system bus.
+            let mut signals = subscribe(Some(&watcher), Some(&watcher)).await.unwrap();
+
+            let desktop = connect(&bus.address).await;
+            desktop.request_name(SCREENSAVERS[0]).await.unwrap();
+            desktop.request_name(LOGIN1).await.unwrap();
+            let stranger = connect(&bus.address).await;
+
+            // Deactivation and signals from impostors are ignored.
+            let path = "/org/freedesktop/ScreenSaver";
+            let (screensaver, none) = (SCREENSAVERS[0], None::<&str>);
+            desktop
+                .emit_signal(none, path, screensaver, "ActiveChanged", &false)
+                .await
+                .unwrap();
+            stranger
+                .emit_signal(none, path, screensaver, "ActiveChanged", &true)
+                .await
+                .unwrap();
+            desktop
+                .emit_signal(none, path, screensaver, "ActiveChanged", &true)
+                .await
+                .unwrap();
+            assert_eq!(
+                within_timeout(signals.next()).await,
+                Some(LockCause::ScreenSaver)
+            );
+
+            let session = "/org/freedesktop/login1/session/_31";
+            desktop
+                .emit_signal(none, session, LOGIN1_SESSION, "Lock", &())
+                .await
+                .unwrap();
+            assert_eq!(
+                within_timeout(signals.next()).await,
+                Some(LockCause::SessionLock)
+            );
+
+            desktop
+                .emit_signal(none, LOGIN1_PATH, LOGIN1_MANAGER, "PrepareForSleep", &false)
+                .await
+                .unwrap();
+            desktop
+                .emit_signal(none, LOGIN1_PATH, LOGIN1_MANAGER, "PrepareForSleep", &true)
+                .await
+                .unwrap();
+            assert_eq!(within_timeout(signals.next()).await, Some(LockCause::Sleep));
+        });
+    }
+
+    /// `future`'s output, or `None` if it takes more than five seconds.
+    async fn within_timeout<T>(future: impl std::future::Future<Output = Option<T>>) -> Option<T> {
+        let timer = tauri::async_runtime::spawn_blocking(|| {
+            std::thread::sleep(Duration::from_secs(5));
+        });
+        match select(pin!(future), timer).await {
+            Either::Left((value, _)) => value,
+            Either::Right(_) => None,
+        }
+    }
+}
@@ -7,20 +7,88 @@
  * - Session tokens are stored in localStorage (shared across windows)
  * - Tokens are 256-bit CSPRNG, validated by Rust on every request
  */
-import { invoke } from '@tauri-apps/api/core';
-import { JsonRpcResponseSchema } from './types';
+import { Channel, invoke } from '@tauri-apps/api/core';
+import { listen, type UnlistenFn } from '@tauri-apps/api/event';
 
 // Session token storage (localStorage is shared across windows)
 const SESSION_TOKEN_KEY = 'cairn_session_token';
 const SESSION_USERNAME_KEY = 'cairn_session_username';
 
+// JSON-RPC error code the kernel returns when the session is missing/invalid.
+const SESSION_REQUIRED = -32003;
+
+/**
+ * Structured error returned by every Rust command (see `error.rs`).
+ * - `kernel`: transport failure; `reason` is e.g. 'timeout', 'cancelled', 'exited'
+ * - `rpc`: the kernel answered with a JSON-RPC error (`code`, `data`)
+ * - `auth`: missing, invalid or expired session
+ * - `policy`: the shell refused the method; `reason` is 'unknown_method',
+ *   'unknown_tool', 'shell_only', 'reauth_required', 'rate_limited' or
+ *   'declined'
+ * - `invalid_params`: params failed the method's schema; `errors` lists
+ *   each failing field as a JSON pointer and message
+ */
+export interface CommandError {
+  kind:
+    | 'kernel'
+    | 'rpc'
+    | 'auth'
+    | 'policy'
+    | 'invalid_params'
+    | 'pty'
+    | 'invalid_argument'
+    | 'lock_poisoned'
+    | 'internal';
+  message: string;
+  code?: number;
+  data?: unknown;
+  reason?: string;
+  errors?: ParamsFieldError[];
+}
+
+/** One schema failure in an `invalid_params` error. */
+export interface ParamsFieldError {
+  /** JSON pointer into the params, e.g. '/title'. */
+  path: string;
+  message: string;
+}
+
+/**
+ * Check whether a rejected `invoke` value is a structured `CommandError`.
+ */
+export function isCommandError(value: unknown): value is CommandError {
+  return (
+    typeof value === 'object' &&
+    value !== null &&
+    typeof (value as CommandError).kind === 'string' &&
+    typeof (value as CommandError).message === 'string'
+  );
+}
+
+/**
+ * Human-readable message for anything an `invoke` call rejected with.
+ */
+export function errorMessage(value: unknown): string {
+  if (value instanceof Error) return value.message;
+  if (isCommandError(value)) return value.message;
+  return String(value);
+}
+
 export class KernelError extends Error {
   code: number;
+  kind: CommandError['kind'];
+  reason?: string;
+  data?: unknown;
+  errors?: ParamsFieldError[];
 
-  constructor(message: string, code: number) {
+  constructor(message: string, code: number, detail: Partial<CommandError> = {}) {
     super(message);
     this.name = 'KernelError';
     this.code = code;
+    this.kind = detail.kind ?? 'rpc';
+    this.reason = detail.reason;
+    this.data = detail.data;
+    this.errors = detail.errors;
   }
 }
 
@@ -165,34 +233,545 @@ export async function refreshSession(): Promise<boolean> {
   }
 }
 
+/**
+ * Rethrow a rejected kernel command as `AuthenticationError` (clearing the
+ * stored session) or `KernelError`.
+ */
+function rethrowKernelFailure(err: unknown): never {
+  if (!isCommandError(err)) {
+    throw err;
+  }
+  if (err.kind === 'auth' || (err.kind === 'rpc' && err.code === SESSION_REQUIRED)) {
+    clearSession();
+    throw new AuthenticationError('Session expired. Please login again.');
+  }
+  throw new KernelError(err.message, err.code ?? -32603, err);
+}
+
 /**
  * Send a JSON-RPC request to the Python kernel.
  * Requires an authenticated session.
  *
  * @param method - The RPC method name (e.g., 'chat/respond', 'tools/call')
  * @param params - The parameters for the method
+ * @param options - Optional `requestId` handle for `cancelKernelRequest`
  * @returns The result from the kernel
  * @throws AuthenticationError if not authenticated
+ * @throws KernelError if the kernel returns an error or cannot be reached
+ */
+export async function kernelRequest(
+  method: string,
+  params: unknown,
+  options: { requestId?: string } = {},
+): Promise<unknown> {
+  const sessionToken = getSessionToken();
+
+  if (!sessionToken) {
+    throw new AuthenticationError('Not authenticated. Please login first.');
+  }
+
+  try {
+    return await invoke('kernel_request', {
+      sessionToken,
+      method,
+      params,
+      requestId: options.requestId ?? null,
+    });
+  } catch (err) {
+    rethrowKernelFailure(err);
+  }
+}
+
+/**
+ * Events delivered on a `kernel_request_stream` channel.
+ */
+type StreamEvent =
+  | { event: 'partial'; data: unknown }
+  | { event: 'result'; data: unknown }
+  | { event: 'error'; data: CommandError };
+
+/**
+ * Send a JSON-RPC request whose response the kernel may stream in chunks
+ * (e.g. token-by-token chat). Kernels that do not stream for the method
+ * just resolve with the final result and never call `onPartial`.
+ *
+ * @param method - The RPC method name
+ * @param params - The parameters for the method
+ * @param onPartial - Called with each partial chunk as it arrives
+ * @param options - Optional `requestId` handle for `cancelKernelRequest`
+ * @returns The final result from the kernel
+ * @throws AuthenticationError if not authenticated
  * @throws KernelError if the kernel returns an error
  */
-export async function kernelRequest(method: string, params: unknown): Promise<unknown> {
+export async function kernelRequestStream(
+  method: string,
+  params: unknown,
+  onPartial: (chunk: unknown) => void,
+  options: { requestId?: string } = {},
+): Promise<unknown> {
   const sessionToken = getSessionToken();
 
   if (!sessionToken) {
     throw new AuthenticationError('Not authenticated. Please login first.');
   }
 
-  const raw = await invoke('kernel_request', { sessionToken, method, params });
-  const parsed = JsonRpcResponseSchema.parse(raw);
+  // Channel messages can be delivered after the invoke promise settles, so
+  // wait for the terminal event itself rather than for the command.
+  let resolveFinal: (event: StreamEvent) => void = () => {};
+  const final = new Promise<StreamEvent>((resolve) => {
+    resolveFinal = resolve;
+  });
+  const onEvent = new Channel<StreamEvent>();
+  onEvent.onmessage = (message) => {
+    if (message.event === 'partial') {
+      onPartial(message.data);
+    } else {
+      resolveFinal(message);
+    }
+  };
 
-  if (parsed.error) {
-    // Check for session errors
-    if (parsed.error.code === -32003) {
-      clearSession();
-      throw new AuthenticationError('Session expired. Please login again.');
+  try {
+    await invoke('kernel_request_stream', {
+      sessionToken,
+      method,
+      params,
+      requestId: options.requestId ?? null,
+      onEvent,
+    });
+  } catch (err) {
+    rethrowKernelFailure(err);
+  }
+
+  const last = await final;
+  if (last.event === 'error') {
+    rethrowKernelFailure(last.data);
+  }
+  return last.data;
+}
+
+/**
+ * One call in a `kernelRequestBatch`.
+ */
+export interface KernelBatchCall {
+  method: string;
+  params?: unknown;
+}
+
+/**
+ * Outcome of one call in a `kernelRequestBatch`, in input order.
+ */
+export type KernelBatchOutcome =
+  | { status: 'ok'; result: unknown }
+  | { status: 'error'; error: CommandError };
+
+/**
+ * Send several JSON-RPC requests to the kernel in one round trip.
+ * The session is checked once; every call is still checked, audited and
+ * cached on its own, and a failed call does not fail the others.
+ *
+ * @param calls - Up to 64 method/params pairs
+ * @param options - Optional `requestId` handle for `cancelKernelRequest`,
+ *   which cancels every call still in flight
+ * @returns One outcome per call, in the same order
+ * @throws AuthenticationError if not authenticated
+ * @throws KernelError if the batch as a whole is rejected
+ */
+export async function kernelRequestBatch(
+  calls: KernelBatchCall[],
+  options: { requestId?: string } = {},
+): Promise<KernelBatchOutcome[]> {
+  const sessionToken = getSessionToken();
+
+  if (!sessionToken) {
+    throw new AuthenticationError('Not authenticated. Please login first.');
+  }
+
+  try {
+    return await invoke<KernelBatchOutcome[]>('kernel_request_batch', {
+      sessionToken,
+      calls: calls.map(({ method, params }) => ({ method, params: params ?? null })),
+      requestId: options.requestId ?? null,
+    });
+  } catch (err) {
+    rethrowKernelFailure(err);
+  }
+}
+
+/**
+ * Kernel methods that accept an uploaded file via `kernelUpload`.
+ */
+export type KernelUploadMethod = 'documents/insert' | 'play/attachments/add';
+
+/**
+ * Progress of a `kernelUpload`, reported after every chunk.
+ */
+export interface UploadProgress {
+  sent: number;
+  total: number;
+}
+
+/**
+ * A filter for `pickUploadFile`'s dialog.
+ */
+export interface FileFilter {
+  name: string;
+  extensions: string[];
+}
+
+/**
+ * Let the user pick a file to upload, in the shell's native dialog.
+ * `kernelUpload` only accepts paths returned from here, each once.
+ *
+ * @param filters - File types offered in the dialog
+ * @returns The picked path, or null if the dialog was cancelled
+ * @throws AuthenticationError if not authenticated
+ */
+export async function pickUploadFile(filters: FileFilter[] = []): Promise<string | null> {
+  const sessionToken = getSessionToken();
+
+  if (!sessionToken) {
+    throw new AuthenticationError('Not authenticated. Please login first.');
+  }
+
+  try {
+    return await invoke<string | null>('pick_upload_file', { sessionToken, filters });
+  } catch (err) {
+    rethrowKernelFailure(err);
+  }
+}
+
+/**
+ * Upload a file from disk into the kernel, then call `method` with the
+ * uploaded file in place of `file_path`. The shell streams the bytes in
+ * binary chunks; nothing is base64 encoded.
+ *
+ * @param path - Path returned by `pickUploadFile`
+ * @param method - The method that receives the file
+ * @param params - Its other params
+ * @param onProgress - Called after each chunk is sent
+ * @returns The method's result
+ * @throws AuthenticationError if not authenticated
+ * @throws KernelError if the upload or the call fails
+ */
+export async function kernelUpload(
+  path: string,
+  method: KernelUploadMethod,
+  params: Record<string, unknown> = {},
+  onProgress?: (progress: UploadProgress) => void,
+): Promise<unknown> {
+  const sessionToken = getSessionToken();
+
+  if (!sessionToken) {
+    throw new AuthenticationError('Not authenticated. Please login first.');
+  }
+
+  const channel = new Channel<UploadProgress>();
+  channel.onmessage = (progress) => onProgress?.(progress);
+
+  try {
+    return await invoke('kernel_upload', {
+      sessionToken,
+      path,
+      method,
+      params,
+      onProgress: channel,
+    });
+  } catch (err) {
+    rethrowKernelFailure(err);
+  }
+}
+
+/**
+ * What a `blobUrl` points at.
+ */
+export type BlobKind = 'attachment' | 'document' | 'blob';
+
+/**
+ * A `cairn-blob://` URL for an attachment, a document's original file or an
+ * uploaded blob, usable directly as an `<img>`, `<iframe>` or `fetch`
+ * source. It stops working after ten minutes or when the session ends.
+ *
+ * @param kind - What `id` identifies
+ * @param id - The attachment, document or blob id
+ * @throws AuthenticationError if not authenticated
+ */
+export async function blobUrl(kind: BlobKind, id: string): Promise<string> {
+  const sessionToken = getSessionToken();
+
+  if (!sessionToken) {
+    throw new AuthenticationError('Not authenticated. Please login first.');
+  }
+
+  try {
+    return await invoke<string>('blob_url', { sessionToken, kind, id });
+  } catch (err) {
+    rethrowKernelFailure(err);
+  }
+}
+
+/**
+ * Cancel an in-flight kernel request started with the given `requestId`.
+ * The pending `kernelRequest` promise rejects with a cancellation error;
+ * calls of a `kernelRequestBatch` still in flight come back as cancelled
+ * outcomes.
+ *
+ * @param requestId - The handle passed to `kernelRequest` or `kernelRequestBatch`
+ * @returns True if a matching request was in flight
+ */
+export async function cancelKernelRequest(requestId: string): Promise<boolean> {
+  const sessionToken = getSessionToken();
+  if (!sessionToken) return false;
+
+  try {
+    return await invoke<boolean>('kernel_cancel', { sessionToken, requestId });
+  } catch {
+    return false;
+  }
+}
+
+/**
+ * Kernel lifecycle event emitted by the Rust supervisor.
+ */
+export interface KernelStatusEvent {
+  status: 'starting' | 'ready' | 'crashed' | 'restarting';
+  epoch: number;
+  detail?: string;
+}
+
+/**
+ * Subscribe to kernel lifecycle changes (e.g. to show a "restarting" banner).
+ * @param handler - Called for every `cairn://kernel-status` event
+ * @returns Function that removes the listener
+ */
+export function onKernelStatus(handler: (event: KernelStatusEvent) => void): Promise<UnlistenFn> {
+  return listen<KernelStatusEvent>('cairn://kernel-status', (event) => handler(event.payload));
+}
+
+/**
+ * Emitted when the Rust shell reaps an idle session.
+ */
+export interface SessionExpiredEvent {
+  username: string;
+  /** First 16 characters of the expired session's token. */
+  session_id: string;
+  /** `kernel` when the kernel holding the session's key exited or dropped it. */
+  reason: 'idle' | 'lifetime' | 'kernel';
+}
+
+/**
+ * Subscribe to session expiry, so the lock screen can show without waiting
+ * for a request to fail.
+ * @param handler - Called for every `cairn://session-expired` event
+ * @returns Function that removes the listener
+ */
+export function onSessionExpired(handler: (event: SessionExpiredEvent) => void): Promise<UnlistenFn> {
+  return listen<SessionExpiredEvent>('cairn://session-expired', (event) => handler(event.payload));
+}
+
+/**
+ * Emitted once a session enters the warning window before it expires.
+ */
+export interface SessionExpiringEvent {
+  username: string;
+  /** First 16 characters of the session's token. */
+  session_id: string;
+  expires_in_secs: number;
+  /** `idle` can be extended with `refreshSession`; `lifetime` needs a new login. */
+  reason: 'idle' | 'lifetime';
+}
+
+/**
+ * Subscribe to warnings that a session is about to expire.
+ * @param handler - Called for every `cairn://session-expiring` event
+ * @returns Function that removes the listener
+ */
+export function onSessionExpiring(handler: (event: SessionExpiringEvent) => void): Promise<UnlistenFn> {
+  return listen<SessionExpiringEvent>('cairn://session-expiring', (event) => handler(event.payload));
+}
+
+/**
+ * Emitted when the shell ends every session because the desktop locked.
+ */
+export interface LockedEvent {
+  cause: 'screen_saver' | 'session_lock' | 'sleep';
+  /** How many sessions were ended. */
+  sessions: number;
+}
+
+/**
+ * Subscribe to screen lock and suspend, after which every session is gone.
+ * @param handler - Called for every `cairn://locked` event
+ * @returns Function that removes the listener
+ */
+export function onLocked(handler: (event: LockedEvent) => void): Promise<UnlistenFn> {
+  return listen<LockedEvent>('cairn://locked', (event) => handler(event.payload));
+}
+
+/**
+ * Subscribe this window to a kernel notification method and handle its events.
+ * The kernel pushes these as id-less JSON-RPC messages; the Rust shell only
+ * forwards them to windows that subscribed.
+ *
+ * @param method - Exact notification method (e.g. 'cairn/chat_status')
+ * @param handler - Called with the notification params
+ * @returns Function that removes the listener and the subscription
+ */
+export async function subscribeKernel<T = unknown>(
+  method: string,
+  handler: (params: T) => void,
+): Promise<UnlistenFn> {
+  const sessionToken = getSessionToken();
+  if (!sessionToken) {
+    throw new AuthenticationError('Not authenticated. Please login first.');
+  }
+
+  const unlisten = await listen<T>(`cairn://kernel/${method}`, (event) => handler(event.payload));
+  await invoke('kernel_subscribe', { sessionToken, topics: [method] });
+
+  return () => {
+    unlisten();
+    const token = getSessionToken();
+    if (token) {
+      invoke('kernel_unsubscribe', { sessionToken: token, topics: [method] }).catch(() => {});
     }
-    throw new KernelError(parsed.error.message, parsed.error.code);
+  };
+}
+
+/**
+ * Protocol info negotiated with the kernel during its startup handshake.
+ */
+export interface KernelInfo {
+  protocol_version: number;
+  kernel_version: string;
+  capabilities: string[];
+}
+
+/**
+ * Get the running kernel's negotiated protocol info (for diagnostics).
+ * @returns Kernel info, or null if no kernel is running
+ */
+export async function getKernelInfo(): Promise<KernelInfo | null> {
+  try {
+    return await invoke<KernelInfo | null>('kernel_info');
+  } catch {
+    return null;
+  }
+}
+
+/**
+ * A captured line of kernel stderr.
+ */
+export interface KernelLogLine {
+  timestamp_ms: number;
+  level: 'debug' | 'info' | 'warning' | 'error' | 'critical';
+  source?: string;
+  message: string;
+}
+
+/**
+ * Fetch recent kernel log lines for the diagnostics panel.
+ * @param filter - Minimum level, earliest capture time (Unix ms), max lines
+ * @returns Matching lines, oldest first
+ */
+export async function getKernelLogs(
+  filter: { level?: KernelLogLine['level']; sinceMs?: number; limit?: number } = {},
+): Promise<KernelLogLine[]> {
+  const sessionToken = getSessionToken();
+  if (!sessionToken) {
+    throw new AuthenticationError('Not authenticated. Please login first.');
   }
 
-  return parsed.result;
+  return invoke<KernelLogLine[]>('kernel_logs', {
+    sessionToken,
+    level: filter.level ?? null,
+    sinceMs: filter.sinceMs ?? null,
+    limit: filter.limit ?? null,
+  });
+}
+
+/**
+ * Latency percentiles for one method, in milliseconds.
+ */
+export interface LatencySummary {
+  p50_ms: number;
+  p95_ms: number;
+  p99_ms: number;
+  mean_ms: number;
+  max_ms: number;
+}
+
+/**
+ * Request metrics since the app started for the methods matching one policy
+ * pattern (`method`), or `(unlisted)` for methods no pattern matches.
+ * `queue_wait` is the time before the request got the writer to the kernel.
+ */
+export interface KernelMethodMetrics {
+  method: string;
+  requests: number;
+  errors: number;
+  latency: LatencySummary;
+  queue_wait: LatencySummary;
+}
+
+/**
+ * Fetch per-method kernel request metrics for the latency dashboard.
+ */
+export async function getKernelMetrics(): Promise<{
+  since_ms: number;
+  methods: KernelMethodMetrics[];
+}> {
+  const sessionToken = getSessionToken();
+  if (!sessionToken) {
+    throw new AuthenticationError('Not authenticated. Please login first.');
+  }
+
+  return invoke('kernel_metrics', { sessionToken });
+}
+
+/**
+ * Result of checking the shell's hash-chained audit log of kernel calls.
+ * `head_seq`/`head_hash` identify the last good entry; record them to detect
+ * entries later removed from the end.
+ */
+export interface AuditVerification {
+  intact: boolean;
+  entries: number;
+  head_seq: number;
+  head_hash: string;
+  problem: { line: number; reason: string } | null;
+}
+
+/**
+ * Verify the audit log's hash chain.
+ */
+export async function verifyAuditLog(): Promise<AuditVerification> {
+  const sessionToken = getSessionToken();
+  if (!sessionToken) {
+    throw new AuthenticationError('Not authenticated. Please login first.');
+  }
+
+  return invoke<AuditVerification>('audit_verify', { sessionToken });
+}
+
+/**
+ * Export the audit log as JSON lines.
+ */
+export async function exportAuditLog(): Promise<string> {
+  const sessionToken = getSessionToken();
+  if (!sessionToken) {
+    throw new AuthenticationError('Not authenticated. Please login first.');
+  }
+
+  return invoke<string>('audit_export', { sessionToken });
+}
+
+/**
+ * Stop the kernel gracefully (it restarts on the next request).
+ * @returns True if a kernel was running
+ */
+export async function stopKernel(): Promise<boolean> {
+  const sessionToken = getSessionToken();
+  if (!sessionToken) return false;
+
+  return invoke<boolean>('kernel_stop', { sessionToken });
 }
@@ -19,6 +19,11 @@ import {
   logout,
   getSessionUsername,
   getSessionToken,
+  onSessionExpired,
+  onSessionExpiring,
+  onLocked,
+  refreshSession,
+  subscribeKernel,
 } from './kernel';
 import { checkSessionOrLogin, showLockOverlay } from './lockScreen';
 import { el, escapeHtml, rowHeader, label, textInput, textArea, smallButton } from './dom';
@@ -511,7 +516,18 @@ function buildUi() {
   async function handleCairnMessage(message: string): Promise<void> {
     // Show thinking indicator while waiting for response
     cairnView.showThinking();
+    // The kernel announces the end of each async chat as a cairn/chat_status
+    // notification. Subscribe first: a quick chat can end before
+    // cairn/chat_async has returned its chat_id.
+    const endedChats = new Set<string>();
+    let onChatEnded: (() => void) | null = null;
+    let unsubscribe: (() => void) | null = null;
     try {
+      unsubscribe = await subscribeKernel<{ chat_id: string }>('cairn/chat_status', (params) => {
+        endedChats.add(params.chat_id);
+        onChatEnded?.();
+      });
+
       // Start async chat - returns immediately with chat_id
       // This allows consciousness/poll requests to be handled while chat processes
       const asyncResult = (await kernelRequest('cairn/chat_async', {
@@ -521,47 +537,47 @@ function buildUi() {
 
       const chatId = asyncResult.chat_id;
 
-      // Poll for chat completion
+      // Wait for the chat to end
       // The consciousness stream polling runs in parallel (started in cairnView.ts)
-      let chatComplete = false;
-      while (!chatComplete) {
-        // Small delay between status checks
-        await new Promise(resolve => setTimeout(resolve, 200));
-
-        const statusResult = (await kernelRequest('cairn/chat_status', {
-          chat_id: chatId,
-        })) as { status: string; result?: ChatRespondResult; error?: string };
-
-        if (statusResult.status === 'complete' && statusResult.result) {
-          chatComplete = true;
-          cairnView.hideThinking();
-          if (statusResult.result.conversation_id) {
-            currentConversationId = statusResult.result.conversation_id;
-          }
-          // Use addAssistantMessage to include full response data (thinking steps, tool calls)
-          cairnView.addAssistantMessage(statusResult.result);
-
-          // Persist consciousness events and show feedback UI (RLHF)
-          if (statusResult.result.conversation_id && statusResult.result.user_message_id && statusResult.result.message_id) {
-            void cairnView.persistAndShowFeedback(
-              statusResult.result.conversation_id,
-              statusResult.result.user_message_id,
-              statusResult.result.message_id,
-            );
-          }
-
-          // Refresh attention items after CAIRN chat - scene moves may have changed act assignments
-          void refreshAttentionItems();
-        } else if (statusResult.status === 'error') {
-          chatComplete = true;
-          cairnView.hideThinking();
-          cairnView.addChatMessage('assistant', `Error: ${statusResult.error || 'Unknown error'}`);
+      await new Promise<void>((resolve) => {
+        onChatEnded = () => {
+          if (endedChats.has(chatId)) resolve();
+        };
+        onChatEnded();
+      });
+
+      // The notification only carries the outcome; fetch the result once
+      const statusResult = (await kernelRequest('cairn/chat_status', {
+        chat_id: chatId,
+      })) as { status: string; result?: ChatRespondResult; error?: string };
+
+      cairnView.hideThinking();
+      if (statusResult.status === 'complete' && statusResult.result) {
+        if (statusResult.result.conversation_id) {
+          currentConversationId = statusResult.result.conversation_id;
+        }
+        // Use addAssistantMessage to include full response data (thinking steps, tool calls)
+        cairnView.addAssistantMessage(statusResult.result);
+
+        // Persist consciousness events and show feedback UI (RLHF)
+        if (statusResult.result.conversation_id && statusResult.result.user_message_id && statusResult.result.message_id) {
+          void cairnView.persistAndShowFeedback(
+            statusResult.result.conversation_id,
+            statusResult.result.user_message_id,
+            statusResult.result.message_id,
+          );
         }
-        // If status is "processing", continue polling
+
+        // Refresh attention items after CAIRN chat - scene moves may have changed act assignments
+        void refreshAttentionItems();
+      } else {
+        cairnView.addChatMessage('assistant', `Error: ${statusResult.error || 'Unknown error'}`);
       }
     } catch (error) {
       cairnView.hideThinking();
       cairnView.addChatMessage('assistant', `Error: ${error instanceof Error ? error.message : 'Unknown error'}`);
+    } finally {
+      unsubscribe?.();
     }
   }
 
@@ -1359,10 +1375,37 @@ async function initializeApp(): Promise<void> {
 /**
  * Set up session monitoring for auto-lock.
  * Monitors for:
- * - Session expiry (periodic validation)
+ * - Session expiry (reaped by the Rust shell, plus periodic validation)
+ * - Desktop screen lock and suspend (the shell ends every session)
  * - Window visibility changes (potential system lock)
  */
 function setupSessionMonitoring(): void {
+  // Lock as soon as the shell reaps this window's session
+  void onSessionExpired((event) => {
+    const token = getSessionToken();
+    if (token && token.startsWith(event.session_id)) {
+      showLockOverlay(() => {
+        // Session restored, continue normally
+      });
+    }
+  });
+
+  // The shell has already ended the session; just show the lock screen
+  void onLocked(() => {
+    if (!isAuthenticated()) return;
+    showLockOverlay(() => {
+      // Session restored, continue normally
+    });
+  });
+
+  // Warn before the cutoff; an idle session can be kept alive from here
+  void onSessionExpiring((event) => {
+    const token = getSessionToken();
+    if (token && token.startsWith(event.session_id)) {
+      showExpiryWarning(event.expires_in_secs, event.reason === 'idle');
+    }
+  });
+
   // Check session validity every 5 minutes
   setInterval(async () => {
     if (!isAuthenticated()) return;
@@ -1390,6 +1433,35 @@ function setupSessionMonitoring(): void {
   });
 }
 
+/**
+ * Show a banner counting down to session expiry. Idle sessions get a button
+ * that refreshes activity; a session at its maximum lifetime cannot be extended.
+ */
+function showExpiryWarning(expiresInSecs: number, canExtend: boolean): void {
+  document.getElementById('session-expiry-warning')?.remove();
+
+  const banner = el('div', { id: 'session-expiry-warning' });
+  banner.style.cssText = `
+    position: fixed; top: 0; left: 0; right: 0;
+    background: #f59e0b; color: #1f2937; padding: 10px;
+    text-align: center; font-size: 13px; z-index: 9998;
+  `;
+  const minutes = Math.max(1, Math.round(expiresInSecs / 60));
+  banner.textContent = canExtend
+    ? `Your session will lock in about ${minutes} min due to inactivity. `
+    : `Your session ends in about ${minutes} min; you will need to sign in again. `;
+
+  if (canExtend) {
+    const stay = smallButton('Stay signed in');
+    stay.addEventListener('click', () => {
+      void refreshSession().then(() => banner.remove());
+    });
+    banner.appendChild(stay);
+  }
+  document.body.appendChild(banner);
+  setTimeout(() => banner.remove(), expiresInSecs * 1000);
+}
+
 // Initialize app on load
 initializeApp().catch((err) => {
   console.error('Failed to initialize app:', err);
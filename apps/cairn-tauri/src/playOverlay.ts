@@ -14,7 +14,7 @@
 
 import { open } from '@tauri-apps/plugin-dialog';
 import { el } from './dom';
-import { kernelRequest } from './kernel';
+import { kernelRequest, kernelUpload, pickUploadFile } from './kernel';
 import { mountBlockEditor } from './react/index';
 import type {
   PlayActsListResult,
@@ -357,24 +357,22 @@ export function createPlayOverlay(onClose: () => void): {
 
   async function handleAddAttachment() {
     try {
-      const selected = await open({
-        multiple: false,
-        filters: [
-          {
-            name: 'Documents',
-            extensions: ['pdf', 'doc', 'docx', 'txt', 'csv', 'xls', 'xlsx', 'md'],
-          },
-        ],
-      });
-
-      if (selected && typeof selected === 'string') {
+      const selected = await pickUploadFile([
+        {
+          name: 'Documents',
+          extensions: ['pdf', 'doc', 'docx', 'txt', 'csv', 'xls', 'xlsx', 'md'],
+        },
+      ]);
+
+      if (selected) {
         // For play level, pass no act_id; for others, pass the appropriate IDs
-        const params: Record<string, string | null> = { file_path: selected };
+        const params: Record<string, string | null> = {};
         if (state.selectedLevel !== 'play' && state.activeActId) {
           params.act_id = state.activeActId;
           params.scene_id = state.selectedSceneId;
         }
-        await kernelRequest('play/attachments/add', params);
+        // The shell streams the file into the kernel's blob store.
+        await kernelUpload(selected, 'play/attachments/add', params);
 
         await refreshAttachments();
         render();
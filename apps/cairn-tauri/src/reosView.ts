@@ -15,6 +15,7 @@ import '@xterm/xterm/css/xterm.css';
 import { invoke } from '@tauri-apps/api/core';
 import { listen, type UnlistenFn } from '@tauri-apps/api/event';
 import { el } from './dom';
+import { errorMessage } from './kernel';
 import { createConversationalShell } from './reosConversationalView';
 
 // ── Types ──────────────────────────────────────────────────────────────
@@ -1266,7 +1267,7 @@ export function createReosView(callbacks: ReosViewCallbacks): {
         currentTraceId = '';
       }
     }).catch((e: unknown) => {
-      const msg = e instanceof Error ? e.message : String(e);
+      const msg = errorMessage(e);
       termStatus.textContent = `Error: ${msg}`;
       termStatus.style.color = 'rgba(239,68,68,0.8)';
       console.error('[PTY] pty_start error:', e);
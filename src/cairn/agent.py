@@ -6,6 +6,7 @@ import re
 import sqlite3
 import uuid
 from dataclasses import dataclass, field
+from collections.abc import Callable
 from datetime import datetime
 from typing import Any
 
@@ -524,6 +525,7 @@ class ChatAgent:
         extended_thinking: bool | None = None,
         is_system_initiated: bool = False,
         force_approve: bool = False,
+        on_token: Callable[[str], None] | None = None,
     ) -> ChatResponse:
         """Respond to user message with conversation context.
 
@@ -543,6 +545,8 @@ class ChatAgent:
             force_approve: Internal bypass for re-executing an approved operation.
                           Skips the approval gate in the atomic ops bridge.
                           Must not be exposed in RPC interfaces.
+            on_token: Called with each chunk of the raw answer as the model
+                     generates it, when the provider can stream.
 
         Returns:
             ChatResponse with answer and metadata
@@ -876,6 +880,7 @@ class ChatAgent:
             llm=llm,
             temperature=temperature,
             top_p=top_p,
+            on_token=on_token,
         )
 
         # Validate response certainty
@@ -1760,9 +1765,14 @@ class ChatAgent:
         llm: LLMProvider,
         temperature: float,
         top_p: float,
+        on_token: Callable[[str], None] | None = None,
     ) -> tuple[str, list[str]]:
         """Generate answer with optional thinking steps.
 
+        With ``on_token`` and a provider that can stream, the answer is
+        generated with ``chat_stream`` and each chunk is passed on as it
+        arrives.
+
         Returns:
             Tuple of (answer, thinking_steps)
         """
@@ -1839,7 +1849,15 @@ class ChatAgent:
             )
         print(f"[CAIRN DEBUG] User message preview: {user[:500]}", file=sys.stderr)
 
-        raw = llm.chat_text(system=system, user=user, temperature=temperature, top_p=top_p)
+        chat_stream = getattr(llm, "chat_stream", None) if on_token else None
+        if chat_stream is not None and on_token is not None:
+            chunks: list[str] = []
+            for chunk in chat_stream(system=system, user=user, temperature=temperature, top_p=top_p):
+                on_token(chunk)
+                chunks.append(chunk)
+            raw = "".join(chunks)
+        else:
+            raw = llm.chat_text(system=system, user=user, temperature=temperature, top_p=top_p)
 
         # Debug: trace LLM response
         print(
@@ -52,6 +52,8 @@ class Session:
     created_at: datetime
     last_activity: datetime
     key_material: bytes = field(repr=False)  # Never print key material
+    # Opaque name for the session, handed to the Rust shell in place of the token
+    ref: str = field(default_factory=lambda: secrets.token_hex(16), repr=False)
 
     def is_expired(self) -> bool:
         """Check if session has expired due to inactivity."""
@@ -72,12 +74,14 @@ class SessionStore:
 
     def __init__(self) -> None:
         self._sessions: dict[str, Session] = {}
+        self._tokens_by_ref: dict[str, str] = {}
         self._lock = threading.Lock()
 
     def insert(self, session: Session) -> None:
         """Store a new session."""
         with self._lock:
             self._sessions[session.token] = session
+            self._tokens_by_ref[session.ref] = session.token
 
     def get(self, token: str) -> Session | None:
         """Get a session by token (if valid and not expired)."""
@@ -98,9 +102,18 @@ class SessionStore:
                     zeros = bytes(len(session.key_material))
                     session.key_material = zeros
                 del self._sessions[token]
+                self._tokens_by_ref.pop(session.ref, None)
                 return True
             return False
 
+    def token_for_ref(self, ref: str) -> str | None:
+        """Find the token of the session named by ``ref``.
+
+        The Rust shell keeps only this reference, not the token itself.
+        """
+        with self._lock:
+            return self._tokens_by_ref.get(ref)
+
     def refresh(self, token: str) -> bool:
         """Refresh a session's activity timestamp."""
         with self._lock:
@@ -120,6 +133,7 @@ class SessionStore:
                     zeros = bytes(len(session.key_material))
                     session.key_material = zeros
                 del self._sessions[token]
+                self._tokens_by_ref.pop(session.ref, None)
             return len(expired)
 
 
@@ -507,7 +521,7 @@ def login_polkit(username: str) -> dict[str, Any]:
         username: Linux username
 
     Returns:
-        Dict with success status, session_token, username, or error
+        Dict with success status, session_token, username, session_ref, or error
     """
     from . import db_crypto
 
@@ -553,6 +567,7 @@ def login_polkit(username: str) -> dict[str, Any]:
         "success": True,
         "session_token": session.token,
         "username": session.username,
+        "session_ref": session.ref,
     }
 
 
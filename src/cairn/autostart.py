@@ -0,0 +1,73 @@
+"""Start Talking Rock when the user logs in.
+
+Autostart is an XDG autostart entry (``$XDG_CONFIG_HOME/autostart``, by
+default ``~/.config/autostart``) that runs the repository's ``talkingrock``
+launcher. Enabling writes the entry; disabling removes it.
+"""
+
+from __future__ import annotations
+
+import os
+from pathlib import Path
+from typing import Any
+
+DESKTOP_FILE_NAME = "talkingrock.desktop"
+
+
+def _autostart_dir() -> Path:
+    config_home = os.environ.get("XDG_CONFIG_HOME") or str(Path.home() / ".config")
+    return Path(config_home) / "autostart"
+
+
+def _cairn_executable() -> Path:
+    """The ``talkingrock`` launcher at the repository root."""
+    return Path(__file__).resolve().parents[2] / "talkingrock"
+
+
+def _desktop_entry(executable: Path) -> str:
+    return (
+        "[Desktop Entry]\n"
+        "Type=Application\n"
+        "Name=Talking Rock\n"
+        "Comment=Local-first AI assistant\n"
+        f'Exec="{executable}"\n'
+        "Terminal=false\n"
+        "X-GNOME-Autostart-enabled=true\n"
+    )
+
+
+def get_autostart_status() -> dict[str, Any]:
+    """Whether the autostart entry exists, and what it would launch."""
+    desktop_file = _autostart_dir() / DESKTOP_FILE_NAME
+    executable = _cairn_executable()
+    return {
+        "enabled": desktop_file.is_file(),
+        "desktop_file": str(desktop_file),
+        "cairn_executable": str(executable),
+        "cairn_exists": executable.is_file(),
+    }
+
+
+def set_autostart(enabled: bool) -> dict[str, Any]:
+    """Write or remove the autostart entry.
+
+    Returns ``success`` and the resulting ``enabled`` state, plus ``error``
+    when the entry could not be changed.
+    """
+    desktop_file = _autostart_dir() / DESKTOP_FILE_NAME
+    try:
+        if enabled:
+            executable = _cairn_executable()
+            if not executable.is_file():
+                return {
+                    "success": False,
+                    "enabled": desktop_file.is_file(),
+                    "error": f"Launcher not found: {executable}",
+                }
+            desktop_file.parent.mkdir(parents=True, exist_ok=True)
+            desktop_file.write_text(_desktop_entry(executable))
+        else:
+            desktop_file.unlink(missing_ok=True)
+    except OSError as exc:
+        return {"success": False, "enabled": desktop_file.is_file(), "error": str(exc)}
+    return {"success": True, "enabled": enabled}
@@ -0,0 +1,151 @@
+"""Blob store for binary uploads from the Rust shell.
+
+A file the user picks is streamed into the kernel as binary frames rather
+than passed as a path or as base64 inside JSON:
+
+1. ``blob/open`` with the file's name and size returns a ``blob_id``.
+2. The shell writes the bytes as ``application/octet-stream`` frames
+   carrying a ``Blob-Id`` header; each one is handed to ``BlobStore.append``.
+3. ``blob/close`` with the SHA-256 of everything sent checks size and digest
+   and moves the upload to ``<data_dir>/blobs/<blob_id>/<name>``.
+
+The committed blob's path is then given to methods that take a
+``blob_id`` (``play/attachments/add``, ``documents/insert``) in place of a
+``file_path``. ``blob/abort`` drops an upload the shell gave up on.
+"""
+
+from __future__ import annotations
+
+import hashlib
+import logging
+import re
+import shutil
+import threading
+import uuid
+from dataclasses import dataclass, field
+from pathlib import Path
+
+from .settings import settings
+
+logger = logging.getLogger(__name__)
+
+# Suggested chunk size, reported to the shell by ``blob/open``.
+CHUNK_SIZE = 256 * 1024
+
+# Largest upload accepted.
+MAX_BLOB_BYTES = 100 * 1024 * 1024
+
+_BLOB_ID = re.compile(r"^[0-9a-f]{32}$")
+_UNSAFE_NAME_CHARS = re.compile(r"[^\w.\- ]")
+
+
+class BlobError(ValueError):
+    """An upload that cannot be opened, completed or found."""
+
+
+def _safe_name(name: str) -> str:
+    """``name`` reduced to a plain file name that cannot leave its directory."""
+    name = _UNSAFE_NAME_CHARS.sub("_", Path(name).name).strip(". ")
+    return name[:200] or "blob"
+
+
+@dataclass
+class _Upload:
+    name: str
+    size: int
+    part: Path
+    received: int = 0
+    digest: "hashlib._Hash" = field(default_factory=hashlib.sha256)
+    # First problem seen while receiving; reported by ``close``.
+    error: str | None = None
+
+
+class BlobStore:
+    """Uploads in progress and the blobs they produced."""
+
+    def __init__(self, root: Path | None = None) -> None:
+        self.root = root if root is not None else settings.data_dir / "blobs"
+        self._uploads: dict[str, _Upload] = {}
+        self._lock = threading.Lock()
+
+    def open(self, name: str, size: int) -> str:
+        """Start an upload of ``size`` bytes and return its id."""
+        if size < 0 or size > MAX_BLOB_BYTES:
+            raise BlobError(f"size must be between 0 and {MAX_BLOB_BYTES} bytes")
+        blob_id = uuid.uuid4().hex
+        incoming = self.root / "incoming"
+        incoming.mkdir(parents=True, exist_ok=True, mode=0o700)
+        part = incoming / blob_id
+        part.touch(mode=0o600)
+        with self._lock:
+            self._uploads[blob_id] = _Upload(name=_safe_name(name), size=size, part=part)
+        return blob_id
+
+    def append(self, blob_id: str, data: bytes) -> None:
+        """Add a chunk to an upload.
+
+        Chunks arrive as frames with no reply, so problems are recorded on
+        the upload and reported when it is closed instead of raised here.
+        """
+        with self._lock:
+            upload = self._uploads.get(blob_id)
+        if upload is None:
+            logger.warning("Dropping %d bytes for unknown blob %s", len(data), blob_id[:32])
+            return
+        if upload.error is not None:
+            return
+        if upload.received + len(data) > upload.size:
+            upload.error = f"received more than the announced {upload.size} bytes"
+            return
+        try:
+            with upload.part.open("ab") as f:
+                f.write(data)
+        except OSError as exc:
+            upload.error = f"could not store upload: {exc}"
+            return
+        upload.received += len(data)
+        upload.digest.update(data)
+
+    def close(self, blob_id: str, sha256: str) -> Path:
+        """Finish an upload whose bytes hash to ``sha256`` and return its path."""
+        with self._lock:
+            upload = self._uploads.pop(blob_id, None)
+        if upload is None:
+            raise BlobError(f"unknown blob: {blob_id}")
+        try:
+            if upload.error is not None:
+                raise BlobError(upload.error)
+            if upload.received != upload.size:
+                raise BlobError(f"received {upload.received} of {upload.size} bytes")
+            if upload.digest.hexdigest() != sha256.lower():
+                raise BlobError("sha256 does not match the bytes received")
+            target_dir = self.root / blob_id
+            target_dir.mkdir(mode=0o700)
+            target = target_dir / upload.name
+            upload.part.replace(target)
+            return target
+        finally:
+            upload.part.unlink(missing_ok=True)
+
+    def abort(self, blob_id: str) -> bool:
+        """Drop an unfinished upload. False if there was none."""
+        with self._lock:
+            upload = self._uploads.pop(blob_id, None)
+        if upload is None:
+            return False
+        upload.part.unlink(missing_ok=True)
+        return True
+
+    def path(self, blob_id: str) -> Path | None:
+        """The file of a committed blob, or None."""
+        if not _BLOB_ID.match(blob_id):
+            return None
+        directory = self.root / blob_id
+        if not directory.is_dir():
+            return None
+        return next((f for f in directory.iterdir() if f.is_file()), None)
+
+    def discard(self, blob_id: str) -> None:
+        """Delete a committed blob once its contents have been copied elsewhere."""
+        if _BLOB_ID.match(blob_id):
+            shutil.rmtree(self.root / blob_id, ignore_errors=True)
@@ -52,6 +52,8 @@ class AgentProcess:
     events: list[dict[str, Any]] = field(default_factory=list)
     busy: bool = False
     partial_text: str = ""
+    # Called with (index, event) for every event buffered from the last send on
+    on_event: Callable[[int, dict[str, Any]], None] | None = field(default=None, repr=False)
     _read_task: asyncio.Task[None] | None = field(default=None, repr=False)
 
 
@@ -164,8 +166,19 @@ class CCManager:
 
     # --- Session (process management) ---
 
-    async def send_message(self, agent_id: str, text: str) -> dict[str, Any]:
-        """Send a message to an agent. Spawns a Claude Code process."""
+    async def send_message(
+        self,
+        agent_id: str,
+        text: str,
+        *,
+        on_event: Callable[[int, dict[str, Any]], None] | None = None,
+    ) -> dict[str, Any]:
+        """Send a message to an agent. Spawns a Claude Code process.
+
+        ``on_event``, if given, is called with the index and contents of every
+        event buffered for the agent from then on, as ``poll_events`` would
+        return them.
+        """
         conn = self._db.get_connection()
         row = conn.execute(
             "SELECT id, cwd, session_id FROM cc_agents WHERE id = ?", (agent_id,)
@@ -181,11 +194,13 @@ class CCManager:
         if ap.busy:
             raise RpcError(code=-32000, message="Agent is busy")
 
+        ap.on_event = on_event
+
         # Inject relevant context (e.g. approved memories) into the prompt
         text = self._inject_context(text)
 
         # Record user message
-        ap.events.append({"type": "user", "text": text})
+        self._push(ap, {"type": "user", "text": text})
         self._persist_history(agent_id, "user", text)
 
         # Spawn process
@@ -219,8 +234,8 @@ class CCManager:
             )
         except Exception as exc:
             ap.busy = False
-            ap.events.append({"type": "error", "text": f"Failed to start claude: {exc}"})
-            ap.events.append({"type": "done"})
+            self._push(ap, {"type": "error", "text": f"Failed to start claude: {exc}"})
+            self._push(ap, {"type": "done"})
             raise RpcError(code=-32000, message=f"Failed to spawn claude: {exc}") from exc
 
         ap.proc = proc
@@ -255,7 +270,7 @@ class CCManager:
             except asyncio.TimeoutError:
                 ap.proc.kill()
             ap.busy = False
-            ap.events.append({"type": "done"})
+            self._push(ap, {"type": "done"})
         return {"ok": True}
 
     def get_history(self, agent_id: str, limit: int = 100) -> list[dict[str, Any]]:
@@ -336,31 +351,31 @@ class CCManager:
                             if new_text:
                                 current_partial = block["text"]
                                 ap.partial_text = block["text"]
-                                ap.events.append(
-                                    {"type": "assistant_delta", "text": new_text}
-                                )
+                                self._push(ap, {"type": "assistant_delta", "text": new_text})
 
                         elif block.get("type") == "tool_use":
-                            ap.events.append(
+                            self._push(
+                                ap,
                                 {
                                     "type": "tool_use",
                                     "tool": block.get("name", ""),
                                     "input": _summarize_tool_input(
                                         block.get("name", ""), block.get("input")
                                     ),
-                                }
+                                },
                             )
 
                         elif block.get("type") == "tool_result":
                             content = block.get("content", "")
                             if not isinstance(content, str):
                                 content = json.dumps(content)
-                            ap.events.append(
+                            self._push(
+                                ap,
                                 {
                                     "type": "tool_result",
                                     "text": content[:500],
                                     "is_error": block.get("is_error", False),
-                                }
+                                },
                             )
 
                 elif msg.get("type") == "result":
@@ -371,13 +386,13 @@ class CCManager:
                     if result_text:
                         remaining = result_text[len(current_partial):]
                         if remaining:
-                            ap.events.append({"type": "assistant_delta", "text": remaining})
+                            self._push(ap, {"type": "assistant_delta", "text": remaining})
                         assistant_text = result_text
                         ap.partial_text = result_text
 
         except Exception as exc:
             logger.exception("Error reading claude output for agent %s", agent_id)
-            ap.events.append({"type": "error", "text": str(exc)})
+            self._push(ap, {"type": "error", "text": str(exc)})
 
         # Wait for process exit and stderr drain
         await proc.wait()
@@ -389,17 +404,26 @@ class CCManager:
         elif stderr_buf.strip():
             err_msg = f"Error: {stderr_buf.strip()}"
             self._persist_history(agent_id, "error", err_msg)
-            ap.events.append({"type": "error", "text": err_msg})
+            self._push(ap, {"type": "error", "text": err_msg})
 
         ap.partial_text = ""
         ap.busy = False
         ap.proc = None
-        ap.events.append({"type": "done"})
+        self._push(ap, {"type": "done"})
 
         # Submit completed session to callback (e.g. session observer in Cairn)
         if assistant_text:
             self._submit_to_observer(agent_id)
 
+    def _push(self, ap: AgentProcess, event: dict[str, Any]) -> None:
+        """Buffer an event for polling and hand it to the agent's listener."""
+        ap.events.append(event)
+        if ap.on_event is not None:
+            try:
+                ap.on_event(len(ap.events) - 1, event)
+            except Exception:
+                logger.exception("cc event listener failed for agent %s", ap.agent_id)
+
     def _persist_history(self, agent_id: str, role: str, content: str) -> None:
         """Save a message to cc_history."""
         now = datetime.now(timezone.utc).isoformat()
@@ -3072,6 +3072,27 @@ def list_attachments(
     ]
 
 
+def get_attachment(attachment_id: str) -> dict[str, Any] | None:
+    """Get one attachment by ID, or None if it does not exist."""
+    conn = _get_connection()
+    row = conn.execute(
+        "SELECT * FROM attachments WHERE attachment_id = ?", (attachment_id,)
+    ).fetchone()
+    if row is None:
+        return None
+    return {
+        "attachment_id": row["attachment_id"],
+        "act_id": row["act_id"],
+        "scene_id": row["scene_id"],
+        "beat_id": row["scene_id"],  # backward compat
+        "page_id": row["page_id"],
+        "file_path": row["file_path"],
+        "file_name": row["file_name"],
+        "file_type": row["file_type"],
+        "added_at": row["added_at"],
+    }
+
+
 def add_attachment(
     *,
     act_id: str | None = None,
@@ -8,6 +8,7 @@ Seven methods for agent CRUD and session lifecycle:
 from __future__ import annotations
 
 import os
+from collections.abc import Callable
 from typing import Any
 
 from cairn.db import Database
@@ -126,11 +127,19 @@ def handle_cc_agents_delete(db: Database, *, agent_id: str) -> dict[str, Any]:
 
 
 async def handle_cc_session_send(
-    db: Database, *, agent_id: str, text: str
+    db: Database,
+    *,
+    agent_id: str,
+    text: str,
+    on_event: Callable[[int, dict[str, Any]], None] | None = None,
 ) -> dict[str, Any]:
-    """Send a message to an agent. Spawns a Claude Code process."""
+    """Send a message to an agent. Spawns a Claude Code process.
+
+    ``on_event`` is called with each event the session produces; see
+    ``CCManager.send_message``.
+    """
     mgr = _get_manager()
-    return await mgr.send_message(agent_id, text)
+    return await mgr.send_message(agent_id, text, on_event=on_event)
 
 
 def handle_cc_session_poll(
@@ -7,6 +7,7 @@ from __future__ import annotations
 
 import re
 import uuid
+from collections.abc import Callable
 from typing import Any
 
 from cairn.agent import ChatAgent
@@ -34,8 +35,12 @@ def handle_chat_respond(
     conversation_id: str | None = None,
     agent_type: str | None = None,
     extended_thinking: bool | None = None,
+    on_token: Callable[[str], None] | None = None,
 ) -> dict[str, Any]:
-    """Process a chat message and get AI response."""
+    """Process a chat message and get AI response.
+
+    ``on_token`` receives the answer chunk by chunk as it is generated.
+    """
     agent = ChatAgent(db=db)
 
     # Check for conversational intents (Phase 6)
@@ -98,6 +103,7 @@ def handle_chat_respond(
         conversation_id=conversation_id,
         agent_type=agent_type,
         extended_thinking=extended_thinking,
+        on_token=on_token,
     )
 
     # Fire turn assessment asynchronously — zero latency impact on the caller.
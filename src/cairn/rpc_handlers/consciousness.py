@@ -8,6 +8,7 @@ from __future__ import annotations
 
 import threading
 import uuid
+from collections.abc import Callable
 from dataclasses import dataclass
 from typing import Any
 
@@ -119,6 +120,7 @@ def handle_cairn_chat_async(
     text: str,
     conversation_id: str | None = None,
     extended_thinking: bool = False,
+    on_status: Callable[[dict[str, Any]], None] | None = None,
 ) -> dict[str, Any]:
     """Start CAIRN chat processing in background thread.
 
@@ -126,6 +128,8 @@ def handle_cairn_chat_async(
     while chat is processing, enabling real-time event streaming.
 
     Returns immediately with a chat_id that can be used to poll for status.
+    ``on_status``, if given, is called from the background thread with
+    ``{"chat_id", "status", "error"?}`` once the chat completes or fails.
     """
     from cairn.cairn.consciousness_stream import ConsciousnessObserver
 
@@ -160,6 +164,11 @@ def handle_cairn_chat_async(
         finally:
             # End consciousness session
             observer.end_session()
+        if on_status is not None:
+            status: dict[str, Any] = {"chat_id": chat_id, "status": "complete"}
+            if context.error:
+                status.update(status="error", error=context.error)
+            on_status(status)
 
     # Start background thread
     thread = threading.Thread(target=run_chat, daemon=True)
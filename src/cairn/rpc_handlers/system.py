@@ -157,6 +157,25 @@ def handle_thunderbird_reset(db: Database) -> dict[str, Any]:
     return {"success": True}
 
 
+# =============================================================================
+# Autostart Handlers
+# =============================================================================
+
+
+def handle_autostart_get(_db: Database) -> dict[str, Any]:
+    """Report whether Talking Rock starts on login."""
+    from cairn.autostart import get_autostart_status
+
+    return get_autostart_status()
+
+
+def handle_autostart_set(_db: Database, *, enabled: bool) -> dict[str, Any]:
+    """Turn starting Talking Rock on login on or off."""
+    from cairn.autostart import set_autostart
+
+    return set_autostart(enabled)
+
+
 # =============================================================================
 # CAIRN Attention Handler
 # =============================================================================
@@ -4,7 +4,8 @@ This is a small JSON-RPC 2.0 server over stdio intended to be used by a
 TypeScript desktop shell (Tauri).
 
 Design goals:
-- Local-only (stdio; no network listener).
+- Local-only (stdio, or with ``--socket`` a 0600 Unix socket that only
+  accepts peers running as the same user; no network listener).
 - Metadata-first by default.
 - Stable, explicit contract between UI and kernel.
 
@@ -15,15 +16,29 @@ catalog so the UI can reuse those capabilities.
 
 from __future__ import annotations
 
+import argparse
+import asyncio
+import contextvars
+import inspect
 import json
 import logging
+import mimetypes
+import os
+import queue
+import socket
+import stat
+import struct
 import sys
+import threading
 import uuid
-from typing import Any
+from pathlib import Path
+from typing import Any, BinaryIO, NamedTuple
 
 logger = logging.getLogger(__name__)
 
 from . import auth
+from .blobs import CHUNK_SIZE as BLOB_CHUNK_SIZE
+from .blobs import MAX_BLOB_BYTES, BlobError, BlobStore
 from .db import Database, get_db
 from .mcp_tools import ToolError, call_tool, list_tools
 from .rpc_handlers import RpcError
@@ -517,6 +532,12 @@ from .rpc_handlers.safety import (
     handle_safety_settings as _handle_safety_settings,
 )
 # System/Thunderbird RPC handlers (extracted to separate module)
+from .rpc_handlers.system import (
+    handle_autostart_get as _handle_autostart_get,
+)
+from .rpc_handlers.system import (
+    handle_autostart_set as _handle_autostart_set,
+)
 from .rpc_handlers.system import (
     handle_cairn_attention as _handle_cairn_attention,
 )
@@ -582,22 +603,435 @@ def _jsonrpc_result(*, req_id: Any, result: Any) -> _JSON:
     return {"jsonrpc": "2.0", "id": req_id, "result": result}
 
 
-def _readline() -> str | None:
-    line = sys.stdin.readline()
-    if not line:
-        return None
-    return line
+# Message framings, most preferred first. Both ends read either one: a
+# message that starts with a ``Content-Length`` header is a sized frame, any
+# other line is a whole message. What each end writes is settled in
+# ``initialize``, where the shell offers framings and the kernel picks one.
+FRAMINGS = ("content-length", "line")
+
+_CONTENT_LENGTH = b"content-length:"
+
+# Content type of a binary frame. With ``content-length`` framing the shell
+# sends upload chunks this way (see ``blobs.py``), and ``blob/fetch`` answers
+# with the bytes ahead of its JSON reply.
+OCTET_STREAM = "application/octet-stream"
+
+
+class BlobChunk(NamedTuple):
+    """Bytes for the upload ``blob_id``, read from a binary frame."""
+
+    blob_id: str
+    data: bytes
+
+
+def _read_message(stream: BinaryIO) -> str | BlobChunk | None:
+    """Next message on ``stream`` in either framing, or None at EOF."""
+    while True:
+        line = stream.readline()
+        if not line:
+            return None
+        if line[: len(_CONTENT_LENGTH)].lower() != _CONTENT_LENGTH:
+            return line.decode("utf-8", errors="replace")
+        try:
+            length = int(line[len(_CONTENT_LENGTH) :].strip())
+        except ValueError:
+            logger.warning("Ignoring malformed frame header %r", line)
+            continue
+        # Any further headers, up to the blank line that ends them.
+        headers: dict[str, str] = {}
+        while line.strip():
+            line = stream.readline()
+            if not line:
+                return None
+            name, sep, value = line.decode("latin-1").partition(":")
+            if sep:
+                headers[name.strip().lower()] = value.strip()
+        body = stream.read(length)
+        if headers.get("content-type", "").lower() == OCTET_STREAM:
+            blob_id = headers.get("blob-id")
+            if not blob_id:
+                logger.warning("Ignoring binary frame without a Blob-Id")
+                continue
+            return BlobChunk(blob_id, body)
+        return body.decode("utf-8", errors="replace")
+
+
+def _frame(obj: Any, framing: str) -> bytes:
+    body = json.dumps(obj, ensure_ascii=False).encode("utf-8")
+    if framing == "content-length":
+        return b"Content-Length: %d\r\n\r\n" % len(body) + body
+    return body + b"\n"
+
+
+# Serializes stdout writes so notifications sent from worker threads never
+# interleave with responses written by the main loop.
+_write_lock = threading.Lock()
+
+# Framing for messages written to stdout, chosen in ``initialize``.
+_stdout_framing = "line"
+
+
+class _SocketClient:
+    """One shell (or CLI, or test harness) connected over ``--socket``."""
+
+    def __init__(self, conn: socket.socket) -> None:
+        self.conn = conn
+        self._lock = threading.Lock()
+        # Chosen by this client's ``initialize``.
+        self.framing = "line"
+
+    def send(self, obj: Any) -> None:
+        self.send_raw(_frame(obj, self.framing))
+
+    def send_raw(self, data: bytes) -> None:
+        try:
+            with self._lock:
+                self.conn.sendall(data)
+        except OSError:
+            # Disconnected; its reader thread unregisters it.
+            pass
+
+
+# Client whose request is being handled, in socket mode. None over stdio.
+_current_client: contextvars.ContextVar[_SocketClient | None] = contextvars.ContextVar(
+    "_current_client", default=None
+)
+
+# Connected socket clients; None when serving over stdio.
+_socket_clients: set[_SocketClient] | None = None
+_socket_clients_lock = threading.Lock()
 
 
 def _write(obj: Any) -> None:
+    client = _current_client.get()
+    if client is not None:
+        client.send(obj)
+        return
     try:
-        sys.stdout.write(json.dumps(obj, ensure_ascii=False) + "\n")
-        sys.stdout.flush()
+        with _write_lock:
+            if _stdout_framing == "line":
+                sys.stdout.write(json.dumps(obj, ensure_ascii=False) + "\n")
+                sys.stdout.flush()
+            else:
+                # Anything print() left in the text buffer goes out first.
+                sys.stdout.flush()
+                sys.stdout.buffer.write(_frame(obj, _stdout_framing))
+                sys.stdout.buffer.flush()
     except BrokenPipeError:
         # Client closed the pipe (e.g., UI exited). Treat as a clean shutdown.
         raise SystemExit(0) from None
 
 
+def _current_framing() -> str:
+    """Framing negotiated with whoever sent the request being handled."""
+    client = _current_client.get()
+    return client.framing if client is not None else _stdout_framing
+
+
+def _write_bytes(req_id: int, data: bytes) -> None:
+    """Send raw bytes for request ``req_id`` ahead of its reply.
+
+    Only valid once ``content-length`` framing is in use; callers check
+    ``_current_framing`` first.
+    """
+    frame = (
+        b"Content-Length: %d\r\nContent-Type: %s\r\nRequest-Id: %d\r\n\r\n"
+        % (len(data), OCTET_STREAM.encode(), req_id)
+        + data
+    )
+    client = _current_client.get()
+    if client is not None:
+        client.send_raw(frame)
+        return
+    try:
+        with _write_lock:
+            sys.stdout.flush()
+            sys.stdout.buffer.write(frame)
+            sys.stdout.buffer.flush()
+    except BrokenPipeError:
+        raise SystemExit(0) from None
+
+
+def notify(method: str, params: Any | None = None) -> None:
+    """Push a JSON-RPC notification (no id) to the Rust shell.
+
+    The shell forwards it as a ``cairn://kernel/<method>`` event to every
+    window subscribed to the method. In socket mode every connected client
+    receives it. Safe to call from any thread.
+    """
+    msg: _JSON = {"jsonrpc": "2.0", "method": method}
+    if params is not None:
+        msg["params"] = params
+    if _socket_clients is None:
+        _write(msg)
+        return
+    with _socket_clients_lock:
+        clients = list(_socket_clients)
+    for client in clients:
+        client.send(msg)
+
+
+# Notifications for work that outlives the request that started it.
+# ``{"chat_id", "status", "error"?}`` when a ``cairn/chat_async`` chat ends.
+CHAT_STATUS_NOTIFICATION = "cairn/chat_status"
+# ``{"agent_id", "index", "event"}`` for each event of a Claude Code session,
+# as ``cc/session/poll`` would return it.
+CC_OUTPUT_NOTIFICATION = "cc/session/output"
+
+
+def _notify_chat_status(status: dict[str, Any]) -> None:
+    notify(CHAT_STATUS_NOTIFICATION, status)
+
+
+def _cc_output_notifier(agent_id: str) -> Callable[[int, dict[str, Any]], None]:
+    def relay(index: int, event: dict[str, Any]) -> None:
+        notify(CC_OUTPUT_NOTIFICATION, {"agent_id": agent_id, "index": index, "event": event})
+
+    return relay
+
+
+# Event loop for Claude Code sessions. Their output is read by tasks that
+# outlive cc/session/send, so the loop keeps running on its own thread.
+_cc_loop: asyncio.AbstractEventLoop | None = None
+_cc_loop_lock = threading.Lock()
+
+
+def _run_cc(coro: Coroutine[Any, Any, dict[str, Any]]) -> dict[str, Any]:
+    """Run ``coro`` on the Claude Code session loop and wait for its result."""
+    global _cc_loop
+    with _cc_loop_lock:
+        if _cc_loop is None:
+            _cc_loop = asyncio.new_event_loop()
+            threading.Thread(target=_cc_loop.run_forever, name="cc-loop", daemon=True).start()
+        loop = _cc_loop
+    return asyncio.run_coroutine_threadsafe(coro, loop).result()
+
+
+# Notification the shell sends to abandon a request: ``{"id": <request id>}``.
+CANCEL_METHOD = "rpc/cancel"
+
+# Error code for a request that stopped because it was cancelled.
+REQUEST_CANCELLED = -32800
+
+# Requests read but not yet answered, and those of them cancelled with
+# ``rpc/cancel``, keyed by (client, id); the client is None over stdio.
+# Reader threads add to both, the thread handling requests removes.
+_pending_requests: set[tuple[Any, Any]] = set()
+_cancelled_requests: set[tuple[Any, Any]] = set()
+_requests_lock = threading.Lock()
+
+# Id of the request being handled.
+_request_id: contextvars.ContextVar[Any] = contextvars.ContextVar("_request_id", default=None)
+
+
+def _track_request(client: Any, req: Any) -> bool:
+    """Note a request just read from ``client``, before it is queued.
+
+    An ``rpc/cancel`` takes effect here, on the reader thread, so it reaches
+    a request that is already running. Returns False for it: there is
+    nothing left to queue.
+    """
+    if isinstance(req, list):
+        # Batch items are cancelled one by one, by their own ids.
+        with _requests_lock:
+            _pending_requests.update(
+                (client, item["id"])
+                for item in req
+                if isinstance(item, dict) and item.get("id") is not None
+            )
+        return True
+    if req.get("method") == CANCEL_METHOD:
+        _cancel_request(client, req.get("params"))
+        return False
+    if req.get("id") is not None:
+        with _requests_lock:
+            _pending_requests.add((client, req["id"]))
+    return True
+
+
+def _cancel_request(client: Any, params: Any) -> None:
+    req_id = params.get("id") if isinstance(params, dict) else None
+    with _requests_lock:
+        if (client, req_id) in _pending_requests:
+            _cancelled_requests.add((client, req_id))
+
+
+def is_cancelled() -> bool:
+    """Whether the caller has cancelled the request being handled."""
+    key = (_current_client.get(), _request_id.get())
+    with _requests_lock:
+        return key in _cancelled_requests
+
+
+def check_cancelled() -> None:
+    """Stop the request being handled if its caller cancelled it.
+
+    Long-running handlers call this between steps; the error reply is
+    dropped by the shell, which has already given up on the request.
+    """
+    if is_cancelled():
+        raise RpcError(code=REQUEST_CANCELLED, message="Request cancelled")
+
+
+# Version of the shell <-> kernel protocol (envelope conventions such as
+# ``__session``, ``__stream`` and ``partial`` messages). The Rust shell
+# rejects kernels outside the range it supports.
+PROTOCOL_VERSION = 1
+
+# Optional protocol features this kernel implements, reported in the
+# ``initialize`` reply so the shell can adapt.
+KERNEL_CAPABILITIES = ("notifications", "streaming", "batch", "blobs")
+
+
+def _handle_initialize(params: Any) -> dict[str, Any]:
+    """Report what this kernel speaks and pick a framing from the offer.
+
+    The chosen framing applies to everything written to the caller from
+    here on, starting with this reply; the caller's reader accepts both.
+    """
+    global _stdout_framing
+    from . import __version__
+
+    offered = params.get("framing") if isinstance(params, dict) else None
+    if not isinstance(offered, list):
+        offered = ["line"]
+    framing = next((f for f in FRAMINGS if f in offered), "line")
+    client = _current_client.get()
+    if client is not None:
+        client.framing = framing
+    else:
+        _stdout_framing = framing
+
+    return {
+        "protocol_version": PROTOCOL_VERSION,
+        "kernel_version": __version__,
+        "capabilities": list(KERNEL_CAPABILITIES),
+        "framing": framing,
+    }
+
+
+# Id of the request being handled, when its caller asked for a streamed
+# response (``__stream: true`` in params). None otherwise.
+_stream_id: contextvars.ContextVar[Any] = contextvars.ContextVar("_stream_id", default=None)
+
+
+def _stream_target(req: dict[str, Any]) -> Any:
+    params = req.get("params")
+    if isinstance(params, dict) and params.get("__stream") is True:
+        return req.get("id")
+    return None
+
+
+def stream_partial(chunk: Any) -> bool:
+    """Send a partial result for the request currently being handled.
+
+    The Rust shell relays it to the caller's stream channel; the handler's
+    return value still becomes the final result. Returns False (and sends
+    nothing) when the caller did not request streaming.
+    """
+    req_id = _stream_id.get()
+    if req_id is None:
+        return False
+    _write({"jsonrpc": "2.0", "id": req_id, "partial": chunk})
+    return True
+
+
+def _token_streamer() -> Callable[[str], None] | None:
+    """Callback relaying generated tokens as ``{"delta": ...}`` partials.
+
+    It also stops generation once the request is cancelled. None when the
+    caller did not ask for a streamed response, so handlers keep their
+    non-streaming (and retrying) path.
+    """
+    if _stream_id.get() is None:
+        return None
+
+    def relay(token: str) -> None:
+        check_cancelled()
+        stream_partial({"delta": token})
+
+    return relay
+
+
+# Uploads streamed in by the shell (see ``blobs.py``).
+_blobs = BlobStore()
+
+
+def _require_binary_framing() -> None:
+    if _current_framing() != "content-length":
+        raise RpcError(code=-32000, message="binary transfers need content-length framing")
+
+
+def _uploaded_blob(blob_id: Any) -> Path:
+    """Path of the committed upload ``blob_id``, for methods that accept one."""
+    if not isinstance(blob_id, str) or not blob_id:
+        raise RpcError(code=-32602, message="blob_id must be a non-empty string")
+    path = _blobs.path(blob_id)
+    if path is None:
+        raise RpcError(code=-32602, message=f"unknown blob: {blob_id}")
+    return path
+
+
+def _session_token_param(params: Any) -> str | None:
+    """Session token named by an ``auth/*`` call, or None if unknown.
+
+    Takes ``session_token`` or ``session_ref``, the session's opaque name
+    from ``auth/login``, which is all the Rust shell keeps of it.
+    """
+    if not isinstance(params, dict):
+        raise RpcError(code=-32602, message="params must be an object")
+    session_token = params.get("session_token")
+    if isinstance(session_token, str) and session_token:
+        return session_token
+    session_ref = params.get("session_ref")
+    if isinstance(session_ref, str) and session_ref:
+        return auth.get_session_store().token_for_ref(session_ref)
+    raise RpcError(code=-32602, message="session_token or session_ref is required")
+
+
+def _handle_blob_fetch(req_id: Any, *, kind: str, object_id: str) -> dict[str, Any]:
+    """Send the bytes of an attachment, document or blob as binary frames.
+
+    The reply itself only describes what was sent.
+    """
+    _require_binary_framing()
+    if not isinstance(req_id, int):
+        raise RpcError(code=-32600, message="blob/fetch needs an integer request id")
+
+    path: Path | None = None
+    name: str | None = None
+    if kind == "attachment":
+        from .play_db import get_attachment
+
+        attachment = get_attachment(object_id)
+        if attachment is not None:
+            path = Path(attachment["file_path"])
+            name = attachment["file_name"]
+    elif kind == "document":
+        from .documents import get_original_file
+
+        path = get_original_file(object_id)
+    elif kind == "blob":
+        path = _blobs.path(object_id)
+    else:
+        raise RpcError(code=-32602, message=f"unknown kind: {kind}")
+    if path is None or not path.is_file():
+        raise RpcError(code=-32602, message=f"{kind} not found: {object_id}")
+
+    size = path.stat().st_size
+    if size > MAX_BLOB_BYTES:
+        raise RpcError(code=-32602, message=f"{kind} is larger than {MAX_BLOB_BYTES} bytes")
+    name = name or path.name
+    with path.open("rb") as f:
+        while chunk := f.read(BLOB_CHUNK_SIZE):
+            _write_bytes(req_id, chunk)
+    return {
+        "file_name": name,
+        "mime": mimetypes.guess_type(name)[0] or OCTET_STREAM,
+        "size": size,
+    }
+
+
 # -------------------------------------------------------------------------
 # Authentication handlers (PAM + session management)
 # -------------------------------------------------------------------------
@@ -642,15 +1076,18 @@ try:
     )
 
     _REOS_AVAILABLE = True
+    # Releases of reos that generate token by token take an ``on_token``.
+    _REOS_CONVERSE_STREAMS = "on_token" in inspect.signature(_handle_reos_converse).parameters
 except ImportError:
     _REOS_AVAILABLE = False
+    _REOS_CONVERSE_STREAMS = False
     logger.info("ReOS not installed — reos/* RPC handlers unavailable")
 
 # -------------------------------------------------------------------------
 # RPC Handler Registry - Simple handlers dispatched via lookup
 # -------------------------------------------------------------------------
 
-from collections.abc import Callable
+from collections.abc import Callable, Coroutine
 
 # Handlers with no params - just call handler(db)
 _SIMPLE_HANDLERS: dict[str, Callable[[Database], Any]] = {
@@ -663,6 +1100,7 @@ _SIMPLE_HANDLERS: dict[str, Callable[[Database], Any]] = {
     "cairn/thunderbird/status": _handle_cairn_thunderbird_status,
     "thunderbird/check": _handle_thunderbird_check,
     "thunderbird/reset": _handle_thunderbird_reset,
+    "autostart/get": _handle_autostart_get,
     "consciousness/start": _handle_consciousness_start,
     "consciousness/snapshot": _handle_consciousness_snapshot,
     "health/status": _handle_health_status,
@@ -715,10 +1153,20 @@ def _handle_jsonrpc_request(db: Database, req: dict[str, Any]) -> dict[str, Any]
         )
 
     try:
+        # Usually applied by the reader thread already (see _track_request);
+        # this covers cancels inside a batch.
+        if method == CANCEL_METHOD:
+            _cancel_request(_current_client.get(), params)
+            return None
+
         # Notifications can omit id; ignore.
         if req_id is None:
             return None
 
+        # Startup handshake from the Rust shell.
+        if method == "initialize":
+            return _jsonrpc_result(req_id=req_id, result=_handle_initialize(params))
+
         # Authentication methods (Polkit - native system dialog)
         if method == "auth/login":
             if not isinstance(params, dict):
@@ -747,30 +1195,26 @@ def _handle_jsonrpc_request(db: Database, req: dict[str, Any]) -> dict[str, Any]
             return _jsonrpc_result(req_id=req_id, result=result)
 
         if method == "auth/logout":
-            if not isinstance(params, dict):
-                raise RpcError(code=-32602, message="params must be an object")
-            session_token = params.get("session_token")
-            if not isinstance(session_token, str) or not session_token:
-                raise RpcError(code=-32602, message="session_token is required")
+            session_token = _session_token_param(params)
+            if session_token is None:
+                return _jsonrpc_result(
+                    req_id=req_id, result={"success": False, "error": "Session not found"}
+                )
             result = auth.logout(session_token)
             if result.get("success"):
                 audit_log(AuditEventType.AUTH_LOGOUT, {"session_id": session_token[:16]})
             return _jsonrpc_result(req_id=req_id, result=result)
 
         if method == "auth/validate":
-            if not isinstance(params, dict):
-                raise RpcError(code=-32602, message="params must be an object")
-            session_token = params.get("session_token")
-            if not isinstance(session_token, str) or not session_token:
-                raise RpcError(code=-32602, message="session_token is required")
+            session_token = _session_token_param(params)
+            if session_token is None:
+                return _jsonrpc_result(req_id=req_id, result={"valid": False})
             return _jsonrpc_result(req_id=req_id, result=auth.validate_session(session_token))
 
         if method == "auth/refresh":
-            if not isinstance(params, dict):
-                raise RpcError(code=-32602, message="params must be an object")
-            session_token = params.get("session_token")
-            if not isinstance(session_token, str) or not session_token:
-                raise RpcError(code=-32602, message="session_token is required")
+            session_token = _session_token_param(params)
+            if session_token is None:
+                return _jsonrpc_result(req_id=req_id, result={"success": False})
             refreshed = auth.refresh_session(session_token)
             return _jsonrpc_result(req_id=req_id, result={"success": refreshed})
 
@@ -862,6 +1306,9 @@ def _handle_jsonrpc_request(db: Database, req: dict[str, Any]) -> dict[str, Any]
             system_context = params.get("system_context", {})
             if not isinstance(turn_history, list):
                 raise RpcError(code=-32602, message="turn_history must be an array")
+            streaming: dict[str, Any] = {}
+            if _REOS_CONVERSE_STREAMS:
+                streaming["on_token"] = _token_streamer()
             return _jsonrpc_result(
                 req_id=req_id,
                 result=_handle_reos_converse(
@@ -870,6 +1317,7 @@ def _handle_jsonrpc_request(db: Database, req: dict[str, Any]) -> dict[str, Any]
                     conversation_id=conversation_id,
                     turn_history=turn_history,
                     system_context=system_context,
+                    **streaming,
                 ),
             )
 
@@ -919,6 +1367,7 @@ def _handle_jsonrpc_request(db: Database, req: dict[str, Any]) -> dict[str, Any]
                 text=text,
                 conversation_id=conversation_id,
                 extended_thinking=extended_thinking,
+                on_token=_token_streamer(),
             )
             return _jsonrpc_result(req_id=req_id, result=result)
 
@@ -936,6 +1385,7 @@ def _handle_jsonrpc_request(db: Database, req: dict[str, Any]) -> dict[str, Any]
                 text=text,
                 conversation_id=conversation_id,
                 extended_thinking=extended_thinking,
+                on_status=_notify_chat_status,
             )
             return _jsonrpc_result(req_id=req_id, result=result)
 
@@ -1347,6 +1797,9 @@ def _handle_jsonrpc_request(db: Database, req: dict[str, Any]) -> dict[str, Any]
             scene_id = params.get("scene_id")
             file_path = params.get("file_path")
             file_name = params.get("file_name")
+            if "blob_id" in params:
+                # Uploaded by the shell; the blob becomes the attachment's file.
+                file_path = str(_uploaded_blob(params["blob_id"]))
             if not isinstance(file_path, str) or not file_path:
                 raise RpcError(code=-32602, message="file_path is required")
             for k, v in {"act_id": act_id, "scene_id": scene_id, "file_name": file_name}.items():
@@ -2061,15 +2514,82 @@ def _handle_jsonrpc_request(db: Database, req: dict[str, Any]) -> dict[str, Any]
         if method == "documents/insert":
             if not isinstance(params, dict):
                 raise RpcError(code=-32602, message="params must be an object")
+            act_id = params.get("act_id")
+            if "blob_id" in params:
+                # Uploaded by the shell. The document store keeps its own
+                # copy, so the blob is dropped whatever the outcome.
+                blob_path = _uploaded_blob(params["blob_id"])
+                try:
+                    result = _handle_documents_insert(db, file_path=str(blob_path), act_id=act_id)
+                finally:
+                    _blobs.discard(params["blob_id"])
+                return _jsonrpc_result(req_id=req_id, result=result)
             file_path = params.get("file_path")
             if not isinstance(file_path, str) or not file_path:
                 raise RpcError(code=-32602, message="file_path is required")
-            act_id = params.get("act_id")
             return _jsonrpc_result(
                 req_id=req_id,
                 result=_handle_documents_insert(db, file_path=file_path, act_id=act_id),
             )
 
+        # --- Binary transfers (see blobs.py) ---
+
+        if method == "blob/open":
+            if not isinstance(params, dict):
+                raise RpcError(code=-32602, message="params must be an object")
+            _require_binary_framing()
+            name = params.get("name")
+            size = params.get("size")
+            if not isinstance(name, str) or not name:
+                raise RpcError(code=-32602, message="name is required")
+            if not isinstance(size, int) or isinstance(size, bool):
+                raise RpcError(code=-32602, message="size must be an integer")
+            try:
+                blob_id = _blobs.open(name, size)
+            except BlobError as exc:
+                raise RpcError(code=-32602, message=str(exc)) from exc
+            return _jsonrpc_result(
+                req_id=req_id, result={"blob_id": blob_id, "chunk_size": BLOB_CHUNK_SIZE}
+            )
+
+        if method == "blob/close":
+            if not isinstance(params, dict):
+                raise RpcError(code=-32602, message="params must be an object")
+            blob_id = params.get("blob_id")
+            sha256 = params.get("sha256")
+            if not isinstance(blob_id, str) or not blob_id:
+                raise RpcError(code=-32602, message="blob_id is required")
+            if not isinstance(sha256, str) or not sha256:
+                raise RpcError(code=-32602, message="sha256 is required")
+            try:
+                path = _blobs.close(blob_id, sha256)
+            except BlobError as exc:
+                raise RpcError(code=-32602, message=str(exc)) from exc
+            return _jsonrpc_result(
+                req_id=req_id,
+                result={"blob_id": blob_id, "file_name": path.name, "size": path.stat().st_size},
+            )
+
+        if method == "blob/abort":
+            if not isinstance(params, dict):
+                raise RpcError(code=-32602, message="params must be an object")
+            blob_id = params.get("blob_id")
+            if not isinstance(blob_id, str) or not blob_id:
+                raise RpcError(code=-32602, message="blob_id is required")
+            return _jsonrpc_result(req_id=req_id, result={"aborted": _blobs.abort(blob_id)})
+
+        if method == "blob/fetch":
+            if not isinstance(params, dict):
+                raise RpcError(code=-32602, message="params must be an object")
+            kind = params.get("kind")
+            object_id = params.get("id")
+            if not isinstance(kind, str) or not isinstance(object_id, str) or not object_id:
+                raise RpcError(code=-32602, message="kind and id are required")
+            return _jsonrpc_result(
+                req_id=req_id,
+                result=_handle_blob_fetch(req_id, kind=kind, object_id=object_id),
+            )
+
         if method == "documents/list":
             if not isinstance(params, dict):
                 params = {}
@@ -2797,6 +3317,14 @@ def _handle_jsonrpc_request(db: Database, req: dict[str, Any]) -> dict[str, Any]
         if method == "thunderbird/decline":
             return _jsonrpc_result(req_id=req_id, result=_handle_thunderbird_decline(db))
 
+        if method == "autostart/set":
+            if not isinstance(params, dict):
+                raise RpcError(code=-32602, message="params must be an object")
+            enabled = params.get("enabled")
+            if not isinstance(enabled, bool):
+                raise RpcError(code=-32602, message="enabled must be a boolean")
+            return _jsonrpc_result(req_id=req_id, result=_handle_autostart_set(db, enabled=enabled))
+
         if method == "cairn/attention":
             if not isinstance(params, dict):
                 params = {}
@@ -3125,12 +3653,13 @@ def _handle_jsonrpc_request(db: Database, req: dict[str, Any]) -> dict[str, Any]
                 raise RpcError(code=-32602, message="agent_id is required")
             if not isinstance(text, str) or not text.strip():
                 raise RpcError(code=-32602, message="text is required")
-            # NOTE: send is async but ui_rpc_server is sync stdio — caller should
-            # use the HTTP RPC path for async send. For Tauri, wrap in asyncio.run.
-            import asyncio as _aio
-
-            result = _aio.get_event_loop().run_until_complete(
-                _handle_cc_session_send(db, agent_id=agent_id, text=text)
+            result = _run_cc(
+                _handle_cc_session_send(
+                    db,
+                    agent_id=agent_id,
+                    text=text,
+                    on_event=_cc_output_notifier(agent_id),
+                )
             )
             return _jsonrpc_result(req_id=req_id, result=result)
 
@@ -3154,11 +3683,7 @@ def _handle_jsonrpc_request(db: Database, req: dict[str, Any]) -> dict[str, Any]
             agent_id = params.get("agent_id")
             if not isinstance(agent_id, str) or not agent_id:
                 raise RpcError(code=-32602, message="agent_id is required")
-            import asyncio as _aio
-
-            result = _aio.get_event_loop().run_until_complete(
-                _handle_cc_session_stop(db, agent_id=agent_id)
-            )
+            result = _run_cc(_handle_cc_session_stop(db, agent_id=agent_id))
             return _jsonrpc_result(req_id=req_id, result=result)
 
         if method == "cc/session/history":
@@ -3256,43 +3781,268 @@ def _load_persisted_safety_settings(db: Database) -> None:
             pass
 
 
-def run_stdio_server() -> None:
-    """Run the UI kernel server over stdio."""
-    print(
-        "[ui_rpc_server] ========== PYTHON BACKEND STARTING ==========", file=sys.stderr, flush=True
-    )
-
+def _start_backend() -> Database:
     db = get_db()
     db.migrate()
 
     # Load persisted safety settings
     _load_persisted_safety_settings(db)
     print("[ui_rpc_server] Backend ready, waiting for requests...", file=sys.stderr, flush=True)
+    return db
 
-    while True:
-        line = _readline()
-        if line is None:
-            return
 
-        line = line.strip()
-        if not line:
+def _parse_request(line: str) -> dict[str, Any] | list[Any] | None:
+    """A single request object, or a JSON-RPC batch (a list)."""
+    line = line.strip()
+    if not line:
+        return None
+    try:
+        req = json.loads(line)
+    except json.JSONDecodeError:
+        return None
+    return req if isinstance(req, (dict, list)) else None
+
+
+def _handle_batch(db: Database, batch: list[Any]) -> list[dict[str, Any]] | dict[str, Any] | None:
+    """Handle a JSON-RPC 2.0 batch in order and collect the responses.
+
+    Each item is handled like a single request, so it can be cancelled on
+    its own, but is not streamed even if it asks to be. Returns None when
+    every item was a notification, so nothing is written back.
+    """
+    if not batch:
+        return _jsonrpc_error(req_id=None, code=-32600, message="Invalid Request: empty batch")
+    responses: list[dict[str, Any]] = []
+    for item in batch:
+        if not isinstance(item, dict):
+            responses.append(_jsonrpc_error(req_id=None, code=-32600, message="Invalid Request"))
             continue
+        resp = _handle_tracked_request(db, item, streamed=False)
+        if resp is not None:
+            responses.append(resp)
+    return responses or None
+
+
+def _handle_tracked_request(
+    db: Database, req: dict[str, Any], *, streamed: bool = True
+) -> dict[str, Any] | None:
+    """Handle one request with its id in context for ``check_cancelled()``.
+
+    A request cancelled before it starts is answered without running. Once
+    handled it is no longer pending, so a late ``rpc/cancel`` is ignored.
+    """
+    stream_token = _stream_id.set(_stream_target(req) if streamed else None)
+    request_token = _request_id.set(req.get("id"))
+    key = (_current_client.get(), req.get("id"))
+    try:
+        if is_cancelled():
+            return _jsonrpc_error(
+                req_id=req.get("id"), code=REQUEST_CANCELLED, message="Request cancelled"
+            )
+        return _handle_jsonrpc_request(db, req)
+    finally:
+        _request_id.reset(request_token)
+        _stream_id.reset(stream_token)
+        with _requests_lock:
+            _pending_requests.discard(key)
+            _cancelled_requests.discard(key)
+
 
+def _dispatch(db: Database, req: dict[str, Any] | list[Any]) -> None:
+    if isinstance(req, list):
+        resp = _handle_batch(db, req)
+    else:
+        resp = _handle_tracked_request(db, req)
+    if resp is not None:
+        _write(resp)
+
+
+def run_stdio_server() -> None:
+    """Run the UI kernel server over stdio."""
+    print(
+        "[ui_rpc_server] ========== PYTHON BACKEND STARTING ==========", file=sys.stderr, flush=True
+    )
+
+    db = _start_backend()
+
+    # Requests are handled one at a time on this thread; stdin is read on
+    # another so an ``rpc/cancel`` reaches the request it names while that
+    # request is still running.
+    requests: queue.Queue = queue.Queue()
+    stdin = sys.stdin.buffer
+
+    def read_stdin() -> None:
         try:
-            req = json.loads(line)
-        except json.JSONDecodeError:
+            while (message := _read_message(stdin)) is not None:
+                if isinstance(message, BlobChunk):
+                    requests.put(message)
+                    continue
+                req = _parse_request(message)
+                if req is not None and _track_request(None, req):
+                    requests.put(req)
+        finally:
+            requests.put(None)
+
+    threading.Thread(target=read_stdin, name="rpc-stdin", daemon=True).start()
+    while True:
+        item = requests.get()
+        if item is None:
+            return
+        if isinstance(item, BlobChunk):
+            _blobs.append(item.blob_id, item.data)
             continue
+        _dispatch(db, item)
 
-        if not isinstance(req, dict):
-            continue
 
-        resp = _handle_jsonrpc_request(db, req)
-        if resp is not None:
-            _write(resp)
+def _peer_uid(conn: socket.socket) -> int | None:
+    """uid of the connecting process, or None if the platform can't tell."""
+    if hasattr(socket, "SO_PEERCRED"):
+        creds = conn.getsockopt(socket.SOL_SOCKET, socket.SO_PEERCRED, struct.calcsize("3i"))
+        _pid, uid, _gid = struct.unpack("3i", creds)
+        return uid
+    return None
+
+
+def _bind_private_socket(path: str) -> socket.socket:
+    """Listen on ``path`` with mode 0600 inside a 0700 directory we own."""
+    directory = os.path.dirname(path) or "."
+    os.makedirs(directory, mode=0o700, exist_ok=True)
+    st = os.stat(directory)
+    if st.st_uid != os.getuid() or st.st_mode & 0o077:
+        raise SystemExit(f"refusing socket dir {directory}: not private to this user")
+
+    # A socket file left by a kernel that died without cleaning up.
+    try:
+        if stat.S_ISSOCK(os.lstat(path).st_mode):
+            probe = socket.socket(socket.AF_UNIX, socket.SOCK_STREAM)
+            try:
+                probe.connect(path)
+            except ConnectionRefusedError:
+                os.unlink(path)
+            else:
+                raise SystemExit(f"another kernel is already listening on {path}")
+            finally:
+                probe.close()
+    except FileNotFoundError:
+        pass
+
+    listener = socket.socket(socket.AF_UNIX, socket.SOCK_STREAM)
+    old_umask = os.umask(0o177)
+    try:
+        listener.bind(path)
+    finally:
+        os.umask(old_umask)
+    os.chmod(path, 0o600)
+    listener.listen()
+    return listener
+
+
+def _read_client(client: _SocketClient, requests: queue.Queue) -> None:
+    try:
+        with client.conn.makefile("rb") as stream:
+            while (message := _read_message(stream)) is not None:
+                if isinstance(message, BlobChunk):
+                    # Queued like a request so it lands after the blob/open
+                    # that precedes it and before the blob/close that follows.
+                    requests.put((client, message))
+                    continue
+                req = _parse_request(message)
+                if req is not None and _track_request(client, req):
+                    requests.put((client, req))
+    except OSError:
+        pass
+    finally:
+        with _socket_clients_lock:
+            if _socket_clients is not None:
+                _socket_clients.discard(client)
+        client.conn.close()
+
+
+def run_socket_server(path: str, *, exit_with_parent: bool = False) -> None:
+    """Run the UI kernel server on a Unix socket shared by several clients.
+
+    Each client gets its own reader thread; requests from all of them are
+    handled one at a time on this thread, as over stdio, and each response
+    goes back to the client that asked. Notifications go to every client.
+
+    With ``exit_with_parent`` the server exits when stdin closes, so the
+    shell that spawned it can stop it the same way it stops a stdio kernel.
+    """
+    global _socket_clients
+
+    print(
+        "[ui_rpc_server] ========== PYTHON BACKEND STARTING ==========", file=sys.stderr, flush=True
+    )
+    db = _start_backend()
+
+    listener = _bind_private_socket(path)
+    _socket_clients = set()
+    requests: queue.Queue = queue.Queue()
+    print(f"[ui_rpc_server] Listening on {path}", file=sys.stderr, flush=True)
+
+    def accept_loop() -> None:
+        while True:
+            try:
+                conn, _ = listener.accept()
+            except OSError:
+                return
+            uid = _peer_uid(conn)
+            if uid is not None and uid != os.getuid():
+                logger.warning("Rejected kernel socket peer with uid %s", uid)
+                conn.close()
+                continue
+            client = _SocketClient(conn)
+            with _socket_clients_lock:
+                _socket_clients.add(client)
+            threading.Thread(
+                target=_read_client, args=(client, requests), name="rpc-client", daemon=True
+            ).start()
+
+    def watch_parent() -> None:
+        while sys.stdin.readline():
+            pass
+        requests.put(None)
+
+    threading.Thread(target=accept_loop, name="rpc-accept", daemon=True).start()
+    if exit_with_parent:
+        threading.Thread(target=watch_parent, name="rpc-parent", daemon=True).start()
+
+    try:
+        while True:
+            item = requests.get()
+            if item is None:
+                return
+            client, req = item
+            if isinstance(req, BlobChunk):
+                _blobs.append(req.blob_id, req.data)
+                continue
+            client_token = _current_client.set(client)
+            try:
+                _dispatch(db, req)
+            finally:
+                _current_client.reset(client_token)
+    finally:
+        listener.close()
+        try:
+            os.unlink(path)
+        except OSError:
+            pass
 
 
 def main() -> None:
-    run_stdio_server()
+    parser = argparse.ArgumentParser(prog="cairn.ui_rpc_server")
+    parser.add_argument("--socket", help="serve on this Unix socket instead of stdio")
+    parser.add_argument(
+        "--exit-with-parent",
+        action="store_true",
+        help="with --socket, exit when stdin closes",
+    )
+    args = parser.parse_args()
+
+    if args.socket:
+        run_socket_server(args.socket, exit_with_parent=args.exit_with_parent)
+    else:
+        run_stdio_server()
 
 
 if __name__ == "__main__":
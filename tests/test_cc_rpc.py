@@ -226,7 +226,7 @@ class TestHandleCcSessionSend:
         with patch("cairn.rpc_handlers.cc._get_manager", return_value=mgr):
             run(handle_cc_session_send(_mock_db(), agent_id="agent-id-1", text="do something"))
 
-        mgr.send_message.assert_awaited_once_with("agent-id-1", "do something")
+        mgr.send_message.assert_awaited_once_with("agent-id-1", "do something", on_event=None)
 
     def test_propagates_rpc_error_for_missing_agent(self) -> None:
         from cairn.rpc_handlers import RpcError
@@ -307,6 +307,46 @@ class TestPlayHandlers:
         )
 
 
+class TestAutostartHandlers:
+    """Test the autostart/* handlers behind Settings > Start on Login."""
+
+    def test_autostart_set_writes_and_removes_entry(
+        self, db: Database, tmp_path: Path, monkeypatch: pytest.MonkeyPatch
+    ) -> None:
+        """autostart/set toggles the XDG autostart entry autostart/get reports."""
+        from cairn.ui_rpc_server import _handle_jsonrpc_request
+
+        monkeypatch.setenv("XDG_CONFIG_HOME", str(tmp_path / "config"))
+        session = {"username": "u", "session_id": "s"}
+
+        def call(method: str, params: dict[str, Any]) -> dict[str, Any]:
+            req = {
+                "jsonrpc": "2.0",
+                "id": 1,
+                "method": method,
+                "params": {**params, "__session": session},
+            }
+            result = _handle_jsonrpc_request(db, req)
+            assert result is not None
+            return result
+
+        status = call("autostart/get", {})["result"]
+        assert status["enabled"] is False
+        entry = Path(status["desktop_file"])
+        assert entry.parent == tmp_path / "config" / "autostart"
+
+        assert call("autostart/set", {"enabled": True})["result"] == {
+            "success": True,
+            "enabled": True,
+        }
+        assert call("autostart/get", {})["result"]["enabled"] is True
+        assert f'Exec="{status["cairn_executable"]}"' in entry.read_text()
+
+        assert call("autostart/set", {"enabled": False})["result"]["success"] is True
+        assert not entry.exists()
+        assert call("autostart/set", {"enabled": "yes"})["error"]["code"] == -32602
+
+
 class TestCodeExecutionHandlers:
     """Test code execution (RIVA) RPC handlers."""
 
@@ -411,6 +451,83 @@ class TestSessionEnforcement:
         # Should reach the handler, not be blocked by session check
         assert result["error"]["code"] != -32003 if "error" in result else True
 
+    def test_auth_methods_accept_session_ref(self, db: Database) -> None:
+        """The shell names sessions by the ref issued at login."""
+        from datetime import UTC, datetime
+
+        from cairn import auth
+        from cairn.ui_rpc_server import _handle_jsonrpc_request
+
+        now = datetime.now(UTC)
+        session = auth.Session(
+            token=auth.generate_session_token(),
+            username="testuser",
+            created_at=now,
+            last_activity=now,
+            key_material=bytes(32),
+        )
+        auth.get_session_store().insert(session)
+        ref = session.ref
+        assert ref != session.token
+
+        def call(method: str, params: dict[str, Any]) -> dict[str, Any]:
+            req = {"jsonrpc": "2.0", "id": 4, "method": method, "params": params}
+            result = _handle_jsonrpc_request(db, req)
+            assert result is not None
+            return result
+
+        assert call("auth/refresh", {"session_ref": ref})["result"]["success"] is True
+        assert call("auth/validate", {"session_ref": ref})["result"]["valid"] is True
+        assert call("auth/logout", {"session_ref": ref})["result"]["success"] is True
+        assert call("auth/validate", {"session_ref": ref})["result"]["valid"] is False
+        assert call("auth/validate", {})["error"]["code"] == -32602
+        assert auth.get_session_store().token_for_ref(ref) is None
+
+
+class TestInitializeHandshake:
+    """Test the startup handshake the Rust shell performs."""
+
+    def test_initialize_reports_protocol_and_capabilities(self, db: Database) -> None:
+        """initialize needs no session and reports what the kernel speaks."""
+        from cairn import __version__
+        from cairn.ui_rpc_server import PROTOCOL_VERSION, _handle_jsonrpc_request
+
+        req = {
+            "jsonrpc": "2.0",
+            "id": 1,
+            "method": "initialize",
+            "params": {"protocol_version": 1, "client": "cairn-tauri"},
+        }
+        result = _handle_jsonrpc_request(db, req)
+
+        assert result is not None
+        assert "error" not in result
+        assert result["result"]["protocol_version"] == PROTOCOL_VERSION
+        assert result["result"]["kernel_version"] == __version__
+        assert "notifications" in result["result"]["capabilities"]
+
+    def test_batch_answers_in_one_array(self, db: Database) -> None:
+        """A batch gets one array of responses; bad items get their own error."""
+        from cairn.ui_rpc_server import _dispatch
+
+        batch = [
+            {"jsonrpc": "2.0", "id": 1, "method": "initialize"},
+            "not a request",
+            {"jsonrpc": "2.0", "method": "initialize"},
+            {"jsonrpc": "2.0", "id": 2, "method": "no/such/method"},
+        ]
+        output = StringIO()
+        with patch("sys.stdout", output):
+            _dispatch(db, batch)
+
+        lines = output.getvalue().splitlines()
+        assert len(lines) == 1
+        responses = json.loads(lines[0])
+        assert [r["id"] for r in responses] == [1, None, 2]
+        assert "result" in responses[0]
+        assert responses[1]["error"]["code"] == -32600
+        assert "error" in responses[2]
+
 
 class TestJsonRpcProtocol:
     """Test JSON-RPC 2.0 protocol compliance."""
@@ -506,3 +623,438 @@ class TestStdioProtocol:
 
         # Should exit with code 0 (clean shutdown)
         assert exc_info.value.code == 0
+
+
+class TestFraming:
+    """Test Content-Length framing negotiated in initialize."""
+
+    def test_reads_lines_and_frames(self) -> None:
+        """Either framing is accepted on input, whatever was negotiated."""
+        from io import BytesIO
+
+        from cairn.ui_rpc_server import _read_message
+
+        body = '{"id": 2, "params": {"text": "a\\nb \u00e9"}}'.encode()
+        stream = BytesIO(
+            b'{"id": 1}\n'
+            + b"Content-Length: %d\r\n\r\n" % len(body)
+            + body
+        )
+
+        assert json.loads(_read_message(stream))["id"] == 1
+        assert json.loads(_read_message(stream))["params"]["text"] == "a\nb \u00e9"
+        assert _read_message(stream) is None
+
+    def test_initialize_switches_stdout_framing(self, db: Database) -> None:
+        """Once content-length is picked, replies go out as sized frames."""
+        from io import BytesIO, TextIOWrapper
+
+        import cairn.ui_rpc_server as server
+
+        req = {
+            "jsonrpc": "2.0",
+            "id": 1,
+            "method": "initialize",
+            "params": {"protocol_version": 1, "framing": ["content-length", "line"]},
+        }
+        raw = BytesIO()
+        stdout = TextIOWrapper(raw, encoding="utf-8")
+        try:
+            with patch("sys.stdout", stdout):
+                server._dispatch(db, req)
+        finally:
+            server._stdout_framing = "line"
+
+        header, _, body = raw.getvalue().partition(b"\r\n\r\n")
+        assert header == b"Content-Length: %d" % len(body)
+        assert json.loads(body)["result"]["framing"] == "content-length"
+
+
+class TestStreaming:
+    """Test partial results sent ahead of a streamed request's final reply."""
+
+    def test_chat_tokens_arrive_before_the_result(self, db: Database) -> None:
+        """chat/respond relays generated tokens as partials when asked to."""
+        from types import SimpleNamespace
+
+        import cairn.ui_rpc_server as server
+
+        class FakeAgent:
+            def __init__(self, db: Database) -> None:
+                pass
+
+            def respond(self, text: str, *, on_token: Any = None, **_: Any) -> Any:
+                for token in ("Hel", "lo"):
+                    if on_token is not None:
+                        on_token(token)
+                return SimpleNamespace(
+                    answer="Hello",
+                    conversation_id="conv-1",
+                    message_id="msg-1",
+                    message_type="text",
+                    tool_calls=[],
+                    thinking_steps=[],
+                    pending_approval_id=None,
+                    extended_thinking_trace=None,
+                    user_message_id="msg-0",
+                )
+
+        def chat(req_id: int, stream: bool) -> list[dict[str, Any]]:
+            params = {"text": "hi", "__session": {"username": "u", "session_id": "s"}}
+            if stream:
+                params["__stream"] = True
+            written: list[dict[str, Any]] = []
+            with (
+                patch("cairn.rpc_handlers.chat.ChatAgent", FakeAgent),
+                patch.object(server, "_write", written.append),
+            ):
+                server._dispatch(db, {"jsonrpc": "2.0", "id": req_id, "method": "chat/respond", "params": params})
+            return written
+
+        written = chat(7, stream=True)
+        assert [m.get("partial") for m in written] == [{"delta": "Hel"}, {"delta": "lo"}, None]
+        assert all(m["id"] == 7 for m in written)
+        assert written[-1]["result"]["answer"] == "Hello"
+
+        # Without __stream only the final reply is sent.
+        written = chat(8, stream=False)
+        assert len(written) == 1
+        assert written[0]["result"]["answer"] == "Hello"
+
+
+class TestNotifications:
+    """Test notifications pushed for work that outlives its request."""
+
+    @staticmethod
+    def _wait_for(written: list[dict[str, Any]], done: Any) -> list[dict[str, Any]]:
+        import time
+
+        deadline = time.monotonic() + 5
+        while not any(done(m) for m in list(written)):
+            assert time.monotonic() < deadline, written
+            time.sleep(0.01)
+        return [m for m in written if "id" not in m]
+
+    def test_chat_status_arrives_when_the_chat_ends(self, db: Database) -> None:
+        """cairn/chat_async announces completion instead of waiting to be polled."""
+        import cairn.ui_rpc_server as server
+
+        written: list[dict[str, Any]] = []
+        params = {"text": "hi", "__session": {"username": "u", "session_id": "s"}}
+        req = {"jsonrpc": "2.0", "id": 3, "method": "cairn/chat_async", "params": params}
+        with (
+            patch(
+                "cairn.rpc_handlers.consciousness.handle_chat_respond",
+                return_value={"answer": "Hello"},
+            ),
+            patch.object(server, "_write", written.append),
+        ):
+            server._dispatch(db, req)
+            notes = self._wait_for(written, lambda m: m.get("method") == "cairn/chat_status")
+
+        (reply,) = (m for m in written if m.get("id") == 3)
+        chat_id = reply["result"]["chat_id"]
+        assert notes == [
+            {
+                "jsonrpc": "2.0",
+                "method": "cairn/chat_status",
+                "params": {"chat_id": chat_id, "status": "complete"},
+            }
+        ]
+
+    def test_cc_session_output_arrives_as_it_is_read(self, db: Database) -> None:
+        """Every event a Claude Code session buffers is also pushed."""
+        import cairn.ui_rpc_server as server
+        from cairn.cc_manager import CCManager
+
+        lines = [
+            json.dumps({"type": "result", "result": "Done.", "session_id": "s-1"}).encode() + b"\n"
+        ]
+
+        class FakeStream:
+            def __init__(self, lines: list[bytes]) -> None:
+                self.lines = lines
+
+            async def readline(self) -> bytes:
+                return self.lines.pop(0) if self.lines else b""
+
+            async def read(self, _n: int) -> bytes:
+                return b""
+
+        class FakeProc:
+            returncode = 0
+
+            def __init__(self) -> None:
+                self.stdout = FakeStream(lines)
+                self.stderr = FakeStream([])
+
+            async def wait(self) -> int:
+                return 0
+
+        async def spawn(*_args: Any, **_kwargs: Any) -> FakeProc:
+            return FakeProc()
+
+        cc_db = MagicMock()
+        cc_db.get_connection.return_value.execute.return_value.fetchone.return_value = {
+            "id": "agent-1",
+            "cwd": "/tmp",
+            "session_id": None,
+        }
+        manager = CCManager(db=cc_db)
+        written: list[dict[str, Any]] = []
+        params = {
+            "agent_id": "agent-1",
+            "text": "build it",
+            "__session": {"username": "u", "session_id": "s"},
+        }
+        with (
+            patch("cairn.rpc_handlers.cc._get_manager", return_value=manager),
+            patch("asyncio.create_subprocess_exec", spawn),
+            patch.object(server, "_write", written.append),
+        ):
+            server._dispatch(
+                db, {"jsonrpc": "2.0", "id": 5, "method": "cc/session/send", "params": params}
+            )
+            notes = self._wait_for(
+                written, lambda m: m.get("params", {}).get("event", {}).get("type") == "done"
+            )
+
+        (reply,) = (m for m in written if m.get("id") == 5)
+        assert reply["result"]["status"] == "accepted"
+        assert {n["method"] for n in notes} == {"cc/session/output"}
+        assert [n["params"]["index"] for n in notes] == [0, 1, 2]
+        assert [n["params"]["event"] for n in notes] == [
+            {"type": "user", "text": "build it"},
+            {"type": "assistant_delta", "text": "Done."},
+            {"type": "done"},
+        ]
+        assert all(n["params"]["agent_id"] == "agent-1" for n in notes)
+
+
+class TestCancellation:
+    """Test rpc/cancel stopping a request while it runs."""
+
+    def test_cancel_stops_a_running_chat(self, db: Database) -> None:
+        """A cancel read while chat/respond generates ends the generation."""
+        import os
+        import threading
+        from types import SimpleNamespace
+
+        import cairn.ui_rpc_server as server
+
+        started = threading.Event()
+        generated: list[str] = []
+
+        class SlowAgent:
+            def __init__(self, db: Database) -> None:
+                pass
+
+            def respond(self, text: str, *, on_token: Any = None, **_: Any) -> Any:
+                for _ in range(500):
+                    on_token("tok")
+                    generated.append("tok")
+                    started.set()
+                    threading.Event().wait(0.01)
+                raise AssertionError("generation was not cancelled")
+
+        read_fd, write_fd = os.pipe()
+        stdin = SimpleNamespace(buffer=os.fdopen(read_fd, "rb"))
+        session = {"username": "u", "session_id": "s"}
+        request = {
+            "jsonrpc": "2.0",
+            "id": 9,
+            "method": "chat/respond",
+            "params": {"text": "hi", "__stream": True, "__session": session},
+        }
+        written: list[dict[str, Any]] = []
+
+        def send_cancel() -> None:
+            with os.fdopen(write_fd, "wb") as pipe:
+                pipe.write(json.dumps(request).encode() + b"\n")
+                pipe.flush()
+                assert started.wait(5)
+                cancel = {"jsonrpc": "2.0", "method": "rpc/cancel", "params": {"id": 9}}
+                pipe.write(json.dumps(cancel).encode() + b"\n")
+
+        with (
+            patch("sys.stdin", stdin),
+            patch.object(server, "_start_backend", return_value=db),
+            patch.object(server, "_write", written.append),
+            patch("cairn.rpc_handlers.chat.ChatAgent", SlowAgent),
+        ):
+            sender = threading.Thread(target=send_cancel)
+            sender.start()
+            server.run_stdio_server()
+            sender.join()
+
+        assert 0 < len(generated) < 500
+        final = written[-1]
+        assert final["id"] == 9
+        assert final["error"]["code"] == server.REQUEST_CANCELLED
+        assert not server._pending_requests and not server._cancelled_requests
+
+    def test_cancel_for_a_queued_request_skips_it(self, db: Database) -> None:
+        """A request cancelled before it starts is answered without running."""
+        import cairn.ui_rpc_server as server
+
+        req = {"jsonrpc": "2.0", "id": 3, "method": "ping", "params": {}}
+        cancel = {"jsonrpc": "2.0", "method": "rpc/cancel", "params": {"id": 3}}
+        assert server._track_request(None, req)
+        assert not server._track_request(None, cancel)
+        # Unknown ids are ignored rather than remembered.
+        server._cancel_request(None, {"id": 4})
+
+        written: list[dict[str, Any]] = []
+        with patch.object(server, "_write", written.append):
+            server._dispatch(db, req)
+        assert written == [
+            {"jsonrpc": "2.0", "id": 3, "error": {"code": -32800, "message": "Request cancelled"}}
+        ]
+        assert not server._cancelled_requests
+
+    def test_cancel_reaches_batch_items(self, db: Database) -> None:
+        """Batch items are tracked, run with their own id, and cancelled by it."""
+        import cairn.ui_rpc_server as server
+
+        batch = [
+            {"jsonrpc": "2.0", "id": 5, "method": "initialize"},
+            {"jsonrpc": "2.0", "id": 6, "method": "initialize"},
+        ]
+        assert server._track_request(None, batch)
+        server._cancel_request(None, {"id": 6})
+
+        handle = server._handle_jsonrpc_request
+        seen_ids: list[Any] = []
+
+        def spy(db: Database, req: dict[str, Any]) -> Any:
+            seen_ids.append(server._request_id.get())
+            return handle(db, req)
+
+        written: list[Any] = []
+        with (
+            patch.object(server, "_handle_jsonrpc_request", spy),
+            patch.object(server, "_write", written.append),
+        ):
+            server._dispatch(db, batch)
+        assert seen_ids == [5]
+        ((ran, skipped),) = written
+        assert ran["id"] == 5 and "result" in ran
+        assert skipped == {
+            "jsonrpc": "2.0",
+            "id": 6,
+            "error": {"code": -32800, "message": "Request cancelled"},
+        }
+        assert not server._pending_requests and not server._cancelled_requests
+
+
+class TestBlobTransfer:
+    """Test binary uploads into the blob store and blob/fetch replies."""
+
+    def test_upload_chunks_are_checked_on_close(self, tmp_path: Path) -> None:
+        """Chunks read from binary frames must add up to the announced file."""
+        import hashlib
+        from io import BytesIO
+
+        from cairn.blobs import BlobError, BlobStore
+        from cairn.ui_rpc_server import BlobChunk, _read_message
+
+        store = BlobStore(tmp_path / "blobs")
+        data = b"\x00line one\nline two\xff"
+        blob_id = store.open("../notes.txt", len(data))
+        stream = BytesIO(
+            b"Content-Length: %d\r\nContent-Type: application/octet-stream\r\n"
+            b"Blob-Id: %s\r\n\r\n" % (len(data), blob_id.encode())
+            + data
+        )
+        chunk = _read_message(stream)
+        assert chunk == BlobChunk(blob_id, data)
+        store.append(chunk.blob_id, chunk.data[:5])
+        store.append(chunk.blob_id, chunk.data[5:])
+
+        path = store.close(blob_id, hashlib.sha256(data).hexdigest())
+        assert path == tmp_path / "blobs" / blob_id / "notes.txt"
+        assert path.read_bytes() == data
+        assert store.path(blob_id) == path
+
+        short = store.open("short.bin", 10)
+        store.append(short, b"abc")
+        with pytest.raises(BlobError):
+            store.close(short, hashlib.sha256(b"abc").hexdigest())
+        assert store.path(short) is None
+
+    def test_fetch_sends_bytes_before_reply(self, db: Database, tmp_path: Path) -> None:
+        """blob/fetch writes binary frames tagged with the request id."""
+        import hashlib
+        from io import BytesIO, TextIOWrapper
+
+        import cairn.ui_rpc_server as server
+        from cairn.blobs import BlobStore
+
+        store = BlobStore(tmp_path / "blobs")
+        data = b"%PDF-1.7 \x00\x01"
+        blob_id = store.open("report.pdf", len(data))
+        store.append(blob_id, data)
+        store.close(blob_id, hashlib.sha256(data).hexdigest())
+
+        req = {
+            "jsonrpc": "2.0",
+            "id": 9,
+            "method": "blob/fetch",
+            "params": {"kind": "blob", "id": blob_id, "__session": {"username": "u"}},
+        }
+        raw = BytesIO()
+        stdout = TextIOWrapper(raw, encoding="utf-8")
+        server._stdout_framing = "content-length"
+        try:
+            with patch("sys.stdout", stdout), patch.object(server, "_blobs", store):
+                server._dispatch(db, req)
+        finally:
+            server._stdout_framing = "line"
+
+        raw.seek(0)
+        header, _, rest = raw.getvalue().partition(b"\r\n\r\n")
+        assert b"Content-Type: application/octet-stream" in header
+        assert b"Request-Id: 9" in header
+        assert rest[: len(data)] == data
+        _, _, body = rest[len(data) :].partition(b"\r\n\r\n")
+        result = json.loads(body)["result"]
+        assert result == {"file_name": "report.pdf", "mime": "application/pdf", "size": len(data)}
+
+
+class TestSocketTransport:
+    """Test the Unix socket mode used to share one kernel between clients."""
+
+    def test_socket_is_private(self, tmp_path: Path) -> None:
+        """The socket is 0600 inside a 0700 directory."""
+        import os
+        import stat
+
+        from cairn.ui_rpc_server import _bind_private_socket
+
+        path = tmp_path / "run" / "kernel.sock"
+        listener = _bind_private_socket(str(path))
+        try:
+            assert stat.S_IMODE(os.stat(path).st_mode) == 0o600
+            assert stat.S_IMODE(os.stat(path.parent).st_mode) == 0o700
+        finally:
+            listener.close()
+
+    def test_response_goes_to_requesting_client(self) -> None:
+        """_write targets the client being served, not stdout."""
+        import socket
+
+        from cairn.ui_rpc_server import _current_client, _SocketClient, _write
+
+        ours, theirs = socket.socketpair()
+        output = StringIO()
+        token = _current_client.set(_SocketClient(ours))
+        try:
+            with patch("sys.stdout", output):
+                _write({"id": 1, "result": "ok"})
+        finally:
+            _current_client.reset(token)
+
+        assert output.getvalue() == ""
+        assert json.loads(theirs.makefile("r").readline()) == {"id": 1, "result": "ok"}
+        ours.close()
+        theirs.close()